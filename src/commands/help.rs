@@ -1,38 +1,65 @@
+use crate::commands::{
+    registry::{CommandArgs, CommandError},
+    servers::CommandResponse,
+};
 use log::*;
-use serenity::framework::standard::macros::help;
-use serenity::framework::standard::CommandError;
-use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, UserId};
 use serenity::prelude::Context;
 
-#[help]
-pub async fn help(ctx: &Context, msg: &Message) -> Result<(), CommandError> {
+pub async fn help(
+    _context: &Context,
+    _channel_id: ChannelId,
+    _user_id: UserId,
+    _args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
     debug!("HELP COMMAND");
-    let _ = msg.reply(
-        (&ctx.cache, ctx.http.as_ref()),
+    Ok(CommandResponse::Reply(
         "Commands (server alias is optional, defaults to channel name): \n\
-         - /add <address:port> <alias>: save the dom6 server address\n\
-         - /add <url for status page.html> <alias>: save the dom6 server address\n\
+         - /add <address:port> [dom5] [anon] <alias>: save the server address, `dom5` for Dominions 5, `anon` hides who plays which nation\n\
+         - /add <url for status page.html> [anon] <alias>: save the dom6 server address\n\
          - /alias <old alias> <new alias>: set a new alias for a server\n\
+         - /claim <alias>: move a game from before games belonged to a server into this server\n\
          - /banish <user_id> <alias>: kick a player from a game, as if they had used !unregister \n\
-         - /list: return a list of the saved server addresses and aliases\n\
+         - /list [all]: return a list of the saved server addresses and aliases, and finished games with `all`\n\
          - /delete <alias>: remove the server address from the list\n\
          - /details <alias>: return a list of the nations and their statuses in the game\n\
-         - /register nation_prefix <alias>: register yourself as a nation in a game\n\
+         - /history <alias>: show who staled, went AI, or was last to submit in previous turns, even after the game finished\n\
+         - /archive [<alias>]: list finished games, or show who won one\n\
+         - /finish <alias>: archive a game that looks over because only one nation is left standing\n\
+         - /register nation_prefix <alias>: register yourself as a nation in a game. Acronyms (TC), nicknames (bogus) and typos are fine\n\
          - /register-id nation_id <alias>: register yourself as a nation in a game using the id\n\
          - /register-custom \"whatever\" <alias>: register yourself with some custom text in a game\n\
                 note that you MUST reregister as the actual nation after uploading but before the game starts\n\
-         - /unregister <alias>: unregister yourself in a game\n\
+         - /unregister <alias>: unregister yourself in a game, or leave its waitlist\n\
+                joining a full lobby puts you on its waitlist, and free slots go to whoever has waited longest\n\
+         - /sub-needed <nation> <alias>: ask for somebody to take over your nation, or (game managers) one that went AI\n\
+         - /subs: list the nations that need a sub\n\
+         - /sub <nation> <alias>: take over a nation that needs a sub\n\
          - /turns: show all of the games you're in and their turn status\n\
-         - /notifications {true, false}: enable/disable turn notifications\n\
-         - /lobby {EA/MA/LA} <num_players> <alias>: create a lobby with no server\n\
+         - /notifications {true, false}: enable/disable turn notifications for all games\n\
+         - /notifications [turn/uploading/reminders/stales/defeats] {true, false, default} <alias>: change notifications for one game\n\
+         - /remind <time before deadline...> <alias>: DM you if you haven't submitted, e.g. /remind 6h 1h\n\
+         - /announce {#channel, off} <alias>: post a summary of each new turn in a channel\n\
+         - /pin <alias>: post the details of a game here and keep them up to date\n\
+         - /manager-role {@role, off}: let a role delete/start/unstart/alias/banish any game (admins only)\n\
+         - /lobby {EA/MA/LA} <num_players> [dom5] [anon] <alias>: create a lobby with no server, `dom5` for Dominions 5, `anon` hides who plays which nation\n\
          - /lobbies: list available lobbies\n\
+         - /draft {signup, random} [hours per pick] <alias>: make the players of an empty lobby take turns to pick nations\n\
+         - /draft {join, start, off} <alias>: sign up for a draft, start it once the lobby is full, or stop drafting\n\
+         - /pick nation_prefix <alias>: pick your nation when it's your turn in a draft\n\
+         - /prefs nation, nation, ... <alias>: rank the nations you'd like in a lobby, to be given one fairly when it fills\n\
+         - /assign <alias>: give everybody who used /prefs in a lobby a nation now\n\
+         - /ban-nation nation, nation, ... <alias>: stop players having some nations in a lobby\n\
+         - /unban-nation nation, nation, ... <alias>: let players have banned nations again\n\
+         - /allow-nations {nation, nation, ..., off} <alias>: only let players have these nations in a lobby\n\
          - /start <address:port> <alias>: register a started server for a lobby game\n\
          - /start <url for status page.html> <alias>: register a started server for a lobby game\n\
          - /{item, spell, unit, site, merc, event} <text>: get dom5inspector search url\n\
          - /help: display this text\n\
          - /describe \"text\" <alias>: add a description to a lobby. Quotes required.\n\
          - /unstart <alias>: turn a game back into a lobby, if you need to change address\n\
-         Source is located at www.github.com/djmcgill/dominions-5-status Contributions welcome!",
-    ).await?;
-    Ok(())
+         Only a game's owner, server admins, or the manager role can use delete, start, unstart, alias, claim, banish, announce, pin, finish, draft, assign, ban-nation, unban-nation and allow-nations\n\
+         Source is located at www.github.com/djmcgill/dominions-5-status Contributions welcome!"
+            .to_owned(),
+    ))
 }
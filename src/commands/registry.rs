@@ -0,0 +1,446 @@
+//! Every command is defined once, as a `BotCommand`, and that one definition drives both
+//! the `!` prefix command and the slash command. The options are typed, so a command gets
+//! its arguments already parsed whichever way it was called.
+
+use crate::commands::servers::CommandResponse;
+use futures::future::BoxFuture;
+use serenity::{
+    all::{CommandDataOption, CommandDataOptionValue, CommandOptionType},
+    builder::{CreateCommand, CreateCommandOption},
+    client::Context,
+    model::id::{ChannelId, UserId},
+};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+pub type CommandError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A command that shouldn't have been used where everybody can see it, e.g. registering for
+/// an anonymous game in a server's channel. The message that ran it gets deleted, and only
+/// whoever ran it is told why.
+#[derive(Debug)]
+pub struct PrivateCommandError(pub String);
+impl fmt::Display for PrivateCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl std::error::Error for PrivateCommandError {}
+
+pub type CommandFn = for<'a> fn(
+    &'a Context,
+    ChannelId,
+    UserId,
+    CommandArgs,
+) -> BoxFuture<'a, Result<CommandResponse, CommandError>>;
+
+pub struct BotCommand {
+    /// The main name then any aliases. Each name gets its own slash command, since Discord
+    /// doesn't have aliases.
+    pub names: &'static [&'static str],
+    pub description: &'static str,
+    /// In the order they're given to the `!` command
+    pub options: &'static [CommandOption],
+    pub run: CommandFn,
+}
+
+impl BotCommand {
+    pub fn slash_commands(&self) -> impl Iterator<Item = CreateCommand> + '_ {
+        // Discord wants the required options before the optional ones
+        let mut options = self.options.iter().collect::<Vec<_>>();
+        options.sort_by_key(|option| !option.required);
+        self.names.iter().map(move |name| {
+            CreateCommand::new(*name)
+                .description(self.description)
+                .set_options(options.iter().map(|option| option.slash_option()).collect())
+        })
+    }
+
+    /// Assigns the words after the command name to the options in order. Optional options
+    /// are skipped over if the next word doesn't fit them.
+    pub fn args_from_words(&self, words: Vec<String>) -> Result<CommandArgs, CommandError> {
+        let mut values = HashMap::new();
+        let mut words = words.into_iter().peekable();
+        for option in self.options {
+            let mut parsed = vec![];
+            while let Some(value) = words.peek().and_then(|word| option.parse_word(word)) {
+                parsed.push(value);
+                words.next();
+                if option.accepts_word.is_none() {
+                    break;
+                }
+            }
+            match parsed.len() {
+                0 if option.required => {
+                    return Err(match words.peek() {
+                        Some(word) => {
+                            format!("Expected {} but got \"{}\"", option.expected(), word)
+                        }
+                        None => format!("Missing {}", option.expected()),
+                    }
+                    .into())
+                }
+                0 => (),
+                1 => {
+                    values.insert(option.name.to_owned(), parsed.remove(0));
+                }
+                _ => {
+                    let joined = parsed
+                        .into_iter()
+                        .filter_map(|value| match value {
+                            ArgValue::String(word) => Some(word),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    values.insert(option.name.to_owned(), ArgValue::String(joined));
+                }
+            }
+        }
+        if words.peek().is_some() {
+            return Err(
+                "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\""
+                    .into(),
+            );
+        }
+        Ok(CommandArgs { values })
+    }
+
+    pub fn args_from_slash_options(
+        &self,
+        data_options: &[CommandDataOption],
+    ) -> Result<CommandArgs, CommandError> {
+        let mut values = HashMap::new();
+        for data_option in data_options {
+            let option = self
+                .options
+                .iter()
+                .find(|option| option.name == data_option.name)
+                .ok_or_else(|| format!("Unexpected option {}", data_option.name))?;
+            let value = match (&data_option.value, option.kind) {
+                // many word options come as one string from a slash command
+                (CommandDataOptionValue::String(text), OptionKind::String)
+                    if option.accepts_word.is_some() =>
+                {
+                    let words = split_words(text);
+                    if words.is_empty()
+                        || words.iter().any(|word| option.parse_word(word).is_none())
+                    {
+                        return Err(
+                            format!("Expected {} but got \"{}\"", option.expected(), text).into(),
+                        );
+                    }
+                    ArgValue::String(words.join(" "))
+                }
+                (CommandDataOptionValue::String(text), OptionKind::String) => {
+                    option.parse_word(text).ok_or_else(|| {
+                        format!("Expected {} but got \"{}\"", option.expected(), text)
+                    })?
+                }
+                (CommandDataOptionValue::Integer(int), OptionKind::Integer) => {
+                    ArgValue::Integer(*int)
+                }
+                (CommandDataOptionValue::User(user_id), OptionKind::User) => {
+                    ArgValue::User(*user_id)
+                }
+                (other, _) => {
+                    return Err(
+                        format!("Expected {} but got {:?}", option.expected(), other).into(),
+                    )
+                }
+            };
+            values.insert(option.name.to_owned(), value);
+        }
+        if let Some(missing) = self
+            .options
+            .iter()
+            .find(|option| option.required && !values.contains_key(option.name))
+        {
+            return Err(format!("Missing {}", missing.expected()).into());
+        }
+        Ok(CommandArgs { values })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    String,
+    Integer,
+    /// A mention or a user ID
+    User,
+}
+
+#[derive(Clone, Copy)]
+pub struct CommandOption {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: OptionKind,
+    pub required: bool,
+    /// (what the slash command shows, the value). If there are any, nothing else is allowed.
+    pub choices: &'static [(&'static str, &'static str)],
+    pub autocomplete: bool,
+    /// For options that can be more than one word, e.g. `!remind 6h 1h`. The words it takes
+    /// are joined back together with spaces.
+    pub accepts_word: Option<fn(&str) -> bool>,
+}
+
+impl CommandOption {
+    pub const fn new(name: &'static str, kind: OptionKind, description: &'static str) -> Self {
+        CommandOption {
+            name,
+            description,
+            kind,
+            required: false,
+            choices: &[],
+            autocomplete: false,
+            accepts_word: None,
+        }
+    }
+
+    pub const fn string(name: &'static str, description: &'static str) -> Self {
+        Self::new(name, OptionKind::String, description)
+    }
+
+    pub const fn integer(name: &'static str, description: &'static str) -> Self {
+        Self::new(name, OptionKind::Integer, description)
+    }
+
+    pub const fn user(name: &'static str, description: &'static str) -> Self {
+        Self::new(name, OptionKind::User, description)
+    }
+
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub const fn choices(mut self, choices: &'static [(&'static str, &'static str)]) -> Self {
+        self.choices = choices;
+        self
+    }
+
+    pub const fn autocomplete(mut self) -> Self {
+        self.autocomplete = true;
+        self
+    }
+
+    pub const fn many_words(mut self, accepts_word: fn(&str) -> bool) -> Self {
+        self.accepts_word = Some(accepts_word);
+        self
+    }
+
+    fn expected(&self) -> String {
+        if self.choices.is_empty() {
+            format!("{} ({})", self.name, self.description)
+        } else {
+            let values = self
+                .choices
+                .iter()
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>();
+            format!("{} (one of {})", self.name, values.join(", "))
+        }
+    }
+
+    fn parse_word(&self, word: &str) -> Option<ArgValue> {
+        if self
+            .accepts_word
+            .is_some_and(|accepts_word| !accepts_word(word))
+        {
+            return None;
+        }
+        match self.kind {
+            OptionKind::String if self.choices.is_empty() => {
+                Some(ArgValue::String(word.to_owned()))
+            }
+            OptionKind::String => self
+                .choices
+                .iter()
+                .find(|(_, value)| value.eq_ignore_ascii_case(word))
+                .map(|(_, value)| ArgValue::String((*value).to_owned())),
+            OptionKind::Integer => i64::from_str(word).ok().map(ArgValue::Integer),
+            OptionKind::User => parse_user(word).map(ArgValue::User),
+        }
+    }
+
+    fn slash_option(&self) -> CreateCommandOption {
+        let kind = match self.kind {
+            OptionKind::String => CommandOptionType::String,
+            OptionKind::Integer => CommandOptionType::Integer,
+            OptionKind::User => CommandOptionType::User,
+        };
+        self.choices.iter().fold(
+            CreateCommandOption::new(kind, self.name, self.description)
+                .required(self.required)
+                .set_autocomplete(self.autocomplete),
+            |option, &(name, value)| option.add_string_choice(name, value),
+        )
+    }
+}
+
+/// `<@123>`, `<@!123>` or just `123`
+fn parse_user(word: &str) -> Option<UserId> {
+    let id = word
+        .strip_prefix("<@")
+        .and_then(|rest| rest.strip_suffix('>'))
+        .map(|rest| rest.trim_start_matches('!'))
+        .unwrap_or(word);
+    u64::from_str(id)
+        .ok()
+        .filter(|&id| id != 0)
+        .map(UserId::new)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgValue {
+    String(String),
+    Integer(i64),
+    User(UserId),
+}
+
+/// The options a command was given, by name. Everything's already been checked against
+/// the command's `CommandOption`s, so the `required_` getters only fail if the command
+/// asks for an option it didn't declare.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandArgs {
+    values: HashMap<String, ArgValue>,
+}
+
+impl CommandArgs {
+    pub fn string(&self, name: &str) -> Option<String> {
+        match self.values.get(name) {
+            Some(ArgValue::String(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.values.get(name) {
+            Some(ArgValue::Integer(int)) => Some(*int),
+            _ => None,
+        }
+    }
+
+    pub fn user(&self, name: &str) -> Option<UserId> {
+        match self.values.get(name) {
+            Some(ArgValue::User(user_id)) => Some(*user_id),
+            _ => None,
+        }
+    }
+
+    pub fn required_string(&self, name: &str) -> Result<String, CommandError> {
+        self.string(name).ok_or_else(|| missing(name))
+    }
+
+    pub fn required_integer(&self, name: &str) -> Result<i64, CommandError> {
+        self.integer(name).ok_or_else(|| missing(name))
+    }
+
+    pub fn required_user(&self, name: &str) -> Result<UserId, CommandError> {
+        self.user(name).ok_or_else(|| missing(name))
+    }
+}
+
+fn missing(name: &str) -> CommandError {
+    format!("Missing {}", name).into()
+}
+
+/// Splits a message into words, keeping anything in "quotes" together
+pub fn split_words(text: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !word.is_empty() || quoted {
+                    words.push(std::mem::take(&mut word));
+                }
+                quoted = false;
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() || quoted {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::FutureExt;
+
+    const NOTIFICATIONS: BotCommand = BotCommand {
+        names: &["notifications"],
+        description: "",
+        options: &[
+            CommandOption::string("kind", "")
+                .choices(&[("new turn", "turn"), ("stales", "stales")]),
+            CommandOption::string("enabled", "")
+                .required()
+                .choices(&[("true", "true"), ("false", "false")]),
+            CommandOption::string("name", ""),
+        ],
+        run: |_, _, _, _| async { Ok(CommandResponse::Reply(String::new())) }.boxed(),
+    };
+
+    fn words(text: &str) -> Vec<String> {
+        split_words(text)
+    }
+
+    #[test]
+    fn splits_words_keeping_quotes_together() {
+        assert_eq!(
+            vec!["EA T'ien Ch'i".to_owned(), "mygame".to_owned()],
+            words("  \"EA T'ien Ch'i\"   mygame ")
+        );
+        assert_eq!(vec!["".to_owned()], words("\"\""));
+    }
+
+    #[test]
+    fn optional_options_are_skipped_when_the_word_does_not_fit() {
+        let args = NOTIFICATIONS.args_from_words(words("TRUE mygame")).unwrap();
+        assert_eq!(None, args.string("kind"));
+        assert_eq!(Some("true".to_owned()), args.string("enabled"));
+        assert_eq!(Some("mygame".to_owned()), args.string("name"));
+
+        let args = NOTIFICATIONS.args_from_words(words("turn false")).unwrap();
+        assert_eq!(Some("turn".to_owned()), args.string("kind"));
+        assert_eq!(None, args.string("name"));
+
+        assert!(NOTIFICATIONS.args_from_words(words("maybe")).is_err());
+        assert!(NOTIFICATIONS
+            .args_from_words(words("true mygame extra"))
+            .is_err());
+    }
+
+    #[test]
+    fn many_word_options_take_every_word_they_accept() {
+        const REMIND: BotCommand = BotCommand {
+            options: &[
+                CommandOption::string("before_deadline", "")
+                    .required()
+                    .many_words(|word| word.ends_with('h')),
+                CommandOption::string("name", ""),
+            ],
+            ..NOTIFICATIONS
+        };
+        let args = REMIND.args_from_words(words("6h 1h mygame")).unwrap();
+        assert_eq!(Some("6h 1h".to_owned()), args.string("before_deadline"));
+        assert_eq!(Some("mygame".to_owned()), args.string("name"));
+    }
+
+    #[test]
+    fn users_can_be_mentions_or_ids() {
+        assert_eq!(Some(UserId::new(123)), parse_user("<@123>"));
+        assert_eq!(Some(UserId::new(123)), parse_user("<@!123>"));
+        assert_eq!(Some(UserId::new(123)), parse_user("123"));
+        assert_eq!(None, parse_user("@someone"));
+    }
+}
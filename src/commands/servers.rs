@@ -1,159 +1,438 @@
 pub mod add_server;
 pub mod alias;
+pub mod announce;
+pub mod archive;
+pub mod autocomplete;
 pub mod describe;
 pub mod details;
+pub mod draft;
+pub mod history;
 pub mod kick;
 pub mod list_servers;
 pub mod lobbies;
 pub mod lobby;
 pub mod notifications;
+pub mod permissions;
+pub mod pin;
+pub mod poll_schedule;
+pub mod prefs;
 pub mod register_player;
+pub mod remind;
 pub mod remove_server;
+pub mod restrictions;
 pub mod start;
+pub mod subs;
 pub mod turn_check;
 pub mod turns;
 pub mod unregister_player;
 pub mod unstart;
+pub mod waitlist;
 
+use crate::{
+    commands::{
+        help,
+        registry::{BotCommand, CommandArgs, CommandError, CommandOption},
+    },
+    db::{DbConnection, DbConnectionKey},
+    model::game_server::GameAlias,
+};
 use chrono::{DateTime, Utc};
-use serenity::all::CreateMessage;
+use futures::FutureExt;
 use serenity::builder::CreateEmbed;
-use serenity::framework::standard::CommandError;
-use serenity::model::id::{ChannelId, UserId};
-use serenity::{
-    framework::standard::{macros::*, Args, CommandResult},
-    model::channel::Message,
-    prelude::*,
-};
-use std::future::Future;
-
-// FIXME: buckets
-#[group]
-#[commands(
-    server_add,
-    server_list,
-    server_delete,
-    server_details,
-    server_register,
-    server_register_id,
-    server_register_custom,
-    server_unregister,
-    server_turns,
-    server_lobby,
-    server_notifications,
-    server_start,
-    server_lobbies,
-    server_describe,
-    server_unstart,
-    server_set_alias,
-    server_kick
-)]
-struct Server;
-
-#[command]
-#[aliases("add")]
-async fn server_add(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    // okay this is a bit more awkward than I thought. But all new servers with the `add` command are for dom6
-    bang_command_wrap(ctx, msg, args, |c, ch, u, a| {
-        add_server::add_server(c, ch, u, 6, a)
-    })
-    .await
-}
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::prelude::*;
 
-#[command]
-#[aliases("list")]
-async fn server_list(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, list_servers::list_servers).await
-}
+const GAME_NAME: CommandOption = CommandOption::string(
+    "name",
+    "The game's bot name. If not present, will use the channel name.",
+)
+.autocomplete();
 
-#[command]
-#[aliases("delete")]
-async fn server_delete(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, remove_server::remove_server).await
-}
+const NATION_NAME: CommandOption = CommandOption::string(
+    "nation_name_prefix",
+    "The start (or all) of a nation name e.g. `tien`, `eatie`, `\"T'ien Ch'i\"`, or `\"EA T'ien Ch'i\"",
+)
+.required()
+.autocomplete();
 
-#[command]
-#[aliases("details", "deets")]
-async fn server_details(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, details::details).await
-}
+const NATION_ID: CommandOption =
+    CommandOption::integer("nation_id", "The ID of the nation, e.g. `10` for EA TC").required();
 
-#[command]
-#[aliases("register", "join")]
-async fn server_register(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, register_player::register_player).await
-}
+const NATION_TEXT: CommandOption =
+    CommandOption::string("nation_text", "Some text to represent your chosen nation").required();
 
-#[command]
-#[aliases("register-id", "join-id")]
-async fn server_register_id(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, register_player::register_player_id).await
-}
-
-#[command]
-#[aliases("register-custom", "join-custom")]
-async fn server_register_custom(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, register_player::register_player_custom).await
-}
+const SUB_NATION: CommandOption =
+    CommandOption::string("nation", "The nation's name or ID, e.g. `tien` or `10`").required();
 
-#[command]
-#[aliases("unregister")]
-async fn server_unregister(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, unregister_player::unregister_player).await
-}
+const DOM_VERSION: CommandOption = CommandOption::string(
+    "version",
+    "Which Dominions the game is for. Dominions 6 if not given.",
+)
+.choices(&[("Dominions 6", "dom6"), ("Dominions 5", "dom5")]);
 
-#[command]
-#[aliases("turns")]
-async fn server_turns(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, turns::turns).await
-}
+const ANONYMOUS: CommandOption = CommandOption::string(
+    "anonymous",
+    "`anon` to keep who is playing which nation secret. Players then register by DMing the bot.",
+)
+.choices(&[("anon", "anon")]);
 
-#[command]
-#[aliases("lobby")]
-async fn server_lobby(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, lobby::lobby).await
-}
-
-#[command]
-#[aliases("notifications")]
-async fn server_notifications(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, notifications::notifications).await
-}
-
-#[command]
-#[aliases("start")]
-async fn server_start(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, start::start).await
-}
-
-#[command]
-#[aliases("lobbies")]
-async fn server_lobbies(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, lobbies::lobbies).await
-}
-
-#[command]
-#[aliases("describe")]
-async fn server_describe(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, describe::describe).await
-}
-
-#[command]
-#[aliases("unstart")]
-async fn server_unstart(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, unstart::unstart).await
-}
-
-#[command]
-#[aliases("alias")]
-async fn server_set_alias(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, alias::server_set_alias).await
-}
+/// Every command, for both `!` and slash commands
+pub static COMMANDS: &[BotCommand] = &[
+    BotCommand {
+        names: &["add"],
+        description: "Add a running game to the bot",
+        options: &[
+            CommandOption::string(
+                "address_and_port",
+                "The address and port, separated by a colon, to connect to the game",
+            )
+            .required(),
+            DOM_VERSION,
+            ANONYMOUS,
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| add_server::add_server(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["list"],
+        description: "List all games and lobbies",
+        options: &[CommandOption::string("show", "`all` to also list finished games")
+            .choices(&[("all", "all")])],
+        run: |c, ch, u, a| list_servers::list_servers(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["delete"],
+        description: "Remove a game or lobby from the bot",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| remove_server::remove_server(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["details", "deets"],
+        description: "Display the details of a game or lobby",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| details::details(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["register", "join"],
+        description: "Register yourself as a nation in a game or lobby",
+        options: &[NATION_NAME, GAME_NAME],
+        run: |c, ch, u, a| register_player::register_player(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["register-id", "join-id"],
+        description: "Register yourself as a nation using the ID in a game or lobby",
+        options: &[NATION_ID, GAME_NAME],
+        run: |c, ch, u, a| register_player::register_player_id(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["register-custom", "join-custom"],
+        description:
+            "Register yourself as some text in a mod game or lobby. You must reregister after uploading.",
+        options: &[NATION_TEXT, GAME_NAME],
+        run: |c, ch, u, a| register_player::register_player_custom(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["unregister"],
+        description: "Unregister yourself from all nations in a game or lobby",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| unregister_player::unregister_player(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["turns"],
+        description: "Have the bot PM you with the details of all games you're currently in.",
+        options: &[],
+        run: |c, ch, u, a| turns::turns(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["notifications"],
+        description: "Disable, or re-enable, the bot pinging you for turns.",
+        options: &[
+            CommandOption::string("kind", "Only change one kind of notification. Needs a game.")
+                .choices(&[
+                    ("new turn", "turn"),
+                    ("uploading", "uploading"),
+                    ("reminders", "reminders"),
+                    ("stales", "stales"),
+                    ("defeats", "defeats"),
+                ]),
+            CommandOption::string(
+                "enabled",
+                "If true, then notifications are sent. `default` goes back to your usual setting.",
+            )
+            .required()
+            .choices(&[("true", "true"), ("false", "false"), ("default", "default")]),
+            CommandOption::string(
+                "name",
+                "Only change notifications for this game. If not present, changes them for all games.",
+            )
+            .autocomplete(),
+        ],
+        run: |c, ch, u, a| notifications::notifications(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["lobby"],
+        description: "Create a lobby with no server for players to join",
+        options: &[
+            CommandOption::string("era", "The era of the game (EA/MA/LA)")
+                .required()
+                .choices(&[("EA", "EA"), ("MA", "MA"), ("LA", "LA")]),
+            CommandOption::integer("number_of_players", "The number of players").required(),
+            DOM_VERSION,
+            ANONYMOUS,
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| lobby::lobby(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["draft"],
+        description: "Have a lobby's players take turns to pick their nations.",
+        options: &[
+            CommandOption::string(
+                "action",
+                "`signup`/`random` sets the pick order, `join` signs up, `start` once full, or `off`",
+            )
+            .required()
+            .choices(&[
+                ("signup", "signup"),
+                ("random", "random"),
+                ("join", "join"),
+                ("start", "start"),
+                ("off", "off"),
+            ]),
+            CommandOption::integer(
+                "pick_hours",
+                "How many hours each player has to pick. 12 if not given.",
+            ),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| draft::draft(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["pick"],
+        description: "Pick your nation when it's your turn in a draft.",
+        options: &[NATION_NAME, GAME_NAME],
+        run: |c, ch, u, a| draft::pick(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["prefs"],
+        description: "Rank the nations you'd like in a lobby, best first, to be given one fairly.",
+        options: &[
+            CommandOption::string(
+                "nations",
+                "Up to 10 nations, best first and separated by commas e.g. `tien, ermor, pythium`",
+            )
+            .required()
+            .many_words(register_player::is_nation_list_word),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| prefs::prefs(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["assign"],
+        description: "Give everybody who ranked nations in a lobby the best nations for them overall.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| prefs::assign(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["ban-nation"],
+        description: "Stop players from having some nations in a lobby.",
+        options: &[
+            CommandOption::string(
+                "nations",
+                "The nations to ban, separated by commas e.g. `ea ulm, pythium`",
+            )
+            .required()
+            .many_words(register_player::is_nation_list_word),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| restrictions::ban_nation(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["unban-nation"],
+        description: "Let players have nations that were banned in a lobby again.",
+        options: &[
+            CommandOption::string(
+                "nations",
+                "The nations to unban, separated by commas e.g. `ea ulm, pythium`",
+            )
+            .required()
+            .many_words(register_player::is_nation_list_word),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| restrictions::unban_nation(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["allow-nations"],
+        description: "Only let players have the given nations in a lobby.",
+        options: &[
+            CommandOption::string(
+                "nations",
+                "The only nations allowed, separated by commas e.g. `tien, ermor`, or `off`",
+            )
+            .required()
+            .many_words(restrictions::is_allow_list_word),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| restrictions::allow_nations(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["lobbies"],
+        description: "List available lobbies",
+        options: &[],
+        run: |c, ch, u, a| lobbies::lobbies(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["start"],
+        description: "Start a lobby with an address, after starting it on the server",
+        options: &[
+            CommandOption::string(
+                "address",
+                "Either \"web.site:1234\" or \"www.illwinter.com/mygame.html\"",
+            )
+            .required(),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| start::start(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["describe"],
+        description: "Add a description to a lobby. Quotemarks required.",
+        options: &[
+            CommandOption::string(
+                "description",
+                "The description to add to the game. Quotemarks required.",
+            )
+            .required(),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| describe::describe(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["unstart"],
+        description: "Turn a game back into a lobby by forgetting its address.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| unstart::unstart(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["alias"],
+        description: "Set a new alias for a server.",
+        options: &[
+            CommandOption::string("alias", "The current alias for the server")
+                .required()
+                .autocomplete(),
+            CommandOption::string(
+                "new_alias",
+                "The new alias for the server. If not present, will use the channel name.",
+            ),
+        ],
+        run: |c, ch, u, a| alias::server_set_alias(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["claim"],
+        description: "Move a game from before games belonged to a server into this server.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| alias::claim(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["banish", "kick"],
+        description: "Kick a user from a game.",
+        options: &[
+            CommandOption::user("user", "The user to kick from this game").required(),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| kick::kick_player(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["sub-needed"],
+        description: "Ask for somebody new to play a nation, e.g. when its player is dropping out.",
+        options: &[SUB_NATION, GAME_NAME],
+        run: |c, ch, u, a| subs::sub_needed(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["subs"],
+        description: "List the nations in every game that need a sub.",
+        options: &[],
+        run: |c, ch, u, a| subs::subs(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["sub"],
+        description: "Take over a nation that needs a sub.",
+        options: &[SUB_NATION, GAME_NAME],
+        run: |c, ch, u, a| subs::sub(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["history"],
+        description: "Show who staled, went AI, or was last to submit in previous turns.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| history::history(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["remind"],
+        description: "Get a DM before the deadline if you haven't submitted your turn.",
+        options: &[
+            CommandOption::string(
+                "before_deadline",
+                "How long before the deadline, e.g. `6h 1h`, or `off` to remove your reminders",
+            )
+            .required()
+            .many_words(remind::is_reminder_word),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| remind::remind(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["announce"],
+        description: "Post a summary of each new turn in a channel.",
+        options: &[
+            CommandOption::string(
+                "channel",
+                "The channel to post in e.g. #general, or `off` to stop announcing",
+            )
+            .required(),
+            GAME_NAME,
+        ],
+        run: |c, ch, u, a| announce::announce(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["pin"],
+        description: "Post the game's details in this channel and keep them up to date.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| pin::pin(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["manager-role"],
+        description:
+            "Let people with a role delete, start, rename and banish from any game. Admins only.",
+        options: &[CommandOption::string("role", "The role e.g. @gamemasters, or `off`").required()],
+        run: |c, ch, u, a| permissions::manager_role(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["archive"],
+        description: "List finished games, or show how one ended.",
+        options: &[CommandOption::string(
+            "name",
+            "The finished game's bot name. If not present, lists every finished game.",
+        )],
+        run: |c, ch, u, a| archive::archive(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["finish"],
+        description: "Archive a game that looks like it's over, once one nation is left standing.",
+        options: &[GAME_NAME],
+        run: |c, ch, u, a| archive::finish(c, ch, u, a).boxed(),
+    },
+    BotCommand {
+        names: &["help"],
+        description: "List the bot's commands.",
+        options: &[],
+        run: |c, ch, u, a| help::help(c, ch, u, a).boxed(),
+    },
+];
 
-#[command]
-#[aliases("kick", "banish")]
-async fn server_kick(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    bang_command_wrap(ctx, msg, args, kick::kick_player).await
+pub fn find_command(name: &str) -> Option<&'static BotCommand> {
+    COMMANDS
+        .iter()
+        .find(|command| command.names.contains(&name))
 }
 
 pub enum CommandResponse {
@@ -161,13 +440,39 @@ pub enum CommandResponse {
     Reply(String),
 }
 
+/// Which Dominions a new game is for, from the `version` option
+fn dom_version_from_args(args: &CommandArgs) -> u8 {
+    match args.string("version").as_deref() {
+        Some("dom5") => 5,
+        _ => 6,
+    }
+}
+
+/// The game the command is about, named either by the command's option or the channel name.
+/// See `DbConnection::resolve_alias` for how that picks between servers.
 async fn alias_from_arg_or_channel_name(
     ctx: &Context,
     channel_id: ChannelId,
-    args: &mut Args,
+    option_name: Option<String>,
+) -> Result<GameAlias, CommandError> {
+    let name = name_from_arg_or_channel_name(ctx, channel_id, option_name).await?;
+    let option_guild_id = guild_for_channel(ctx, channel_id).await?;
+    let db_conn = {
+        let data = ctx.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    Ok(db_conn.resolve_alias(&name, option_guild_id)?)
+}
+
+async fn name_from_arg_or_channel_name(
+    ctx: &Context,
+    channel_id: ChannelId,
+    option_name: Option<String>,
 ) -> Result<String, CommandError> {
-    let result_alias = if !args.is_empty() {
-        args.single_quoted::<String>().ok()
+    let result_alias = if option_name.is_some() {
+        option_name
     } else {
         channel_id
             .to_channel((&ctx.cache, ctx.http.as_ref()))
@@ -189,34 +494,23 @@ async fn alias_from_arg_or_channel_name(
         })
 }
 
-async fn bang_command_wrap<'a, F, Fut>(
-    context: &'a Context,
-    message: &'a Message,
-    args: Args,
-    f: F,
-) -> Result<(), CommandError>
-where
-    F: FnOnce(&'a Context, ChannelId, UserId, Args) -> Fut,
-    Fut: Future<Output = Result<CommandResponse, CommandError>> + 'a,
-{
-    let channel_id = message.channel_id;
-    let user_id = message.author.id;
-    let command_response = f(context, channel_id, user_id, args).await?;
-
-    match command_response {
-        CommandResponse::Reply(reply) => {
-            message
-                .reply((&context.cache, context.http.as_ref()), reply)
-                .await?;
-        }
-        CommandResponse::Embed(embed) => {
-            message
-                .channel_id
-                .send_message(&context.http, CreateMessage::default().embed(*embed))
-                .await?;
-        }
-    }
-    Ok(())
+async fn db_connection(ctx: &Context) -> Result<DbConnection, CommandError> {
+    let data = ctx.data.read().await;
+    Ok(data
+        .get::<DbConnectionKey>()
+        .ok_or("No DbConnection was created on startup. This is a bug.")?
+        .clone())
+}
+
+/// `None` for DMs
+async fn guild_for_channel(
+    ctx: &Context,
+    channel_id: ChannelId,
+) -> Result<Option<GuildId>, CommandError> {
+    let channel = channel_id
+        .to_channel((&ctx.cache, ctx.http.as_ref()))
+        .await?;
+    Ok(channel.guild().map(|guild_channel| guild_channel.guild_id))
 }
 
 pub fn discord_date_format(deadline: DateTime<Utc>) -> String {
@@ -231,3 +525,48 @@ pub fn discord_date_format(deadline: DateTime<Utc>) -> String {
         deadline.timestamp_millis() / 1000
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn commands_fit_discords_rules() {
+        let mut names = HashSet::new();
+        for command in COMMANDS {
+            assert!(command.description.len() <= 100, "{:?}", command.names);
+            for name in command.names {
+                assert!(names.insert(*name), "{} is used twice", name);
+                assert!(name.len() <= 32 && *name == name.to_lowercase());
+            }
+            for option in command.options {
+                assert!(option.description.len() <= 100, "{}", option.name);
+                assert!(option.name.len() <= 32 && option.name == option.name.to_lowercase());
+            }
+        }
+    }
+
+    #[test]
+    fn new_games_can_be_for_dom5() {
+        let add = find_command("add").unwrap();
+        let words = |words: &[&str]| words.iter().map(|word| word.to_string()).collect();
+
+        let args = add
+            .args_from_words(words(&["example.com:1234", "dom5", "anon", "oldgame"]))
+            .unwrap();
+        assert_eq!(5, dom_version_from_args(&args));
+        assert!(args.string("anonymous").is_some());
+        assert_eq!(Some("oldgame".to_owned()), args.string("name"));
+
+        let args = add
+            .args_from_words(words(&["example.com:1234", "newgame"]))
+            .unwrap();
+        assert_eq!(6, dom_version_from_args(&args));
+        assert_eq!(Some("newgame".to_owned()), args.string("name"));
+
+        let lobby = find_command("lobby").unwrap();
+        let args = lobby.args_from_words(words(&["EA", "4", "DOM5"])).unwrap();
+        assert_eq!(5, dom_version_from_args(&args));
+    }
+}
@@ -1,10 +1,12 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::commands::servers::CommandResponse;
 use crate::{
-    commands::servers::alias_from_arg_or_channel_name,
+    commands::servers::{alias_from_arg_or_channel_name, dom_version_from_args},
     db::{DbConnection, DbConnectionKey},
     model::{
-        game_server::{GameServer, GameServerState, StartedState},
+        game_server::{GameAlias, GameServer, GameServerState, StartedState},
         game_state::CacheEntry,
+        nation::GameNationIdentifier,
     },
     server::get_game_data_async,
     snek::snek_details_async,
@@ -13,7 +15,6 @@ use crate::{
 use chrono::Utc;
 use log::*;
 use serenity::{
-    framework::standard::{Args, CommandError},
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
@@ -21,16 +22,25 @@ use std::sync::Arc;
 
 async fn add_server_helper(
     server_address: &str,
-    game_alias: &str,
+    game_alias: &GameAlias,
     dom_version: u8,
+    anonymous: bool,
+    owner: UserId,
     db_connection: DbConnection,
     write_handle_mutex: DetailsCacheHandle,
-) -> Result<(), CommandError> {
+) -> Result<bool, CommandError> {
     let game_data = get_game_data_async(server_address, dom_version).await?;
+    // the server doesn't say which version it is, but dom5 nation ids mostly aren't dom6 ones
+    let has_unknown_nations = game_data
+        .nations
+        .iter()
+        .flatten()
+        .any(|nation| matches!(nation.identifier, GameNationIdentifier::CustomId(_)));
     let option_snek_state = snek_details_async(server_address).await?;
     let server = GameServer {
         dom_version,
-        alias: game_alias.to_string(),
+        anonymous,
+        alias: game_alias.clone(),
         state: GameServerState::StartedState(
             StartedState {
                 address: server_address.to_string(),
@@ -42,7 +52,7 @@ async fn add_server_helper(
 
     db_connection.insert_game_server(&server).map_err(|e| {
         if e.to_string()
-            .contains("UNIQUE constraint failed: game_servers.alias")
+            .contains("UNIQUE constraint failed: game_servers.guild_id, game_servers.alias")
         {
             CommandError::from(format!(
                 "A game called '{}' already exists, if you are starting a lobby use !start",
@@ -52,6 +62,7 @@ async fn add_server_helper(
             CommandError::from(e)
         }
     })?;
+    db_connection.set_game_owner(game_alias, owner)?;
 
     let cache_entry = CacheEntry {
         game_data,
@@ -61,7 +72,7 @@ async fn add_server_helper(
     match guard.get_mut::<DetailsCacheKey>() {
         Some(write_handle) => {
             write_handle.insert(
-                game_alias.to_owned(),
+                game_alias.clone(),
                 Box::new((Utc::now(), Some(cache_entry))),
             );
         }
@@ -70,26 +81,21 @@ async fn add_server_helper(
         }
     }
 
-    Ok(())
+    Ok(has_unknown_nations)
 }
 
 pub async fn add_server(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    dom_version: u8,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     info!("Adding server for {} with args {:?}", user_id, args);
-    let server_address = args.single_quoted::<String>()?;
-
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let server_address = args.required_string("address_and_port")?;
+    let dom_version = dom_version_from_args(&args);
+    let anonymous = args.string("anonymous").is_some();
 
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
 
     let write_handle_mutex = DetailsCacheHandle(Arc::clone(&context.data));
     let db_connection = {
@@ -98,14 +104,26 @@ pub async fn add_server(
             .ok_or("No DbConnection was created on startup. This is a bug.")?
             .clone()
     };
-    add_server_helper(
+    let has_unknown_nations = add_server_helper(
         &server_address,
         &alias,
         dom_version,
+        anonymous,
+        user_id,
         db_connection,
         write_handle_mutex,
     )
     .await?;
-    let text = format!("Successfully inserted with alias {}", alias);
+    let mut text = if anonymous {
+        format!("Successfully inserted anonymous game with alias {}", alias)
+    } else {
+        format!("Successfully inserted with alias {}", alias)
+    };
+    if has_unknown_nations && dom_version == 6 {
+        text.push_str(&format!(
+            "\nSome nations weren't recognised. That's fine for mods, but if this is a Dominions 5 game then `!delete {}` and add it again with `dom5`.",
+            alias
+        ));
+    }
     Ok(CommandResponse::Reply(text))
 }
@@ -1,10 +1,14 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::{
-    commands::servers::{alias_from_arg_or_channel_name, CommandResponse},
+    commands::servers::{
+        alias_from_arg_or_channel_name, guild_for_channel, name_from_arg_or_channel_name,
+        permissions::check_can_manage_game, CommandResponse,
+    },
     db::DbConnectionKey,
+    model::game_server::GameAlias,
     DetailsCacheKey,
 };
 use serenity::{
-    framework::standard::{Args, CommandError},
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
@@ -12,8 +16,8 @@ use serenity::{
 pub async fn server_set_alias(
     context: &Context,
     channel_id: ChannelId,
-    _user_id: UserId,
-    mut args: Args,
+    user_id: UserId,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     let db_conn = {
         let data = context.data.read().await;
@@ -22,13 +26,16 @@ pub async fn server_set_alias(
             .clone()
     };
 
-    let old_alias = args.single_quoted::<String>()?;
-    let new_alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from("Too many arguments."));
-    }
+    let old_name = args.required_string("alias")?.to_lowercase();
+    let new_name =
+        name_from_arg_or_channel_name(context, channel_id, args.string("new_alias")).await?;
+    let option_guild_id = guild_for_channel(context, channel_id).await?;
+    let old_alias = db_conn.resolve_alias(&old_name, option_guild_id)?;
+    // the game stays in the server it was in
+    let new_alias = GameAlias::new(old_alias.guild_id, &new_name);
 
-    db_conn.update_lobby_with_alias(&old_alias, &new_alias)?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &old_alias).await?;
+    db_conn.update_lobby_with_alias(&old_alias, &new_alias.name)?;
 
     let reply = format!("Updated alias from {} to {}", old_alias, new_alias,);
 
@@ -49,3 +56,52 @@ pub async fn server_set_alias(
 
     Ok(CommandResponse::Reply(reply))
 }
+
+/// Moves a game from before games were per-server into the server this is used in, so that
+/// its admins and manager role can manage it and it stops showing up in every other server.
+pub async fn claim(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+
+    let guild_id = guild_for_channel(context, channel_id)
+        .await?
+        .ok_or("Games can only be claimed from a server, not a DM")?;
+    let old_alias =
+        alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    if old_alias.guild_id.is_some() {
+        return Err(CommandError::from(format!(
+            "{} already belongs to a server",
+            old_alias
+        )));
+    }
+
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &old_alias).await?;
+    let new_alias = db_conn
+        .move_game_to_guild(&old_alias, guild_id)
+        .map_err(|_| format!("This server already has a game called {}", old_alias.name))?;
+
+    {
+        let mut data = context.data.write().await;
+        let cache_handle = data
+            .get_mut::<DetailsCacheKey>()
+            .ok_or("No DetailsCache was created on startup. This is a bug.")?;
+
+        if let Some(old_entry) = cache_handle.remove(&old_alias) {
+            cache_handle.insert(new_alias.clone(), old_entry);
+        }
+    }
+
+    Ok(CommandResponse::Reply(format!(
+        "{} now belongs to this server",
+        new_alias
+    )))
+}
@@ -0,0 +1,93 @@
+use super::{
+    alias_from_arg_or_channel_name, guild_for_channel, permissions::check_can_manage_game,
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::{DbConnection, DbConnectionKey},
+    model::game_server::GameAlias,
+};
+use serenity::{
+    model::id::{ChannelId, UserId},
+    prelude::Context,
+    utils::parse_channel_mention,
+};
+use std::str::FromStr;
+
+/// Accepts either a #channel mention or the raw channel id
+fn parse_announce_channel(text: &str) -> Option<ChannelId> {
+    parse_channel_mention(text).or_else(|| {
+        u64::from_str(text)
+            .ok()
+            .filter(|&id| id != 0)
+            .map(ChannelId::new)
+    })
+}
+
+fn announce_helper(
+    db_conn: DbConnection,
+    alias: &GameAlias,
+    option_channel_id: Option<ChannelId>,
+) -> Result<String, CommandError> {
+    db_conn.set_announce_channel(alias, option_channel_id)?;
+    match option_channel_id {
+        Some(channel_id) => Ok(format!(
+            "New turns in {} will be announced in <#{}>",
+            alias,
+            channel_id.get()
+        )),
+        None => Ok(format!("Stopped announcing new turns in {}", alias)),
+    }
+}
+
+pub async fn announce(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let option_channel_id = match args.required_string("channel")?.as_str() {
+        "off" => None,
+        text => Some(
+            parse_announce_channel(text)
+                .ok_or("Expected a channel to announce in, e.g. `!announce #general`, or `off`")?,
+        ),
+    };
+
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    if let (Some(announce_channel_id), Some(guild_id)) = (option_channel_id, alias.guild_id) {
+        if guild_for_channel(context, announce_channel_id).await? != Some(guild_id) {
+            return Err(format!(
+                "<#{}> isn't in the server that {} belongs to",
+                announce_channel_id.get(),
+                alias
+            )
+            .into());
+        }
+    }
+    let reply = announce_helper(db_conn, &alias, option_channel_id)?;
+    Ok(CommandResponse::Reply(reply))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_channels() {
+        assert_eq!(
+            Some(ChannelId::new(1234)),
+            parse_announce_channel("<#1234>")
+        );
+        assert_eq!(Some(ChannelId::new(1234)), parse_announce_channel("1234"));
+        assert_eq!(None, parse_announce_channel("0"));
+        assert_eq!(None, parse_announce_channel("general"));
+    }
+}
@@ -0,0 +1,186 @@
+use super::{
+    alias_from_arg_or_channel_name, db_connection, guild_for_channel,
+    name_from_arg_or_channel_name, permissions::check_can_manage_game, turn_check::archive_game,
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::{DbConnection, DbConnectionKey},
+    model::{
+        archived_game::ArchivedGame,
+        game_server::{GameAlias, GameServerState},
+    },
+    DetailsCacheHandle,
+};
+use serenity::{
+    builder::CreateEmbed,
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        mention::Mention,
+    },
+    prelude::Context,
+};
+use std::sync::Arc;
+
+const ARCHIVE_LIST_LENGTH: usize = 20;
+
+/// The most recently finished game with this alias that can be seen from here
+pub fn find_archived_game(
+    db_conn: &DbConnection,
+    name: &str,
+    option_guild_id: Option<GuildId>,
+) -> Result<ArchivedGame, CommandError> {
+    db_conn
+        .archived_games()?
+        .into_iter()
+        .filter(|archived_game| archived_game.alias.is_visible_from(option_guild_id))
+        .find(|archived_game| archived_game.alias.name == name)
+        .ok_or_else(|| {
+            CommandError::from(format!("Could not find a finished game called {}", name))
+        })
+}
+
+fn winner_names(archived_game: &ArchivedGame) -> String {
+    let winners = archived_game
+        .winners()
+        .map(|nation| nation.identifier.name(None))
+        .collect::<Vec<_>>();
+    if winners.is_empty() {
+        "Nobody".to_owned()
+    } else {
+        winners.join(", ")
+    }
+}
+
+fn archive_list_helper(
+    db_conn: &DbConnection,
+    option_guild_id: Option<GuildId>,
+) -> Result<CreateEmbed, CommandError> {
+    let archived_games = db_conn
+        .archived_games()?
+        .into_iter()
+        .filter(|archived_game| archived_game.alias.is_visible_from(option_guild_id))
+        .take(ARCHIVE_LIST_LENGTH)
+        .collect::<Vec<_>>();
+    if archived_games.is_empty() {
+        return Ok(CreateEmbed::default().title("NO FINISHED GAMES"));
+    }
+
+    let mut aliases = String::new();
+    let mut finished = String::new();
+    let mut winners = String::new();
+    for archived_game in archived_games {
+        aliases.push_str(&format!("{}\n", archived_game.alias));
+        finished.push_str(&format!(
+            "turn {}, <t:{}:d>\n",
+            archived_game.final_turn,
+            archived_game.finished_at.timestamp()
+        ));
+        winners.push_str(&format!("{}\n", winner_names(&archived_game)));
+    }
+    Ok(CreateEmbed::default()
+        .title("Finished games:")
+        .field("Alias", aliases, true)
+        .field("Finished", finished, true)
+        .field("Winners", winners, true))
+}
+
+fn archived_game_embed(archived_game: &ArchivedGame) -> CreateEmbed {
+    let anon_game = archived_game.anonymous;
+    let nations_text = archived_game
+        .nations
+        .iter()
+        .map(|nation| {
+            let player = match nation.player {
+                Some(user_id) if !anon_game => format!(" ({})", Mention::from(user_id)),
+                _ => String::new(),
+            };
+            let result = if nation.won {
+                "won"
+            } else {
+                nation.status.show()
+            };
+            format!("{}{}: {}", nation.identifier.name(None), player, result)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    CreateEmbed::default()
+        .title(format!(
+            "{} ({}): finished on turn {}, <t:{}:d>",
+            archived_game.game_name,
+            archived_game.address,
+            archived_game.final_turn,
+            archived_game.finished_at.timestamp()
+        ))
+        .field("Winners", winner_names(archived_game), false)
+        .field(
+            "Nations",
+            if nations_text.is_empty() {
+                "None!".to_owned()
+            } else {
+                nations_text
+            },
+            false,
+        )
+}
+
+pub async fn archive(
+    context: &Context,
+    channel_id: ChannelId,
+    _user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let option_guild_id = guild_for_channel(context, channel_id).await?;
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    let Some(name) = args.string("name") else {
+        let embed = archive_list_helper(&db_conn, option_guild_id)?;
+        return Ok(CommandResponse::Embed(Box::new(embed)));
+    };
+    let name = name_from_arg_or_channel_name(context, channel_id, Some(name)).await?;
+    let archived_game = find_archived_game(&db_conn, &name, option_guild_id)?;
+    Ok(CommandResponse::Embed(Box::new(archived_game_embed(
+        &archived_game,
+    ))))
+}
+
+/// Archives a direct connect game once it looks like it's over. Their servers never say so
+/// themselves, so we wait for the owner to agree.
+async fn finish_helper(
+    db_conn: &DbConnection,
+    details_handle: &DetailsCacheHandle,
+    alias: &GameAlias,
+) -> Result<String, CommandError> {
+    if let GameServerState::Lobby(_) = db_conn.game_for_alias(alias)?.state {
+        return Err(format!("{} hasn't started yet", alias).into());
+    }
+    let cache_entry = details_handle.get_clone(alias).await.map_err(|_| {
+        CommandError::from(format!("I haven't heard from the server for {} yet", alias))
+    })?;
+    if !cache_entry.game_data.looks_finished() {
+        return Err(format!(
+            "{} still has more than one nation left standing, so it isn't over yet",
+            alias
+        )
+        .into());
+    }
+    Ok(archive_game(alias, db_conn, details_handle, &cache_entry.game_data).await?)
+}
+
+pub async fn finish(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    let details_handle = DetailsCacheHandle(Arc::clone(&context.data));
+    let text = finish_helper(&db_conn, &details_handle, &alias).await?;
+    Ok(CommandResponse::Reply(text))
+}
@@ -0,0 +1,306 @@
+//! Suggestions for slash command options while people are still typing them
+
+use super::{db_connection, name_from_arg_or_channel_name, register_player::started_state_details};
+use crate::commands::registry::CommandError;
+use crate::{
+    db::DbConnection,
+    model::{
+        enums::{Era, NationMatch, Nations, StaticNation},
+        game_server::{GameAlias, GameServerState},
+        game_state::{PotentialPlayer, StartedStateDetails},
+        nation::GameNationIdentifier,
+    },
+    DetailsCacheHandle,
+};
+use serenity::{
+    model::id::{ChannelId, GuildId},
+    prelude::Context,
+};
+use std::sync::Arc;
+
+/// Discord won't show any more than this
+pub const MAX_AUTOCOMPLETE_CHOICES: usize = 25;
+
+/// What to show in the list, and what to fill the option in with
+pub struct Suggestion {
+    pub name: String,
+    pub value: String,
+}
+impl Suggestion {
+    fn for_nation(nation: &StaticNation) -> Self {
+        // the era makes the name unambiguous when it gets sent back to us
+        let value = format!("{} {}", nation.era, nation.name);
+        Suggestion {
+            name: format!("{} ({})", value, nation.id),
+            value,
+        }
+    }
+}
+
+/// Games that can be seen from here, the ones starting with what's been typed first
+pub async fn alias_suggestions(
+    context: &Context,
+    option_guild_id: Option<GuildId>,
+    typed: &str,
+) -> Result<Vec<Suggestion>, CommandError> {
+    let db_conn = db_connection(context).await?;
+    alias_suggestions_helper(&db_conn, option_guild_id, typed)
+}
+
+fn alias_suggestions_helper(
+    db_conn: &DbConnection,
+    option_guild_id: Option<GuildId>,
+    typed: &str,
+) -> Result<Vec<Suggestion>, CommandError> {
+    let typed = typed.to_lowercase();
+    let mut names = db_conn
+        .retrieve_all_servers()?
+        .into_iter()
+        .map(|server| server.alias)
+        .filter(|alias| alias.is_visible_from(option_guild_id) && alias.name.contains(&typed))
+        .map(|alias| alias.name)
+        .collect::<Vec<_>>();
+    names.sort_by_key(|name| (!name.starts_with(&typed), name.clone()));
+    names.dedup();
+    Ok(names
+        .into_iter()
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .map(|name| Suggestion {
+            name: name.clone(),
+            value: name,
+        })
+        .collect())
+}
+
+/// Nations that could still be picked in the game, best match for what's been typed first.
+/// Lobbies offer every allowed nation of the right era that nobody has taken yet, started games
+/// only the nations that are in the game and not registered.
+pub async fn nation_suggestions(
+    context: &Context,
+    channel_id: ChannelId,
+    option_game_name: Option<&str>,
+    option_guild_id: Option<GuildId>,
+    typed: &str,
+) -> Result<Vec<Suggestion>, CommandError> {
+    let db_conn = db_connection(context).await?;
+    let name =
+        name_from_arg_or_channel_name(context, channel_id, option_game_name.map(str::to_owned))
+            .await?;
+    let alias = db_conn.resolve_alias(&name, option_guild_id)?;
+    let details_read_handle = DetailsCacheHandle(Arc::clone(&context.data));
+    nation_suggestions_helper(db_conn, &details_read_handle, &alias, typed).await
+}
+
+async fn nation_suggestions_helper(
+    db_conn: DbConnection,
+    details_read_handle: &DetailsCacheHandle,
+    alias: &GameAlias,
+    typed: &str,
+) -> Result<Vec<Suggestion>, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+
+    let search = |era| -> Result<Vec<NationMatch>, CommandError> {
+        match server.dom_version {
+            5 => Ok(Nations::search(typed, era)),
+            6 => Ok(Nations::search_6(typed, era)),
+            _ => Err(CommandError::from(format!(
+                "Dom {} lol",
+                server.dom_version
+            ))),
+        }
+    };
+
+    let (option_era, is_available): (Option<Era>, Box<dyn Fn(u32) -> bool + Send + Sync>) =
+        match &server.state {
+            GameServerState::Lobby(lobby_state) => {
+                let taken_ids = db_conn
+                    .players_with_nations_for_game_alias(alias)?
+                    .into_iter()
+                    .filter_map(|(_, nation)| nation.id())
+                    .collect::<Vec<_>>();
+                let restrictions = db_conn.nation_restrictions(alias)?;
+                (
+                    Some(lobby_state.era),
+                    Box::new(move |id| !taken_ids.contains(&id) && restrictions.permits(id)),
+                )
+            }
+            GameServerState::StartedState(started_state, option_lobby_state) => {
+                let (started_details, _) = started_state_details(
+                    db_conn.clone(),
+                    started_state,
+                    option_lobby_state.as_ref(),
+                    alias,
+                    details_read_handle,
+                )
+                .await?;
+                let available_ids = unregistered_nation_ids(&started_details);
+                (
+                    option_lobby_state
+                        .as_ref()
+                        .map(|lobby_state| lobby_state.era),
+                    Box::new(move |id| available_ids.contains(&id)),
+                )
+            }
+        };
+
+    Ok(search(option_era)?
+        .into_iter()
+        .filter(|nation_match| is_available(nation_match.nation.id))
+        .take(MAX_AUTOCOMPLETE_CHOICES)
+        .map(|nation_match| Suggestion::for_nation(&nation_match.nation))
+        .collect())
+}
+
+fn unregistered_nation_ids(started_details: &StartedStateDetails) -> Vec<u32> {
+    let players = match started_details {
+        StartedStateDetails::Playing(playing_state) => playing_state.players.iter().collect(),
+        StartedStateDetails::Uploading(uploading_state) => uploading_state
+            .uploading_players
+            .iter()
+            .map(|uploading_player| &uploading_player.potential_player)
+            .collect::<Vec<_>>(),
+    };
+    players
+        .into_iter()
+        .filter_map(|potential_player| match potential_player {
+            PotentialPlayer::GameOnly(player_details) => match player_details.nation_identifier {
+                GameNationIdentifier::Existing(nation) => Some(nation.id),
+                // mod nations can't be looked up by name, they need register-id
+                GameNationIdentifier::CustomId(_) => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::{
+            enums::{NationStatus, SubmissionStatus},
+            game_data::GameData,
+            game_server::{GameServer, StartedState},
+            nation::{BotNationIdentifier, Nation},
+            player::{NotificationSettings, Player},
+        },
+        test_harness::{cache_game_data, empty_details_cache, TempDb},
+    };
+    use chrono::Utc;
+    use serenity::model::id::UserId;
+
+    const ARCOSCEPHALE: u32 = 5;
+    const MEKONE: u32 = 6;
+
+    fn register(db_conn: &DbConnection, alias: &GameAlias, user_id: u64, nation_id: u32) {
+        let player = Player {
+            discord_user_id: UserId::new(user_id),
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        };
+        db_conn
+            .insert_player_into_server(&player, alias, BotNationIdentifier::from_id_6(nation_id))
+            .unwrap();
+    }
+
+    fn values(suggestions: Vec<Suggestion>) -> Vec<String> {
+        suggestions
+            .into_iter()
+            .map(|suggestion| suggestion.value)
+            .collect()
+    }
+
+    #[test]
+    fn only_games_visible_from_here_are_suggested() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let here = GuildId::new(1);
+        for alias in [
+            GameAlias::new(Some(here), "samog"),
+            GameAlias::new(Some(GuildId::new(2)), "samarkand"),
+            // games without a server can be seen from all of them
+            GameAlias::new(None, "oldsam"),
+        ] {
+            temp_db.add_lobby(&alias, Era::Early, 2);
+        }
+
+        assert_eq!(
+            vec!["samog", "oldsam"],
+            values(alias_suggestions_helper(db_conn, Some(here), "sam").unwrap())
+        );
+        // DMs can see everything
+        assert_eq!(
+            vec!["samarkand", "samog", "oldsam"],
+            values(alias_suggestions_helper(db_conn, None, "SAM").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn lobbies_suggest_allowed_nations_nobody_has_taken() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        let alias = GameAlias::new(None, "lobby");
+        temp_db.add_lobby(&alias, Era::Early, 3);
+        register(db_conn, &alias, 2, ARCOSCEPHALE);
+        db_conn.ban_nation(&alias, MEKONE).unwrap();
+
+        let suggest = |typed| nation_suggestions_helper(db_conn.clone(), &cache, &alias, typed);
+        assert!(suggest("arco").await.unwrap().is_empty());
+        assert!(suggest("mekone").await.unwrap().is_empty());
+        assert_eq!(
+            vec!["EA Pangaea, Age of Revelry"],
+            values(suggest("pangaea").await.unwrap())
+        );
+        // wrong era
+        assert!(suggest("jomon").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn started_games_suggest_unregistered_nations_in_the_game() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        let alias = GameAlias::new(None, "started");
+        db_conn
+            .insert_game_server(&GameServer {
+                alias: alias.clone(),
+                state: GameServerState::StartedState(
+                    StartedState {
+                        address: "localhost:1".to_owned(),
+                        last_seen_turn: 1,
+                    },
+                    None,
+                ),
+                dom_version: 6,
+                anonymous: false,
+            })
+            .unwrap();
+        register(db_conn, &alias, 2, ARCOSCEPHALE);
+        let human = |nation_id| {
+            Some(Nation {
+                identifier: GameNationIdentifier::from_id_6(nation_id),
+                status: NationStatus::Human,
+                submitted: SubmissionStatus::NotSubmitted,
+                connected: false,
+            })
+        };
+        let game_data = GameData {
+            game_name: "started".to_owned(),
+            nations: vec![human(ARCOSCEPHALE), human(MEKONE)],
+            turn: 1,
+            turn_deadline: Utc::now(),
+            finished: false,
+        };
+        cache_game_data(&cache, &alias, game_data).await;
+
+        let suggest = |typed| nation_suggestions_helper(db_conn.clone(), &cache, &alias, typed);
+        assert!(suggest("arco").await.unwrap().is_empty());
+        assert_eq!(
+            vec!["EA Mekone, Brazen Giants"],
+            values(suggest("mekone").await.unwrap())
+        );
+        // not in the game
+        assert!(suggest("pangaea").await.unwrap().is_empty());
+    }
+}
@@ -1,8 +1,8 @@
 use super::alias_from_arg_or_channel_name;
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::commands::servers::CommandResponse;
 use crate::db::DbConnectionKey;
 use serenity::{
-    framework::standard::{Args, CommandError},
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
@@ -11,7 +11,7 @@ pub async fn describe(
     context: &Context,
     channel_id: ChannelId,
     _user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     let db_conn = {
         let data = context.data.read().await;
@@ -20,13 +20,8 @@ pub async fn describe(
             .clone()
     };
 
-    let description = args.single_quoted::<String>()?;
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: the description needs to be in quotes",
-        ));
-    }
+    let description = args.required_string("description")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
 
     db_conn.update_lobby_with_description(&alias, &description)?;
     Ok(CommandResponse::Reply(format!(
@@ -1,3 +1,4 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::commands::servers::discord_date_format;
 use crate::{
     commands::servers::{alias_from_arg_or_channel_name, CommandResponse},
@@ -9,6 +10,7 @@ use crate::{
         game_state::*,
         nation::{BotNationIdentifier, Nation},
         player::Player,
+        server_health::ServerHealth,
     },
     server::get_game_data_async,
     snek::{snek_details_async, SnekGameStatus},
@@ -17,7 +19,8 @@ use crate::{
 use log::*;
 use serenity::{
     builder::CreateEmbed,
-    framework::standard::{Args, CommandError},
+    cache::Cache,
+    http::Http,
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
@@ -27,7 +30,7 @@ pub async fn details(
     context: &Context,
     channel_id: ChannelId,
     _user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     // TODO: It's a bit weird to pass the arc here and use it elsewhere
     let data_handle = DetailsCacheHandle(Arc::clone(&context.data));
@@ -38,25 +41,22 @@ pub async fn details(
             .clone()
     };
 
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
     let embed_response = details_helper(&alias, db_conn, data_handle, context).await?;
     Ok(CommandResponse::Embed(Box::new(embed_response)))
 }
 
 pub async fn get_details_for_alias(
     db_conn: DbConnection,
-    alias: &str,
+    alias: &GameAlias,
 ) -> Result<GameDetails, CommandError> {
     let server = db_conn.game_for_alias(alias)?;
     info!("got server details");
 
     let details = match server.state {
-        GameServerState::Lobby(ref lobby_state) => lobby_details(db_conn, lobby_state, alias)?,
+        GameServerState::Lobby(ref lobby_state) => {
+            lobby_details(db_conn, lobby_state, alias, server.dom_version)?
+        }
         GameServerState::StartedState(ref started_state, ref option_lobby_state) => {
             started_details(
                 db_conn,
@@ -76,7 +76,7 @@ async fn started_details(
     db_conn: DbConnection,
     started_state: &StartedState,
     option_lobby_state: Option<&LobbyState>,
-    alias: &str,
+    alias: &GameAlias,
     dom_version: u8,
 ) -> Result<GameDetails, CommandError> {
     let server_address = &started_state.address;
@@ -97,18 +97,65 @@ async fn started_details(
     )
 }
 
-async fn details_helper(
-    alias: &str,
+pub async fn details_helper(
+    alias: &GameAlias,
     db_conn: DbConnection,
     read_handle: DetailsCacheHandle,
     context: &Context,
 ) -> Result<CreateEmbed, CommandError> {
+    let option_unreachable = unreachable_warning(&db_conn.server_health_for_game(alias)?);
+    let details = match cached_details_for_alias(alias, db_conn, read_handle).await {
+        Ok(details) => details,
+        // we've never heard from it, so there's nothing else to show
+        Err(_) if option_unreachable.is_some() => {
+            return Err(CommandError::from(format!(
+                "The server for {} {}",
+                alias,
+                option_unreachable.unwrap_or_default()
+            )))
+        }
+        Err(e) => return Err(e),
+    };
+    let mut embed: CreateEmbed =
+        details_to_embed(details, (&context.cache, context.http.as_ref())).await?;
+    if let Some(unreachable) = option_unreachable {
+        embed = embed.field(
+            "Server unreachable",
+            format!("The server {}, so this is what it last said", unreachable),
+            false,
+        );
+    }
+    Ok(embed)
+}
+
+fn unreachable_warning(health: &ServerHealth) -> Option<String> {
+    if !health.is_unreachable() {
+        return None;
+    }
+    Some(match health.last_success {
+        Some(last_success) => format!(
+            "hasn't answered since <t:{}:R> ({} failed checks)",
+            last_success.timestamp(),
+            health.consecutive_failures
+        ),
+        None => format!(
+            "hasn't answered any of the last {} checks",
+            health.consecutive_failures
+        ),
+    })
+}
+
+/// Like `get_details_for_alias` but uses what the turn checking last saw instead of
+/// asking the server
+pub async fn cached_details_for_alias(
+    alias: &GameAlias,
+    db_conn: DbConnection,
+    read_handle: DetailsCacheHandle,
+) -> Result<GameDetails, CommandError> {
     let server = db_conn.game_for_alias(alias)?;
     match &server.state {
         GameServerState::Lobby(lobby_state) => {
-            let details: GameDetails = lobby_details(db_conn, lobby_state, alias)?;
-            let embed: CreateEmbed = details_to_embed(details, context).await?;
-            Ok(embed)
+            lobby_details(db_conn, lobby_state, alias, server.dom_version)
         }
         GameServerState::StartedState(started_state, option_lobby_state) => {
             let cache = read_handle.get_clone(alias).await?;
@@ -117,17 +164,14 @@ async fn details_helper(
                 option_snek_state,
             } = cache;
 
-            let details: GameDetails = started_details_from_server(
+            started_details_from_server(
                 db_conn,
                 started_state,
                 option_lobby_state.as_ref(),
                 alias,
                 &game_data,
                 option_snek_state.as_ref(),
-            )?;
-
-            let embed: CreateEmbed = details_to_embed(details, context).await?;
-            Ok(embed)
+            )
         }
     }
 }
@@ -135,7 +179,8 @@ async fn details_helper(
 pub fn lobby_details(
     db_conn: DbConnection,
     lobby_state: &LobbyState,
-    alias: &str,
+    alias: &GameAlias,
+    dom_version: u8,
 ) -> Result<GameDetails, CommandError> {
     let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
 
@@ -158,16 +203,42 @@ pub fn lobby_details(
         (lobby_state.player_count - player_nation_details.len() as i32) as u32,
     );
 
+    let waitlist = db_conn
+        .waitlist(alias)?
+        .into_iter()
+        .map(|(player_id, nation_identifier)| {
+            let name = nation_identifier.name(None);
+            LobbyPlayer {
+                player_id,
+                nation_identifier,
+                cached_name: name,
+            }
+        })
+        .collect();
+
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    let nation_names = |nation_ids: &[u32]| {
+        nation_ids
+            .iter()
+            .filter_map(|&id| BotNationIdentifier::from_id_and_name(Some(id), None, dom_version))
+            .map(|nation| nation.name(None))
+            .collect()
+    };
+
     let lobby_details = LobbyDetails {
         players: player_nation_details,
         era: Some(lobby_state.era),
         remaining_slots,
+        waitlist,
+        banned_nations: nation_names(&restrictions.banned),
+        allowed_nations: nation_names(&restrictions.allowed),
     };
 
     Ok(GameDetails {
         alias: alias.to_owned(),
         owner: Some(lobby_state.owner),
         description: lobby_state.description.clone(),
+        anonymous: db_conn.is_game_anonymous(alias)?,
         nations: NationDetails::Lobby(lobby_details),
         cache_entry: None, // lobbies have no cache entry
     })
@@ -198,28 +269,26 @@ fn join_players_with_nations(
             ));
         }
     }
-    for maybe_nation in nations {
-        if let Some(nation) = maybe_nation {
-            match players_by_nation_id.remove(&nation.identifier.id()) {
-                // Lobby and game
-                Some((player, _)) => {
-                    let player_details = PlayerDetails {
-                        nation_identifier: nation.identifier.clone(),
-                        submitted: nation.submitted,
-                        player_status: nation.status,
-                    };
-                    potential_players.push(PotentialPlayer::RegisteredAndGame(
-                        player.clone(),
-                        player_details,
-                    ))
-                }
-                // Game only
-                None => potential_players.push(PotentialPlayer::GameOnly(PlayerDetails {
+    for nation in nations.iter().flatten() {
+        match players_by_nation_id.remove(&nation.identifier.id()) {
+            // Lobby and game
+            Some((player, _)) => {
+                let player_details = PlayerDetails {
                     nation_identifier: nation.identifier.clone(),
                     submitted: nation.submitted,
                     player_status: nation.status,
-                })),
+                };
+                potential_players.push(PotentialPlayer::RegisteredAndGame(
+                    player.clone(),
+                    player_details,
+                ))
             }
+            // Game only
+            None => potential_players.push(PotentialPlayer::GameOnly(PlayerDetails {
+                nation_identifier: nation.identifier.clone(),
+                submitted: nation.submitted,
+                player_status: nation.status,
+            })),
         }
     }
     // Lobby only RegisteredOnly(UserId, BotNationIdentifier),
@@ -236,7 +305,7 @@ pub fn started_details_from_server(
     db_conn: DbConnection,
     started_state: &StartedState,
     option_lobby_state: Option<&LobbyState>,
-    alias: &str,
+    alias: &GameAlias,
     game_data: &GameData,
     option_snek_details: Option<&SnekGameStatus>,
 ) -> Result<GameDetails, CommandError> {
@@ -293,6 +362,7 @@ pub fn started_details_from_server(
         alias: alias.to_owned(),
         owner: option_lobby_state.map(|lobby_state| lobby_state.owner),
         description: option_lobby_state.and_then(|lobby_state| lobby_state.description.clone()),
+        anonymous: db_conn.is_game_anonymous(alias)?,
         nations: NationDetails::Started(started_details),
         cache_entry: Some(CacheEntry {
             game_data: game_data.clone(),
@@ -301,11 +371,11 @@ pub fn started_details_from_server(
     })
 }
 
-async fn details_to_embed(
+pub async fn details_to_embed(
     details: GameDetails,
-    context: &Context,
+    cache_http: (&Arc<Cache>, &Http),
 ) -> Result<CreateEmbed, CommandError> {
-    let anon_game = details.alias.ends_with("_anon");
+    let anon_game = details.anonymous;
 
     let option_snek_state = details
         .cache_entry
@@ -348,10 +418,7 @@ async fn details_to_embed(
                                     match option_user_id {
                                         Some(player) => Some(Cow::Owned(format!(
                                             "**{}**",
-                                            player
-                                                .discord_user_id
-                                                .to_user((&context.cache, context.http.as_ref()))
-                                                .await?
+                                            player.discord_user_id.to_user(cache_http).await?
                                         ))),
                                         None => None,
                                     }
@@ -364,10 +431,7 @@ async fn details_to_embed(
                                 match option_user_id {
                                     Some(player) => Some(Cow::Owned(format!(
                                         "**{}**",
-                                        player
-                                            .discord_user_id
-                                            .to_user((&context.cache, context.http.as_ref()))
-                                            .await?
+                                        player.discord_user_id.to_user(cache_http).await?
                                     ))),
                                     None => None,
                                 }
@@ -447,10 +511,7 @@ async fn details_to_embed(
                         let player_name = match uploading_player.option_player_id() {
                             Some(player) if !anon_game => Cow::Owned(format!(
                                 "**{}**",
-                                player
-                                    .discord_user_id
-                                    .to_user((&context.cache, context.http.as_ref()))
-                                    .await?
+                                player.discord_user_id.to_user(cache_http).await?
                             )),
                             _ => Cow::Borrowed(NationStatus::Human.show()),
                         };
@@ -503,7 +564,7 @@ async fn details_to_embed(
                     let discord_user = if !anon_game {
                         lobby_player
                             .player_id
-                            .to_user((&context.cache, context.http.as_ref()))
+                            .to_user(cache_http)
                             .await?
                             .to_string()
                     } else {
@@ -548,18 +609,42 @@ async fn details_to_embed(
             for embed_text in &embed_texts[1..] {
                 e = e.field("-----", embed_text, false);
             }
+            if !lobby_details.waitlist.is_empty() {
+                let mut waitlist_text = String::new();
+                for (ix, lobby_player) in lobby_details.waitlist.iter().enumerate() {
+                    let discord_user = if !anon_game {
+                        lobby_player
+                            .player_id
+                            .to_user(cache_http)
+                            .await?
+                            .to_string()
+                    } else {
+                        "Human".to_string()
+                    };
+                    waitlist_text.push_str(&format!(
+                        "{}. {}: {}\n",
+                        ix + 1,
+                        lobby_player.cached_name,
+                        discord_user
+                    ));
+                }
+                e = e.field("Waitlist", waitlist_text, false);
+            }
+            if !lobby_details.banned_nations.is_empty() {
+                e = e.field("Banned", lobby_details.banned_nations.join(", "), false);
+            }
+            if !lobby_details.allowed_nations.is_empty() {
+                e = e.field(
+                    "Only allowed",
+                    lobby_details.allowed_nations.join(", "),
+                    false,
+                );
+            }
             e
         }
     };
     if let Some(owner) = details.owner {
-        e = e.field(
-            "Owner",
-            owner
-                .to_user((&context.cache, context.http.as_ref()))
-                .await?
-                .to_string(),
-            false,
-        );
+        e = e.field("Owner", owner.to_user(cache_http).await?.to_string(), false);
     }
 
     if let Some(description) = details.description {
@@ -0,0 +1,628 @@
+//! Lobbies where the players take turns to pick their nations, so that nobody has to be
+//! awake when the lobby goes up to get the nation they want
+
+use super::{
+    alias_from_arg_or_channel_name, db_connection,
+    permissions::check_can_manage_game,
+    register_player::{
+        ambiguous_nation_error, check_command_is_private, search_nations, static_nation_name,
+    },
+    restrictions::check_nation_allowed,
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::DbConnection,
+    model::{
+        draft::{Draft, DraftOrder, DraftPick},
+        enums::{NationSearch, StaticNation},
+        game_server::{GameAlias, GameServer, GameServerState, LobbyState},
+        player::{NotificationSettings, Player},
+    },
+};
+use chrono::{DateTime, Duration, Utc};
+use log::*;
+use rand::seq::SliceRandom;
+use serenity::{
+    http::Http,
+    model::{
+        id::{ChannelId, UserId},
+        mention::Mention,
+    },
+    prelude::Context,
+};
+use std::{str::FromStr, sync::Arc};
+
+/// How long each player gets to pick if the owner doesn't say
+const DEFAULT_PICK_HOURS: i64 = 12;
+/// How often to check whether anybody has run out of time to pick
+const DEADLINE_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Registering with `!join` would skip the queue
+pub fn check_not_drafting(db_conn: &DbConnection, alias: &GameAlias) -> Result<(), CommandError> {
+    if db_conn.draft_for_game(alias)?.is_some() {
+        return Err(format!(
+            "{} is drafting nations, so sign up with !draft join instead",
+            alias
+        )
+        .into());
+    }
+    Ok(())
+}
+
+pub(super) fn lobby_for_alias(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<(GameServer, LobbyState), CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    match &server.state {
+        GameServerState::Lobby(lobby_state) => {
+            let lobby_state = lobby_state.clone();
+            Ok((server, lobby_state))
+        }
+        GameServerState::StartedState(_, _) => Err(format!("{} has already started", alias).into()),
+    }
+}
+
+/// The nations of the lobby's era that nobody has picked yet, whether they're allowed or not
+fn untaken_nations(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    lobby_state: &LobbyState,
+    dom_version: u8,
+) -> Result<Vec<StaticNation>, CommandError> {
+    let taken_ids = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .filter_map(|(_, nation)| nation.id())
+        .collect::<Vec<_>>();
+    // an empty search matches every nation
+    Ok(search_nations("", Some(lobby_state.era), dom_version)?
+        .into_iter()
+        .map(|nation_match| nation_match.nation)
+        .filter(|nation| !taken_ids.contains(&nation.id))
+        .collect())
+}
+
+/// The nations of the lobby's era that nobody has picked yet and the owner allows
+pub(super) fn available_nations(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    lobby_state: &LobbyState,
+    dom_version: u8,
+) -> Result<Vec<StaticNation>, CommandError> {
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    Ok(untaken_nations(db_conn, alias, lobby_state, dom_version)?
+        .into_iter()
+        .filter(|nation| restrictions.permits(nation.id))
+        .collect())
+}
+
+fn set_draft_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    order: DraftOrder,
+    pick_hours: i64,
+) -> Result<String, CommandError> {
+    lobby_for_alias(db_conn, alias)?;
+    if pick_hours < 1 {
+        return Err("Players need at least an hour to pick".into());
+    }
+    match db_conn.draft_for_game(alias)? {
+        Some(draft) if draft.has_started() => {
+            return Err(format!("The draft for {} has already started", alias).into());
+        }
+        Some(_) => {}
+        None => {
+            if !db_conn
+                .players_with_nations_for_game_alias(alias)?
+                .is_empty()
+                || !db_conn.nation_preferences(alias)?.is_empty()
+            {
+                return Err(format!(
+                    "Players have already joined {} with a nation, so it's too late to draft",
+                    alias
+                )
+                .into());
+            }
+        }
+    }
+    db_conn.set_draft(alias, order, (pick_hours * 60) as i32)?;
+    let order_text = match order {
+        DraftOrder::SignUp => "the order players sign up in",
+        DraftOrder::Random => "a random order",
+    };
+    Ok(format!(
+        "{} will draft nations in {}, with {} hours for each pick. Sign up with !draft join, and start the draft once the lobby is full.",
+        alias, order_text, pick_hours
+    ))
+}
+
+fn join_draft_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    user_id: UserId,
+) -> Result<String, CommandError> {
+    let (_, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let draft = db_conn
+        .draft_for_game(alias)?
+        .ok_or_else(|| format!("{} isn't drafting nations, so just !join it", alias))?;
+    if draft.has_started() {
+        return Err(format!("The draft for {} has already started", alias).into());
+    }
+    if draft.players.contains(&user_id) {
+        return Err("You have already joined this draft".into());
+    }
+    let player_count =
+        draft.players.len() + db_conn.players_with_nations_for_game_alias(alias)?.len();
+    if player_count as i32 >= lobby_state.player_count {
+        return Err("lobby already full".into());
+    }
+
+    let player = Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+        notifications: NotificationSettings::all(true),
+    };
+    db_conn.join_draft(alias, &player)?;
+    Ok(format!(
+        "Joined the draft for {} ({}/{})",
+        alias,
+        player_count + 1,
+        lobby_state.player_count
+    ))
+}
+
+fn start_draft_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    channel_id: ChannelId,
+    now: DateTime<Utc>,
+) -> Result<Draft, CommandError> {
+    let (_, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let draft = db_conn
+        .draft_for_game(alias)?
+        .ok_or_else(|| format!("{} isn't drafting nations", alias))?;
+    if draft.has_started() {
+        return Err(format!("The draft for {} has already started", alias).into());
+    }
+    let player_count =
+        draft.players.len() + db_conn.players_with_nations_for_game_alias(alias)?.len();
+    if (player_count as i32) < lobby_state.player_count {
+        return Err(format!(
+            "The lobby isn't full yet ({}/{})",
+            player_count, lobby_state.player_count
+        )
+        .into());
+    }
+
+    let mut pick_order = draft.players.clone();
+    if draft.order == DraftOrder::Random {
+        pick_order.shuffle(&mut rand::thread_rng());
+    }
+    let deadline = now + Duration::minutes(draft.pick_minutes as i64);
+    db_conn.start_draft(alias, &pick_order, channel_id, deadline)?;
+    db_conn
+        .draft_for_game(alias)?
+        .ok_or_else(|| CommandError::from("draft vanished while starting it"))
+}
+
+pub async fn draft(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let action = args.required_string("action")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+
+    let text = match action.as_str() {
+        "signup" | "random" => {
+            check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+            let order = if action == "signup" {
+                DraftOrder::SignUp
+            } else {
+                DraftOrder::Random
+            };
+            let pick_hours = args.integer("pick_hours").unwrap_or(DEFAULT_PICK_HOURS);
+            set_draft_helper(&db_conn, &alias, order, pick_hours)?
+        }
+        "join" => {
+            // who is in an anonymous game is a secret
+            check_command_is_private(context, channel_id, &alias, &db_conn, "join the draft")
+                .await?;
+            join_draft_helper(&db_conn, &alias, user_id)?
+        }
+        "start" => {
+            check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+            let draft = start_draft_helper(&db_conn, &alias, channel_id, Utc::now())?;
+            let current_pick = draft
+                .current_pick
+                .as_ref()
+                .ok_or("draft didn't start. This is a bug.")?;
+            dm_picker(&context.http, &db_conn, &alias, current_pick).await;
+            if db_conn.is_game_anonymous(&alias)? {
+                format!(
+                    "The draft for {} has started! I've DMed whoever picks first.",
+                    alias
+                )
+            } else {
+                format!(
+                    "The draft for {} has started! {} picks first.",
+                    alias,
+                    Mention::from(current_pick.player)
+                )
+            }
+        }
+        "off" => {
+            check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+            if db_conn.draft_for_game(&alias)?.is_none() {
+                return Err(format!("{} isn't drafting nations", alias).into());
+            }
+            db_conn.remove_draft(&alias)?;
+            format!(
+                "{} is no longer drafting. Anybody who hadn't picked yet needs to !join it with a nation.",
+                alias
+            )
+        }
+        _ => return Err(format!("Unknown draft action \"{}\"", action).into()),
+    };
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+/// Whatever draft is waiting on the player, if it's only one. Picks usually happen in DMs,
+/// which have no channel name to get the game from.
+async fn alias_for_pick(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    db_conn: &DbConnection,
+    option_name: Option<String>,
+) -> Result<GameAlias, CommandError> {
+    if option_name.is_none() {
+        let waiting_drafts = db_conn
+            .drafts()?
+            .into_iter()
+            .filter(|draft| {
+                draft
+                    .current_pick
+                    .as_ref()
+                    .is_some_and(|pick| pick.player == user_id)
+            })
+            .collect::<Vec<_>>();
+        if let [draft] = &waiting_drafts[..] {
+            return Ok(draft.alias.clone());
+        }
+    }
+    alias_from_arg_or_channel_name(context, channel_id, option_name).await
+}
+
+fn find_available_nation(
+    arg_nation: &str,
+    available: &[StaticNation],
+    lobby_state: &LobbyState,
+    dom_version: u8,
+) -> Result<StaticNation, CommandError> {
+    if let Ok(nation_id) = u32::from_str(arg_nation) {
+        return available
+            .iter()
+            .find(|nation| nation.id == nation_id)
+            .copied()
+            .ok_or_else(|| format!("Nation {} isn't available", nation_id).into());
+    }
+    let nation_matches = search_nations(arg_nation, Some(lobby_state.era), dom_version)?
+        .into_iter()
+        .filter(|nation_match| {
+            available
+                .iter()
+                .any(|nation| nation.id == nation_match.nation.id)
+        })
+        .collect();
+    match NationSearch::from_matches(nation_matches) {
+        NationSearch::Found(nation) => Ok(nation),
+        NationSearch::Ambiguous(nations) => Err(ambiguous_nation_error(
+            arg_nation,
+            nations.iter().map(static_nation_name),
+        )),
+        NationSearch::NotFound => Err(format!(
+            "Could not find an available nation starting with \"{}\"",
+            arg_nation
+        )
+        .into()),
+    }
+}
+
+/// What the pick was, and who picks next (if anybody)
+fn pick_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    user_id: UserId,
+    arg_nation: &str,
+    now: DateTime<Utc>,
+) -> Result<(Draft, StaticNation, Option<DraftPick>), CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let draft = db_conn
+        .draft_for_game(alias)?
+        .ok_or_else(|| format!("{} isn't drafting nations", alias))?;
+    match &draft.current_pick {
+        None => return Err(format!("The draft for {} hasn't started yet", alias).into()),
+        Some(pick) if pick.player != user_id => {
+            return Err(format!("It isn't your turn to pick in {}", alias).into())
+        }
+        Some(_) => {}
+    }
+    // banned nations are still found, so the player is told why they can't have it
+    let untaken = untaken_nations(db_conn, alias, &lobby_state, server.dom_version)?;
+    let nation = find_available_nation(arg_nation, &untaken, &lobby_state, server.dom_version)?;
+    check_nation_allowed(db_conn, alias, nation.id, &static_nation_name(&nation))?;
+    let option_next_pick = make_pick(db_conn, &draft, user_id, &nation, now)?;
+    Ok((draft, nation, option_next_pick))
+}
+
+fn make_pick(
+    db_conn: &DbConnection,
+    draft: &Draft,
+    user_id: UserId,
+    nation: &StaticNation,
+    now: DateTime<Utc>,
+) -> Result<Option<DraftPick>, CommandError> {
+    let deadline = now + Duration::minutes(draft.pick_minutes as i64);
+    let option_next_player = db_conn.make_draft_pick(&draft.alias, user_id, nation, deadline)?;
+    Ok(option_next_player.map(|player| DraftPick { player, deadline }))
+}
+
+pub async fn pick(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nation = args.required_string("nation_name_prefix")?;
+    let db_conn = db_connection(context).await?;
+    let alias = alias_for_pick(context, channel_id, user_id, &db_conn, args.string("name")).await?;
+    check_command_is_private(context, channel_id, &alias, &db_conn, "pick").await?;
+
+    let (draft, nation, option_next_pick) =
+        pick_helper(&db_conn, &alias, user_id, &arg_nation, Utc::now())?;
+    announce_pick(
+        &context.http,
+        &db_conn,
+        &draft,
+        user_id,
+        &nation,
+        option_next_pick.as_ref(),
+    )
+    .await;
+    Ok(CommandResponse::Reply(format!(
+        "You picked {} in {}",
+        static_nation_name(&nation),
+        alias
+    )))
+}
+
+async fn send_dm(http: &Http, user_id: UserId, text: &str) {
+    let result = match user_id.create_dm_channel(http).await {
+        Ok(private_channel) => private_channel.say(http, text).await.map(|_| ()),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        error!("Could not DM {}: {:?}", user_id, err);
+    }
+}
+
+/// Tell the player it's their turn, and what they can pick from
+async fn dm_picker(http: &Http, db_conn: &DbConnection, alias: &GameAlias, pick: &DraftPick) {
+    let result = lobby_for_alias(db_conn, alias).and_then(|(server, lobby_state)| {
+        available_nations(db_conn, alias, &lobby_state, server.dom_version)
+    });
+    let available = match result {
+        Ok(available) => available,
+        Err(err) => {
+            error!("Could not find the nations left in {}: {:?}", alias, err);
+            return;
+        }
+    };
+    let text = format!(
+        "It's your turn to pick a nation in {}! Use `!pick <nation> {}` before <t:{}:f>, or you'll get a random one. Still available: {}",
+        alias,
+        alias.name,
+        pick.deadline.timestamp(),
+        available
+            .iter()
+            .map(static_nation_name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    send_dm(http, pick.player, &text).await;
+}
+
+/// Tell the lobby about the pick, and the next player that it's their turn
+async fn announce_pick(
+    http: &Http,
+    db_conn: &DbConnection,
+    draft: &Draft,
+    picker: UserId,
+    nation: &StaticNation,
+    option_next_pick: Option<&DraftPick>,
+) {
+    let anonymous = db_conn.is_game_anonymous(&draft.alias).unwrap_or(true);
+    let mut text = if anonymous {
+        format!("Another nation has been picked in {}.", draft.alias)
+    } else {
+        format!(
+            "{} picked {} in {}.",
+            Mention::from(picker),
+            static_nation_name(nation),
+            draft.alias
+        )
+    };
+    match option_next_pick {
+        Some(next_pick) => {
+            if !anonymous {
+                text.push_str(&format!(" {} is up next.", Mention::from(next_pick.player)));
+            }
+            dm_picker(http, db_conn, &draft.alias, next_pick).await;
+        }
+        None => text.push_str(" That was the last pick, so the draft is over!"),
+    }
+    if let Some(channel_id) = draft.channel_id {
+        if let Err(err) = channel_id.say(http, &text).await {
+            error!(
+                "Could not announce a draft pick in {}: {:?}",
+                channel_id, err
+            );
+        }
+    }
+}
+
+/// Keeps drafts moving: gives anybody who has run out of time a random nation, and moves
+/// on if whoever was picking left the lobby
+pub async fn draft_deadline_loop(db_conn: DbConnection, http: Arc<Http>) {
+    loop {
+        match db_conn.drafts() {
+            Err(err) => error!("Could not get drafts: {:?}", err),
+            Ok(drafts) => {
+                let now = Utc::now();
+                for draft in drafts {
+                    if let Err(err) = check_draft_deadline(&db_conn, &http, &draft, now).await {
+                        error!("Could not check the draft for {}: {:?}", draft.alias, err);
+                    }
+                }
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(DEADLINE_CHECK_INTERVAL_SECS)).await;
+    }
+}
+
+async fn check_draft_deadline(
+    db_conn: &DbConnection,
+    http: &Http,
+    draft: &Draft,
+    now: DateTime<Utc>,
+) -> Result<(), CommandError> {
+    let Some(current_pick) = &draft.current_pick else {
+        return Ok(());
+    };
+    match draft.players.first() {
+        None => {
+            // everybody who was left to pick has left the lobby
+            db_conn.remove_draft(&draft.alias)?;
+        }
+        Some(&next_player) if next_player != current_pick.player => {
+            let pick = DraftPick {
+                player: next_player,
+                deadline: now + Duration::minutes(draft.pick_minutes as i64),
+            };
+            db_conn.set_draft_pick(&draft.alias, &pick)?;
+            dm_picker(http, db_conn, &draft.alias, &pick).await;
+        }
+        Some(_) if current_pick.deadline <= now => {
+            let (server, lobby_state) = lobby_for_alias(db_conn, &draft.alias)?;
+            let available =
+                available_nations(db_conn, &draft.alias, &lobby_state, server.dom_version)?;
+            let nation = *available
+                .choose(&mut rand::thread_rng())
+                .ok_or("There are no nations left to pick")?;
+            info!(
+                "{} ran out of time to pick in {}, so got {}",
+                current_pick.player, draft.alias, nation.name
+            );
+            let option_next_pick = make_pick(db_conn, draft, current_pick.player, &nation, now)?;
+            send_dm(
+                http,
+                current_pick.player,
+                &format!(
+                    "You ran out of time to pick a nation in {}, so you got {}",
+                    draft.alias,
+                    static_nation_name(&nation)
+                ),
+            )
+            .await;
+            announce_pick(
+                http,
+                db_conn,
+                draft,
+                current_pick.player,
+                &nation,
+                option_next_pick.as_ref(),
+            )
+            .await;
+        }
+        Some(_) => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::enums::Era, test_harness::TempDb};
+
+    fn lobby_alias() -> GameAlias {
+        GameAlias::new(None, "drafty")
+    }
+
+    #[test]
+    fn players_pick_in_turn_until_everybody_has_a_nation() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = lobby_alias();
+        let now = Utc::now();
+        temp_db.add_lobby(&alias, Era::Middle, 2);
+
+        set_draft_helper(db_conn, &alias, DraftOrder::SignUp, 1).unwrap();
+        join_draft_helper(db_conn, &alias, UserId::new(2)).unwrap();
+        assert!(start_draft_helper(db_conn, &alias, ChannelId::new(9), now).is_err());
+        join_draft_helper(db_conn, &alias, UserId::new(3)).unwrap();
+        assert!(join_draft_helper(db_conn, &alias, UserId::new(4)).is_err());
+        // drafting lobbies can't be joined the usual way
+        assert!(check_not_drafting(db_conn, &alias).is_err());
+
+        let draft = start_draft_helper(db_conn, &alias, ChannelId::new(9), now).unwrap();
+        assert_eq!(Some(ChannelId::new(9)), draft.channel_id);
+        assert_eq!(UserId::new(2), draft.current_pick.unwrap().player);
+        assert!(pick_helper(db_conn, &alias, UserId::new(3), "tc", now).is_err());
+
+        let (_, nation, option_next_pick) =
+            pick_helper(db_conn, &alias, UserId::new(2), "tc", now).unwrap();
+        assert_eq!(69, nation.id);
+        assert_eq!(UserId::new(3), option_next_pick.unwrap().player);
+        // nobody else can have it
+        assert!(pick_helper(db_conn, &alias, UserId::new(3), "69", now).is_err());
+
+        let (_, _, option_next_pick) =
+            pick_helper(db_conn, &alias, UserId::new(3), "bl", now).unwrap();
+        assert_eq!(None, option_next_pick);
+        assert!(check_not_drafting(db_conn, &alias).is_ok());
+        let mut nations = db_conn
+            .players_with_nations_for_game_alias(&alias)
+            .unwrap()
+            .into_iter()
+            .map(|(player, nation)| (player.discord_user_id.get(), nation.id().unwrap()))
+            .collect::<Vec<_>>();
+        nations.sort();
+        assert_eq!(vec![(2, 69), (3, 68)], nations);
+    }
+
+    #[test]
+    fn drafts_need_an_empty_lobby() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = lobby_alias();
+        temp_db.add_lobby(&alias, Era::Middle, 2);
+        db_conn
+            .insert_player_into_server(
+                &Player {
+                    discord_user_id: UserId::new(2),
+                    turn_notifications: true,
+                    notifications: NotificationSettings::all(true),
+                },
+                &alias,
+                crate::model::nation::BotNationIdentifier::from_id_6(69),
+            )
+            .unwrap();
+        assert!(set_draft_helper(db_conn, &alias, DraftOrder::Random, 12).is_err());
+    }
+}
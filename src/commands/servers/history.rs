@@ -0,0 +1,311 @@
+use super::{alias_from_arg_or_channel_name, archive::find_archived_game};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    config,
+    db::{DbConnection, DbConnectionKey},
+    model::{enums::*, game_server::GameAlias, nation::Nation, turn_history::NationTurnRecord},
+};
+use chrono::Duration;
+use serenity::{
+    builder::CreateEmbed,
+    model::id::{ChannelId, UserId},
+    prelude::Context,
+};
+use std::collections::{BTreeMap, HashMap};
+
+const RECENT_TURN_COUNT: usize = 10;
+// discord's limit is 1024 characters per field
+const MAX_FIELD_LENGTH: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+struct TurnSummary<'a> {
+    turn: i32,
+    /// False for the turn that's currently being played
+    finished: bool,
+    stales: Vec<&'a Nation>,
+    went_ai: Vec<&'a Nation>,
+    last_to_submit: Option<&'a Nation>,
+}
+
+/// Works out what happened in each turn. Nobody can have staled in the turn that's
+/// currently being played, but they might have already gone AI.
+fn summarise_turns(records: &[NationTurnRecord]) -> Vec<TurnSummary<'_>> {
+    let mut records_by_turn: BTreeMap<i32, Vec<&NationTurnRecord>> = BTreeMap::new();
+    for record in records {
+        records_by_turn.entry(record.turn).or_default().push(record);
+    }
+    let option_current_turn = records_by_turn.keys().next_back().copied();
+
+    let mut previous_status_by_nation_id: HashMap<u32, NationStatus> = HashMap::new();
+    let mut summaries = vec![];
+    for (turn, turn_records) in records_by_turn {
+        let went_ai = turn_records
+            .iter()
+            .filter(|record| {
+                record.nation.status == NationStatus::AI
+                    && previous_status_by_nation_id
+                        .get(&record.nation.identifier.id())
+                        .is_some_and(|status| status.is_human())
+            })
+            .map(|record| &record.nation)
+            .collect::<Vec<_>>();
+        for record in &turn_records {
+            previous_status_by_nation_id
+                .insert(record.nation.identifier.id(), record.nation.status);
+        }
+
+        if Some(turn) == option_current_turn {
+            summaries.push(TurnSummary {
+                turn,
+                finished: false,
+                stales: vec![],
+                went_ai,
+                last_to_submit: None,
+            });
+            continue;
+        }
+
+        // if the last time we saw this turn was within one poll of the deadline, then the
+        // timer ran out. Otherwise everybody submitted and it was hosted early.
+        let timed_out = turn_records.iter().any(|record| {
+            record
+                .turn_deadline
+                .signed_duration_since(record.recorded_at)
+                <= Duration::from_std(config::timings().poll_interval)
+                    .expect("poll interval always fits in a chrono duration")
+        });
+
+        let human_records = turn_records
+            .iter()
+            .filter(|record| record.nation.status == NationStatus::Human);
+        let stales = if timed_out {
+            human_records
+                .clone()
+                .filter(|record| record.nation.submitted == SubmissionStatus::NotSubmitted)
+                .map(|record| &record.nation)
+                .collect()
+        } else {
+            vec![]
+        };
+        let last_to_submit = human_records
+            .filter(|record| record.nation.submitted == SubmissionStatus::Submitted)
+            .filter_map(|record| record.submitted_at.map(|at| (at, &record.nation)))
+            .max_by_key(|(submitted_at, _)| *submitted_at)
+            .map(|(_, nation)| nation);
+
+        summaries.push(TurnSummary {
+            turn,
+            finished: true,
+            stales,
+            went_ai,
+            last_to_submit,
+        });
+    }
+    summaries
+}
+
+fn nation_names(nations: &[&Nation]) -> String {
+    nations
+        .iter()
+        .map(|nation| nation.identifier.name(None))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn join_lines_for_field(lines: impl Iterator<Item = String>) -> String {
+    let mut text = String::new();
+    for line in lines {
+        if text.len() + line.len() + 1 > MAX_FIELD_LENGTH {
+            text.push_str("...\n");
+            break;
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    if text.is_empty() {
+        "None!".to_owned()
+    } else {
+        text
+    }
+}
+
+fn history_helper(
+    db_conn: DbConnection,
+    alias: &GameAlias,
+) -> Result<CommandResponse, CommandError> {
+    // a game that's still going, or else the last one with this name that finished. This also
+    // checks the game exists so we don't just say "no history" for a typo
+    let (title, records) = match db_conn.game_for_alias(alias) {
+        Ok(_) => (
+            format!("History for {}", alias),
+            db_conn.select_turn_history(alias)?,
+        ),
+        Err(_) => {
+            let archived_game = find_archived_game(&db_conn, &alias.name, alias.guild_id)?;
+            (
+                format!(
+                    "History for {} (finished on turn {})",
+                    alias, archived_game.final_turn
+                ),
+                db_conn.archived_turn_history(archived_game.id)?,
+            )
+        }
+    };
+    if records.is_empty() {
+        return Ok(CommandResponse::Reply(format!(
+            "No turn history recorded for {} yet",
+            alias
+        )));
+    }
+    let summaries = summarise_turns(&records);
+
+    let mut stale_counts: HashMap<u32, (&Nation, u32)> = HashMap::new();
+    for summary in &summaries {
+        for nation in &summary.stales {
+            stale_counts
+                .entry(nation.identifier.id())
+                .or_insert((nation, 0))
+                .1 += 1;
+        }
+    }
+    let mut stale_counts = stale_counts.into_values().collect::<Vec<_>>();
+    stale_counts.sort_by(|(n1, c1), (n2, c2)| {
+        c2.cmp(c1)
+            .then_with(|| n1.identifier.id().cmp(&n2.identifier.id()))
+    });
+    let stales_text = join_lines_for_field(
+        stale_counts
+            .into_iter()
+            .map(|(nation, count)| format!("{}: {}", nation.identifier.name(None), count)),
+    );
+
+    let went_ai_text = join_lines_for_field(summaries.iter().flat_map(|summary| {
+        summary
+            .went_ai
+            .iter()
+            .map(move |nation| format!("Turn {}: {}", summary.turn, nation.identifier.name(None)))
+    }));
+
+    let recent_turns_text = join_lines_for_field(
+        summaries
+            .iter()
+            .rev()
+            .filter(|summary| summary.finished)
+            .take(RECENT_TURN_COUNT)
+            .map(|summary| {
+                let mut line = format!("Turn {}: ", summary.turn);
+                if summary.stales.is_empty() {
+                    line.push_str("no stales");
+                } else {
+                    line.push_str(&format!("stales: {}", nation_names(&summary.stales)));
+                }
+                if !summary.went_ai.is_empty() {
+                    line.push_str(&format!(", went AI: {}", nation_names(&summary.went_ai)));
+                }
+                if let Some(last_to_submit) = summary.last_to_submit {
+                    line.push_str(&format!(
+                        ", last to submit: {}",
+                        last_to_submit.identifier.name(None)
+                    ));
+                }
+                line
+            }),
+    );
+
+    let embed = CreateEmbed::default()
+        .title(title)
+        .field("Stales", stales_text, false)
+        .field("Went AI", went_ai_text, false)
+        .field("Recent turns", recent_turns_text, false);
+    Ok(CommandResponse::Embed(Box::new(embed)))
+}
+
+pub async fn history(
+    context: &Context,
+    channel_id: ChannelId,
+    _user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    history_helper(db_conn, &alias)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::nation::GameNationIdentifier;
+    use chrono::{DateTime, Utc};
+
+    fn record(
+        turn: i32,
+        nation_id: u32,
+        status: NationStatus,
+        submitted: SubmissionStatus,
+        submitted_at_mins: Option<i64>,
+        timed_out: bool,
+    ) -> NationTurnRecord {
+        let turn_start = DateTime::<Utc>::from_timestamp(turn as i64 * 100_000, 0).unwrap();
+        let turn_deadline = turn_start + Duration::hours(24);
+        let recorded_at = if timed_out {
+            turn_deadline - Duration::seconds(30)
+        } else {
+            turn_start + Duration::hours(2)
+        };
+        NationTurnRecord {
+            turn,
+            turn_deadline,
+            nation: Nation {
+                identifier: GameNationIdentifier::from_id_6(nation_id),
+                status,
+                submitted,
+                connected: false,
+            },
+            submitted_at: submitted_at_mins.map(|mins| turn_start + Duration::minutes(mins)),
+            recorded_at,
+        }
+    }
+
+    #[test]
+    fn stales_only_counted_when_timer_ran_out() {
+        use NationStatus::*;
+        use SubmissionStatus::*;
+        let records = vec![
+            record(1, 5, Human, Submitted, Some(10), true),
+            record(1, 6, Human, NotSubmitted, None, true),
+            record(2, 5, Human, Submitted, Some(10), false),
+            record(2, 6, Human, Submitted, Some(20), false),
+            record(3, 5, Human, NotSubmitted, None, false),
+            record(3, 6, Human, NotSubmitted, None, false),
+        ];
+        let summaries = summarise_turns(&records);
+        assert_eq!(3, summaries.len());
+        assert!(!summaries[2].finished);
+        assert_eq!(vec![&records[1].nation], summaries[0].stales);
+        assert_eq!(Some(&records[0].nation), summaries[0].last_to_submit);
+        assert!(summaries[1].stales.is_empty());
+        assert_eq!(Some(&records[3].nation), summaries[1].last_to_submit);
+    }
+
+    #[test]
+    fn went_ai_detected_from_previous_turn() {
+        use NationStatus::*;
+        use SubmissionStatus::*;
+        let records = vec![
+            record(1, 5, Human, Submitted, Some(10), false),
+            record(1, 6, Human, Submitted, Some(10), false),
+            record(2, 5, AI, Submitted, Some(10), false),
+            record(2, 6, Human, NotSubmitted, None, false),
+        ];
+        let summaries = summarise_turns(&records);
+        assert_eq!(2, summaries.len());
+        assert_eq!(2, summaries[1].turn);
+        assert_eq!(vec![&records[2].nation], summaries[1].went_ai);
+    }
+}
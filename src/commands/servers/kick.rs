@@ -1,46 +1,62 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use log::*;
 use serenity::{
-    framework::standard::{Args, CommandError},
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
 
-use super::alias_from_arg_or_channel_name;
+use super::{
+    alias_from_arg_or_channel_name, permissions::check_can_manage_game,
+    waitlist::promote_from_waitlist,
+};
 use crate::{
     commands::servers::CommandResponse,
     db::{DbConnection, DbConnectionKey},
+    model::game_server::GameAlias,
 };
 
-fn kick_helper(user_id: UserId, alias: &str, db_conn: DbConnection) -> Result<(), CommandError> {
+/// Whether they were in the game
+fn kick_helper(
+    user_id: UserId,
+    alias: &GameAlias,
+    db_conn: DbConnection,
+) -> Result<bool, CommandError> {
     let rows_affected = db_conn
         .remove_player_from_game(alias, user_id)
         .map_err(CommandError::from)?;
-
-    if rows_affected > 0 {
-        Ok(())
-    } else {
-        Err(format!("User is not in game {}", alias).into())
-    }
+    Ok(rows_affected > 0)
 }
 
 pub async fn kick_player(
     context: &Context,
     channel_id: ChannelId,
-    _user_id: UserId,
-    mut args: Args,
+    user_id: UserId,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let target_user_id = UserId::from(args.single_quoted::<u64>()?);
+    let target_user_id = args.required_user("user")?;
 
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
     let db_conn = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
             .ok_or("No db connection")?
             .clone()
     };
-    kick_helper(target_user_id, &alias, db_conn)?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    let anonymous = db_conn.is_game_anonymous(&alias)?;
+    let was_in_game = kick_helper(target_user_id, &alias, db_conn.clone())?;
 
-    let text = format!("Kicked from all nations in game {}", alias);
+    // in an anonymous game, whether they were playing at all is a secret
+    let mut text = if anonymous {
+        format!("Kicked from all nations in game {}, if they had any", alias)
+    } else if was_in_game {
+        format!("Kicked from all nations in game {}", alias)
+    } else {
+        return Err(format!("User is not in game {}", alias).into());
+    };
+    if let Some(promoted_text) = promote_from_waitlist(&context.http, &db_conn, &alias).await? {
+        text = format!("{}\n{}", text, promoted_text);
+    }
     info!("{}", text);
     Ok(CommandResponse::Reply(text))
 }
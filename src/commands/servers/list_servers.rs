@@ -1,15 +1,35 @@
-use crate::commands::servers::CommandResponse;
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::commands::servers::{guild_for_channel, CommandResponse};
 use crate::db::*;
 use crate::model::game_server::GameServerState;
 use serenity::builder::CreateEmbed;
-use serenity::framework::standard::{Args, CommandError};
-use serenity::model::id::{ChannelId, UserId};
+use serenity::model::id::{ChannelId, GuildId, UserId};
 use serenity::prelude::Context;
 
-fn list_servers_helper(db_conn: DbConnection) -> Result<CreateEmbed, CommandError> {
-    let server_list = db_conn.retrieve_all_servers().map_err(CommandError::from)?;
+/// Finished games are only shown if `include_archived`
+fn list_servers_helper(
+    db_conn: DbConnection,
+    option_guild_id: Option<GuildId>,
+    include_archived: bool,
+) -> Result<CreateEmbed, CommandError> {
+    let server_list = db_conn
+        .retrieve_all_servers()
+        .map_err(CommandError::from)?
+        .into_iter()
+        .filter(|server| server.alias.is_visible_from(option_guild_id))
+        .collect::<Vec<_>>();
+    let archived_list = if include_archived {
+        db_conn
+            .archived_games()
+            .map_err(CommandError::from)?
+            .into_iter()
+            .filter(|archived_game| archived_game.alias.is_visible_from(option_guild_id))
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
 
-    if server_list.is_empty() {
+    if server_list.is_empty() && archived_list.is_empty() {
         Ok(CreateEmbed::default().title("NO SERVERS"))
     } else {
         let embed_title = "Servers:";
@@ -28,6 +48,10 @@ fn list_servers_helper(db_conn: DbConnection) -> Result<CreateEmbed, CommandErro
                 }
             }
         }
+        for archived_game in archived_list {
+            server_aliases.push_str(&format!("{} (finished)\n", archived_game.alias));
+            server_addresses.push_str(&format!("{}\n", archived_game.address));
+        }
 
         Ok(CreateEmbed::default()
             .title(embed_title)
@@ -38,16 +62,19 @@ fn list_servers_helper(db_conn: DbConnection) -> Result<CreateEmbed, CommandErro
 
 pub async fn list_servers(
     context: &Context,
-    _channel_id: ChannelId,
+    channel_id: ChannelId,
     _user_id: UserId,
-    _args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
+    // the only choice is `all`
+    let include_archived = args.string("show").is_some();
+    let option_guild_id = guild_for_channel(context, channel_id).await?;
     let db_conn = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
             .ok_or_else(|| CommandError::from("No db connection".to_string()))?
             .clone()
     };
-    let embed = list_servers_helper(db_conn)?;
+    let embed = list_servers_helper(db_conn, option_guild_id, include_archived)?;
     Ok(CommandResponse::Embed(Box::new(embed)))
 }
@@ -1,18 +1,19 @@
-use crate::commands::servers::CommandResponse;
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::commands::servers::{guild_for_channel, CommandResponse};
 use crate::{
     db::*,
     model::game_server::{GameServer, GameServerState},
 };
-use serenity::framework::standard::Args;
 use serenity::model::id::{ChannelId, UserId};
-use serenity::{builder::CreateEmbed, framework::standard::CommandError, prelude::Context};
+use serenity::{builder::CreateEmbed, prelude::Context};
 
 pub async fn lobbies(
     context: &Context,
-    _channel_id: ChannelId,
+    channel_id: ChannelId,
     _user_id: UserId,
-    _args: Args,
+    _args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
+    let option_guild_id = guild_for_channel(context, channel_id).await?;
     let db_conn = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
@@ -20,7 +21,11 @@ pub async fn lobbies(
             .clone()
     };
 
-    let lobbies_and_player_count = db_conn.select_lobbies()?;
+    let lobbies_and_player_count = db_conn
+        .select_lobbies()?
+        .into_iter()
+        .filter(|(lobby, _)| lobby.alias.is_visible_from(option_guild_id))
+        .collect::<Vec<_>>();
     let response = if lobbies_and_player_count.is_empty() {
         CommandResponse::Reply("No available lobbies".to_owned())
     } else {
@@ -43,7 +48,11 @@ fn lobbies_helper(
                 .as_ref()
                 .is_some_and(|x| x.starts_with("#hidden"));
             if !has_hidden_description {
-                aliases.push_str(&format!("{}\n", lobby.alias));
+                if lobby.anonymous {
+                    aliases.push_str(&format!("{} (anonymous)\n", lobby.alias));
+                } else {
+                    aliases.push_str(&format!("{}\n", lobby.alias));
+                }
                 player_counts.push_str(&format!("{}/{}\n", registered_count, state.player_count));
             }
         } else {
@@ -1,23 +1,25 @@
-use serenity::framework::standard::{Args, CommandError};
+use crate::commands::registry::{CommandArgs, CommandError};
 use serenity::model::id::{ChannelId, UserId};
 use serenity::prelude::Context;
 
-use super::alias_from_arg_or_channel_name;
+use super::{alias_from_arg_or_channel_name, dom_version_from_args};
 use crate::commands::servers::CommandResponse;
 use crate::db::*;
 use crate::model::enums::Era;
-use crate::model::game_server::{GameServer, GameServerState, LobbyState};
+use crate::model::game_server::{GameAlias, GameServer, GameServerState, LobbyState};
 
 fn lobby_helper(
     db_conn: DbConnection,
     era: Era,
     player_count: i32,
-    alias: &str,
+    alias: &GameAlias,
     author_id: UserId,
     dom_version: u8,
+    anonymous: bool,
 ) -> Result<(), CommandError> {
     db_conn.insert_game_server(&GameServer {
         dom_version,
+        anonymous,
         alias: alias.to_owned(),
         state: GameServerState::Lobby(LobbyState {
             era,
@@ -33,12 +35,15 @@ pub async fn lobby(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let era_str = args.single_quoted::<String>()?;
+    let era_str = args.required_string("era")?;
     let era = Era::from_string(&era_str).ok_or("unknown era")?;
-    let player_count = args.single_quoted::<i32>()?;
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let player_count = i32::try_from(args.required_integer("number_of_players")?)
+        .map_err(|_| "That's too many players")?;
+    let dom_version = dom_version_from_args(&args);
+    let anonymous = args.string("anonymous").is_some();
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
     let db_connection = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
@@ -46,10 +51,26 @@ pub async fn lobby(
             .clone()
     };
 
-    // all new lobbies are for dom6
-    lobby_helper(db_connection, era, player_count, &alias, user_id, 6)?;
-    Ok(CommandResponse::Reply(format!(
-        "Created game lobby with name {}",
-        alias
-    )))
+    lobby_helper(
+        db_connection,
+        era,
+        player_count,
+        &alias,
+        user_id,
+        dom_version,
+        anonymous,
+    )?;
+    let kind = if dom_version == 5 {
+        "Dominions 5 game lobby"
+    } else {
+        "game lobby"
+    };
+    Ok(CommandResponse::Reply(if anonymous {
+        format!(
+            "Created anonymous {} with name {}. Players have to register by DMing me.",
+            kind, alias
+        )
+    } else {
+        format!("Created {} with name {}", kind, alias)
+    }))
 }
@@ -1,9 +1,13 @@
-use serenity::framework::standard::{Args, CommandError};
+use crate::commands::registry::{CommandArgs, CommandError};
 use serenity::model::id::{ChannelId, UserId};
 use serenity::prelude::Context;
+use std::str::FromStr;
 
+use super::alias_from_arg_or_channel_name;
 use crate::commands::servers::CommandResponse;
 use crate::db::*;
+use crate::model::enums::NotificationKind;
+use crate::model::game_server::GameAlias;
 
 fn notifications_helper(
     db_conn: DbConnection,
@@ -14,13 +18,46 @@ fn notifications_helper(
     Ok(())
 }
 
+fn game_notifications_helper(
+    db_conn: DbConnection,
+    player_id: UserId,
+    alias: &GameAlias,
+    option_kind: Option<NotificationKind>,
+    desired: Option<bool>,
+) -> Result<(), CommandError> {
+    let rows_modified = db_conn.set_game_notifications(alias, player_id, option_kind, desired)?;
+    if rows_modified == 0 {
+        Err(CommandError::from(format!(
+            "You are not registered in {}",
+            alias
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// "default" means go back to whatever the global setting is
+fn parse_desired(text: &str) -> Option<Option<bool>> {
+    if text.eq_ignore_ascii_case("default") {
+        Some(None)
+    } else {
+        bool::from_str(&text.to_lowercase()).ok().map(Some)
+    }
+}
+
 pub async fn notifications(
     context: &Context,
-    _channel_id: ChannelId,
+    channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let desired_turn_notifications = args.single_quoted::<bool>()?;
+    let option_kind = args
+        .string("kind")
+        .as_deref()
+        .and_then(NotificationKind::from_string);
+    let desired = parse_desired(&args.required_string("enabled")?)
+        .ok_or("Expected true, false, or default")?;
+
     let db_conn = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
@@ -28,9 +65,28 @@ pub async fn notifications(
             .clone()
     };
 
-    notifications_helper(db_conn, user_id, desired_turn_notifications)?;
+    // With no game given we keep the old behaviour of changing the setting for every game
+    if args.string("name").is_none() && option_kind.is_none() {
+        let desired_turn_notifications =
+            desired.ok_or("Can only use \"default\" for the notifications of a single game")?;
+        notifications_helper(db_conn, user_id, desired_turn_notifications)?;
+        return Ok(CommandResponse::Reply(format!(
+            "Set turn notifications to {}",
+            desired_turn_notifications
+        )));
+    }
+
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    game_notifications_helper(db_conn, user_id, &alias, option_kind, desired)?;
+
+    let kind_text = option_kind
+        .map(|kind| format!("{} notifications", kind))
+        .unwrap_or_else(|| "notifications".to_owned());
+    let desired_text = desired
+        .map(|desired| desired.to_string())
+        .unwrap_or_else(|| "your default".to_owned());
     Ok(CommandResponse::Reply(format!(
-        "Set turn notifications to {}",
-        desired_turn_notifications
+        "Set {} for {} to {}",
+        kind_text, alias, desired_text
     )))
 }
@@ -0,0 +1,176 @@
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::{guild_for_channel, CommandResponse},
+    db::{DbConnection, DbConnectionKey},
+    model::game_server::GameAlias,
+};
+use serenity::{
+    model::id::{ChannelId, GuildId, RoleId, UserId},
+    prelude::Context,
+    utils::parse_role_mention,
+};
+use std::str::FromStr;
+
+/// Destructive commands can only be used by the game's owner, or admins and people with
+/// the manager role (see `!manager-role`) in the server the game belongs to. Games that
+/// don't belong to a server (e.g. from before games were per-server) can be managed from
+/// whichever server the command is used in, so that they're not stuck without an owner.
+pub async fn check_can_manage_game(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<(), CommandError> {
+    if db_conn.owner_for_game(alias)? == Some(user_id) {
+        return Ok(());
+    }
+    let invoking_guild = match alias.guild_id {
+        Some(_) => None,
+        None => guild_for_channel(context, channel_id).await?,
+    };
+    if let Some(guild_id) = managing_guild(alias, invoking_guild) {
+        if is_guild_manager(context, guild_id, user_id, db_conn).await? {
+            return Ok(());
+        }
+    }
+    Err(CommandError::from(format!(
+        "Only the owner of {}, server admins, or people with the manager role can do that",
+        alias
+    )))
+}
+
+/// The server whose admins and managers can manage the game
+fn managing_guild(alias: &GameAlias, invoking_guild: Option<GuildId>) -> Option<GuildId> {
+    alias.guild_id.or(invoking_guild)
+}
+
+async fn is_guild_admin(
+    context: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<bool, CommandError> {
+    let member = guild_id.member(context, user_id).await?;
+    let guild = guild_id.to_partial_guild(context).await?;
+    Ok(guild.member_permissions(&member).administrator())
+}
+
+async fn is_guild_manager(
+    context: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    db_conn: &DbConnection,
+) -> Result<bool, CommandError> {
+    if is_guild_admin(context, guild_id, user_id).await? {
+        return Ok(true);
+    }
+    let Some(role_id) = db_conn.manager_role_for_guild(guild_id)? else {
+        return Ok(false);
+    };
+    let member = guild_id.member(context, user_id).await?;
+    Ok(member.roles.contains(&role_id))
+}
+
+/// Accepts either a @role mention or the raw role id
+fn parse_role(text: &str) -> Option<RoleId> {
+    parse_role_mention(text).or_else(|| {
+        u64::from_str(text)
+            .ok()
+            .filter(|&id| id != 0)
+            .map(RoleId::new)
+    })
+}
+
+pub async fn manager_role(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let option_role_id = match args.required_string("role")?.as_str() {
+        "off" => None,
+        text => Some(
+            parse_role(text)
+                .ok_or("Expected a role, e.g. `!manager-role @gamemasters`, or `off`")?,
+        ),
+    };
+
+    let guild_id = guild_for_channel(context, channel_id)
+        .await?
+        .ok_or("The manager role can only be set in a server, not a DM")?;
+    if !is_guild_admin(context, guild_id, user_id).await? {
+        return Err(CommandError::from(
+            "Only server admins can set the manager role",
+        ));
+    }
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    db_conn.set_manager_role(guild_id, option_role_id)?;
+
+    let reply = match option_role_id {
+        Some(role_id) => format!(
+            "People with <@&{}> can now manage every game",
+            role_id.get()
+        ),
+        None => "Only game owners and server admins can manage games now".to_owned(),
+    };
+    Ok(CommandResponse::Reply(reply))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::game_server::{GameServer, GameServerState, StartedState},
+        test_harness::TempDb,
+    };
+
+    #[test]
+    fn games_without_a_server_or_owner_are_managed_from_the_invoking_server() {
+        let temp_db = TempDb::new();
+        let alias = GameAlias::new(None, "legacy");
+        temp_db
+            .db_conn
+            .insert_game_server(&GameServer {
+                alias: alias.clone(),
+                state: GameServerState::StartedState(
+                    StartedState {
+                        address: "localhost:3000".to_owned(),
+                        last_seen_turn: 1,
+                    },
+                    None,
+                ),
+                dom_version: 5,
+                anonymous: false,
+            })
+            .unwrap();
+
+        assert_eq!(None, temp_db.db_conn.owner_for_game(&alias).unwrap());
+        assert_eq!(
+            Some(GuildId::new(5)),
+            managing_guild(&alias, Some(GuildId::new(5)))
+        );
+        assert_eq!(None, managing_guild(&alias, None));
+    }
+
+    #[test]
+    fn games_in_a_server_are_managed_from_that_server() {
+        let alias = GameAlias::new(Some(GuildId::new(1)), "fakegame");
+        assert_eq!(
+            Some(GuildId::new(1)),
+            managing_guild(&alias, Some(GuildId::new(5)))
+        );
+    }
+
+    #[test]
+    fn parse_roles() {
+        assert_eq!(Some(RoleId::new(1234)), parse_role("<@&1234>"));
+        assert_eq!(Some(RoleId::new(1234)), parse_role("1234"));
+        assert_eq!(None, parse_role("<@1234>"));
+        assert_eq!(None, parse_role("gamemasters"));
+    }
+}
@@ -0,0 +1,92 @@
+use super::{
+    alias_from_arg_or_channel_name, details::details_helper, permissions::check_can_manage_game,
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::{DbConnection, DbConnectionKey},
+    model::game_server::{GameAlias, GameServerState},
+    DetailsCacheHandle,
+};
+use log::*;
+use serenity::{
+    builder::CreateMessage,
+    http::Http,
+    model::id::{ChannelId, MessageId, UserId},
+    prelude::Context,
+};
+use std::sync::Arc;
+
+/// Deletes a game's old status message. Errors are only logged since the message may
+/// well have been deleted by hand already.
+pub async fn delete_pinned_status(http: &Http, option_pinned: Option<(ChannelId, MessageId)>) {
+    if let Some((channel_id, message_id)) = option_pinned {
+        if let Err(e) = channel_id.delete_message(http, message_id).await {
+            warn!(
+                "Could not delete pinned status message {:?} in {:?}: {:#?}",
+                message_id, channel_id, e
+            );
+        }
+    }
+}
+
+async fn pin_helper(
+    context: &Context,
+    channel_id: ChannelId,
+    db_conn: DbConnection,
+    alias: &GameAlias,
+) -> Result<String, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    if let GameServerState::Lobby(_) = server.state {
+        return Err(CommandError::from(
+            "Can only pin the status of a started game, use !details for lobbies",
+        ));
+    }
+
+    let embed = details_helper(
+        alias,
+        db_conn.clone(),
+        DetailsCacheHandle(Arc::clone(&context.data)),
+        context,
+    )
+    .await?;
+    let option_old_pinned = db_conn.pinned_status_for_game(alias)?;
+    let message = channel_id
+        .send_message(context, CreateMessage::new().embed(embed))
+        .await?;
+    db_conn.set_pinned_status(alias, Some((channel_id, message.id)))?;
+    delete_pinned_status(&context.http, option_old_pinned).await;
+
+    match message.pin(context).await {
+        Ok(()) => Ok(format!(
+            "Pinned the status of {}, it will update whenever the game does",
+            alias
+        )),
+        Err(e) => {
+            info!("Could not pin status message for {}: {:#?}", alias, e);
+            Ok(format!(
+                "Posted the status of {}, it will update whenever the game does. \
+                I couldn't pin it though, I might need the Manage Messages permission.",
+                alias
+            ))
+        }
+    }
+}
+
+pub async fn pin(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    let reply = pin_helper(context, channel_id, db_conn, &alias).await?;
+    Ok(CommandResponse::Reply(reply))
+}
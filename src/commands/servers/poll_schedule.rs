@@ -0,0 +1,315 @@
+//! Works out when each game should next be checked for a new turn. Games close to their
+//! deadline, or that have just had a new turn, get checked often; finished games, long
+//! timers and servers that have been down for a while get checked rarely.
+
+use crate::{
+    config,
+    model::{game_data::GameData, game_server::GameAlias},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// The fastest we'll ever poll a game, however close its deadline is
+const MIN_POLL_INTERVAL_SECS: i64 = 15;
+/// How close to the deadline (either side) we start polling fast
+const NEAR_DEADLINE_MINS: i64 = 10;
+/// How long after a new turn we keep polling fast, to catch the first submissions
+const AFTER_NEW_TURN_MINS: i64 = 10;
+/// Deadlines further away than this are long timers, which get polled less often
+const LONG_TIMER_HOURS: i64 = 24;
+/// How many poll intervals to wait between polls of a long timer game
+const LONG_TIMER_MULTIPLIER: i32 = 5;
+/// How often to poll games that are over, or whose server has been down for ages
+const RARE_POLL_HOURS: i64 = 1;
+
+/// What we learned about a game from polling it
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollOutcome {
+    /// Lobbies have no server to talk to yet
+    Lobby,
+    Started {
+        is_new_turn: bool,
+        turn: i32,
+        turn_deadline: DateTime<Utc>,
+        finished: bool,
+    },
+    Unreachable,
+}
+impl PollOutcome {
+    pub fn from_game_data(game_data: &GameData, is_new_turn: bool) -> Self {
+        PollOutcome::Started {
+            is_new_turn,
+            turn: game_data.turn,
+            turn_deadline: game_data.turn_deadline,
+            finished: game_data.finished,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GameSchedule {
+    next_poll: DateTime<Utc>,
+    last_new_turn: Option<DateTime<Utc>>,
+    unreachable_since: Option<DateTime<Utc>>,
+}
+
+/// When every game is next due. Lives only as long as the polling loop, so after a restart
+/// everything gets polled straight away.
+#[derive(Debug, Default)]
+pub struct PollSchedule {
+    games: HashMap<GameAlias, GameSchedule>,
+}
+
+impl PollSchedule {
+    /// The games that should be polled now. Games we haven't seen before are due straight
+    /// away, and games that have since been deleted are forgotten.
+    pub fn due_games(&mut self, all_games: Vec<GameAlias>, now: DateTime<Utc>) -> Vec<GameAlias> {
+        let mut games = HashMap::with_capacity(all_games.len());
+        let mut due = vec![];
+        for alias in all_games {
+            let schedule = self.games.remove(&alias).unwrap_or(GameSchedule {
+                next_poll: now,
+                last_new_turn: None,
+                unreachable_since: None,
+            });
+            if schedule.next_poll <= now {
+                due.push(alias.clone());
+            }
+            games.insert(alias, schedule);
+        }
+        self.games = games;
+        due
+    }
+
+    pub fn record(&mut self, alias: &GameAlias, outcome: &PollOutcome, now: DateTime<Utc>) {
+        let poll_interval = poll_interval();
+        let schedule = self.games.entry(alias.clone()).or_insert(GameSchedule {
+            next_poll: now,
+            last_new_turn: None,
+            unreachable_since: None,
+        });
+        if let PollOutcome::Started {
+            is_new_turn: true, ..
+        } = outcome
+        {
+            schedule.last_new_turn = Some(now);
+        }
+        schedule.unreachable_since = match outcome {
+            PollOutcome::Unreachable => Some(schedule.unreachable_since.unwrap_or(now)),
+            _ => None,
+        };
+        schedule.next_poll = now
+            + next_poll_delay(
+                outcome,
+                schedule.last_new_turn,
+                schedule.unreachable_since,
+                now,
+                poll_interval,
+            );
+    }
+
+    /// The poll went wrong on our side, e.g. the db, so try again after the normal interval
+    /// without counting it against the server
+    pub fn record_error(&mut self, alias: &GameAlias, now: DateTime<Utc>) {
+        let schedule = self.games.entry(alias.clone()).or_insert(GameSchedule {
+            next_poll: now,
+            last_new_turn: None,
+            unreachable_since: None,
+        });
+        schedule.next_poll = now + poll_interval();
+    }
+
+    /// When the loop should next wake up. Never longer than the poll interval, so that
+    /// newly added games don't have to wait long.
+    pub fn next_wakeup(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let latest = now + poll_interval();
+        self.games
+            .values()
+            .map(|schedule| schedule.next_poll)
+            .min()
+            .map_or(latest, |next_poll| next_poll.clamp(now, latest))
+    }
+}
+
+fn poll_interval() -> Duration {
+    Duration::from_std(config::timings().poll_interval)
+        .expect("poll interval is validated when loading the config")
+}
+
+fn next_poll_delay(
+    outcome: &PollOutcome,
+    last_new_turn: Option<DateTime<Utc>>,
+    unreachable_since: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    poll_interval: Duration,
+) -> Duration {
+    let fast = (poll_interval / 4)
+        .max(Duration::seconds(MIN_POLL_INTERVAL_SECS))
+        .min(poll_interval);
+    let rare = Duration::hours(RARE_POLL_HOURS).max(poll_interval);
+    let slow = poll_interval * LONG_TIMER_MULTIPLIER;
+
+    match outcome {
+        PollOutcome::Lobby => poll_interval,
+        PollOutcome::Unreachable => {
+            let down_for = unreachable_since.map_or(Duration::zero(), |since| now - since);
+            if down_for > Duration::days(1) {
+                rare
+            } else if down_for > Duration::hours(1) {
+                slow
+            } else {
+                poll_interval
+            }
+        }
+        PollOutcome::Started { finished: true, .. } => rare,
+        // still waiting for everybody to pick a nation
+        PollOutcome::Started { turn, .. } if *turn < 1 => poll_interval,
+        PollOutcome::Started { turn_deadline, .. } => {
+            let until_deadline = *turn_deadline - now;
+            let near_deadline = until_deadline.abs() <= Duration::minutes(NEAR_DEADLINE_MINS);
+            let after_new_turn =
+                last_new_turn.is_some_and(|at| now - at <= Duration::minutes(AFTER_NEW_TURN_MINS));
+            if near_deadline || after_new_turn {
+                fast
+            } else if until_deadline < Duration::zero() {
+                // long past the deadline, e.g. the timer is paused
+                poll_interval
+            } else if until_deadline > Duration::hours(LONG_TIMER_HOURS) {
+                // don't sleep through the run up to the deadline
+                slow.min(until_deadline - Duration::minutes(NEAR_DEADLINE_MINS))
+            } else {
+                poll_interval
+                    .min(until_deadline - Duration::minutes(NEAR_DEADLINE_MINS))
+                    .max(fast)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn started(turn_deadline: DateTime<Utc>, is_new_turn: bool) -> PollOutcome {
+        PollOutcome::Started {
+            is_new_turn,
+            turn: 3,
+            turn_deadline,
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn polls_faster_near_the_deadline_and_after_new_turns() {
+        let now = Utc::now();
+        let interval = Duration::seconds(60);
+        let delay = |outcome: &PollOutcome, last_new_turn| {
+            next_poll_delay(outcome, last_new_turn, None, now, interval)
+        };
+
+        let hours_away = started(now + Duration::hours(5), false);
+        assert_eq!(interval, delay(&hours_away, None));
+        assert_eq!(
+            Duration::seconds(15),
+            delay(&hours_away, Some(now - Duration::minutes(2)))
+        );
+        assert_eq!(
+            Duration::seconds(15),
+            delay(&started(now + Duration::minutes(3), false), None)
+        );
+        // the deadline passed but the server hasn't hosted yet
+        assert_eq!(
+            Duration::seconds(15),
+            delay(&started(now - Duration::minutes(1), false), None)
+        );
+        assert_eq!(
+            interval,
+            delay(&started(now - Duration::days(2), false), None)
+        );
+        // coming up to the fast polling window
+        assert_eq!(
+            Duration::seconds(20),
+            delay(&started(now + Duration::seconds(620), false), None)
+        );
+    }
+
+    #[test]
+    fn polls_rarely_for_long_timers_finished_and_dead_games() {
+        let now = Utc::now();
+        let interval = Duration::seconds(60);
+
+        let long_timer = started(now + Duration::days(3), false);
+        assert_eq!(
+            Duration::minutes(5),
+            next_poll_delay(&long_timer, None, None, now, interval)
+        );
+
+        let finished = PollOutcome::Started {
+            is_new_turn: false,
+            turn: 40,
+            turn_deadline: now + Duration::minutes(1),
+            finished: true,
+        };
+        assert_eq!(
+            Duration::hours(1),
+            next_poll_delay(&finished, None, None, now, interval)
+        );
+
+        let just_down = Some(now - Duration::minutes(5));
+        let down_for_days = Some(now - Duration::days(3));
+        assert_eq!(
+            interval,
+            next_poll_delay(&PollOutcome::Unreachable, None, just_down, now, interval)
+        );
+        assert_eq!(
+            Duration::hours(1),
+            next_poll_delay(
+                &PollOutcome::Unreachable,
+                None,
+                down_for_days,
+                now,
+                interval
+            )
+        );
+    }
+
+    #[test]
+    fn new_games_are_due_straight_away() {
+        let now = Utc::now();
+        let game = GameAlias::new(None, "newgame");
+        let mut schedule = PollSchedule::default();
+        assert_eq!(
+            vec![game.clone()],
+            schedule.due_games(vec![game.clone()], now)
+        );
+
+        schedule.record(&game, &PollOutcome::Lobby, now);
+        assert!(schedule.due_games(vec![game.clone()], now).is_empty());
+        assert_eq!(now + Duration::seconds(60), schedule.next_wakeup(now));
+        assert_eq!(
+            vec![game.clone()],
+            schedule.due_games(vec![game], now + Duration::seconds(60))
+        );
+    }
+
+    #[test]
+    fn errors_on_our_side_keep_the_normal_interval() {
+        let now = Utc::now();
+        let game = GameAlias::new(None, "flaky");
+        let mut schedule = PollSchedule::default();
+        schedule.due_games(vec![game.clone()], now);
+
+        let after_interval = now + Duration::seconds(60);
+
+        schedule.record(&game, &PollOutcome::Unreachable, now - Duration::days(3));
+        schedule.record_error(&game, now);
+        assert_eq!(
+            vec![game.clone()],
+            schedule.due_games(vec![game.clone()], after_interval)
+        );
+
+        // whereas the server still being down means it's checked rarely
+        schedule.record(&game, &PollOutcome::Unreachable, now);
+        assert!(schedule.due_games(vec![game], after_interval).is_empty());
+    }
+}
@@ -0,0 +1,432 @@
+//! Lobbies where players rank the nations they'd like, and the bot shares them out so
+//! that everybody is as happy as possible overall
+
+use super::{
+    alias_from_arg_or_channel_name, db_connection,
+    draft::{available_nations, check_not_drafting, lobby_for_alias},
+    permissions::check_can_manage_game,
+    register_player::{check_command_is_private, parse_nation_list, static_nation_name},
+    restrictions::check_nation_allowed,
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::DbConnection,
+    model::{
+        enums::StaticNation,
+        game_server::GameAlias,
+        player::{NotificationSettings, Player},
+    },
+};
+use log::*;
+use rand::seq::SliceRandom;
+use serenity::{
+    http::Http,
+    model::{
+        id::{ChannelId, UserId},
+        mention::Mention,
+    },
+    prelude::Context,
+};
+
+/// Nobody needs to rank every nation in the era
+const MAX_PREFERENCES: usize = 10;
+/// Getting a nation that wasn't on your list at all is worse than getting your last choice
+const UNLISTED_COST: i64 = MAX_PREFERENCES as i64;
+
+fn prefs_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    user_id: UserId,
+    arg_nations: &str,
+) -> Result<String, CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    check_not_drafting(db_conn, alias)?;
+    let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+    if players_nations
+        .iter()
+        .any(|(player, _)| player.discord_user_id == user_id)
+    {
+        return Err("you are already registered for this game".into());
+    }
+    let preferences = db_conn.nation_preferences(alias)?;
+    let is_new = !preferences.iter().any(|(player, _)| *player == user_id);
+    if is_new && (players_nations.len() + preferences.len()) as i32 >= lobby_state.player_count {
+        return Err("lobby already full".into());
+    }
+
+    let nations = parse_nation_list(arg_nations, &lobby_state, server.dom_version)?;
+    if nations.len() > MAX_PREFERENCES {
+        return Err(format!("Only rank up to {} nations", MAX_PREFERENCES).into());
+    }
+    for nation in &nations {
+        check_nation_allowed(db_conn, alias, nation.id, &static_nation_name(nation))?;
+    }
+    let player = Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+        notifications: NotificationSettings::all(true),
+    };
+    let nation_ids = nations.iter().map(|nation| nation.id).collect::<Vec<_>>();
+    db_conn.set_nation_preferences(alias, &player, &nation_ids)?;
+    Ok(format!(
+        "Your choices for {} are: {}. You'll be given a nation when the lobby fills up or the owner uses !assign.",
+        alias,
+        nations
+            .iter()
+            .map(static_nation_name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+/// Which column each row gets, for the smallest total cost. There must be at least as
+/// many columns as rows. This is the Hungarian algorithm.
+fn min_cost_assignment(costs: &[Vec<i64>]) -> Vec<usize> {
+    let rows = costs.len();
+    let columns = costs.first().map_or(0, Vec::len);
+    // 1-indexed, with row/column 0 as a sentinel
+    let mut row_potential = vec![0i64; rows + 1];
+    let mut column_potential = vec![0i64; columns + 1];
+    let mut row_for_column = vec![0usize; columns + 1];
+    let mut previous_column = vec![0usize; columns + 1];
+    for row in 1..=rows {
+        row_for_column[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![i64::MAX; columns + 1];
+        let mut visited = vec![false; columns + 1];
+        loop {
+            visited[column] = true;
+            let current_row = row_for_column[column];
+            let mut delta = i64::MAX;
+            let mut next_column = 0;
+            for j in 1..=columns {
+                if visited[j] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][j - 1]
+                    - row_potential[current_row]
+                    - column_potential[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    previous_column[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next_column = j;
+                }
+            }
+            for j in 0..=columns {
+                if visited[j] {
+                    row_potential[row_for_column[j]] += delta;
+                    column_potential[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            column = next_column;
+            if row_for_column[column] == 0 {
+                break;
+            }
+        }
+        // flip the augmenting path
+        while column != 0 {
+            let previous = previous_column[column];
+            row_for_column[column] = row_for_column[previous];
+            column = previous;
+        }
+    }
+    let mut column_for_row = vec![0; rows];
+    for (column, &row) in row_for_column.iter().enumerate().skip(1) {
+        if row != 0 {
+            column_for_row[row - 1] = column - 1;
+        }
+    }
+    column_for_row
+}
+
+/// Gives everybody one of the available nations, so that overall people get nations as
+/// high up their lists as possible. Ties are broken randomly.
+fn choose_nations(
+    preferences: &[(UserId, Vec<u32>)],
+    available: &[StaticNation],
+) -> Result<Vec<(UserId, StaticNation)>, CommandError> {
+    if available.len() < preferences.len() {
+        return Err("There aren't enough nations left for everybody".into());
+    }
+    let mut rng = rand::thread_rng();
+    let mut players = preferences.iter().collect::<Vec<_>>();
+    players.shuffle(&mut rng);
+    let mut nations = available.to_vec();
+    nations.shuffle(&mut rng);
+
+    let costs = players
+        .iter()
+        .map(|(_, nation_ids)| {
+            nations
+                .iter()
+                .map(|nation| {
+                    nation_ids
+                        .iter()
+                        .position(|&nation_id| nation_id == nation.id)
+                        .map_or(UNLISTED_COST, |rank| rank as i64)
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<i64>>>();
+    Ok(min_cost_assignment(&costs)
+        .into_iter()
+        .zip(players)
+        .map(|(column, (user_id, _))| (*user_id, nations[column]))
+        .collect())
+}
+
+/// Who got what, and where it was on their list
+fn assign_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<Vec<(UserId, StaticNation, Option<usize>)>, CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let preferences = db_conn.nation_preferences(alias)?;
+    if preferences.is_empty() {
+        return Err(format!("Nobody in {} is waiting to be given a nation", alias).into());
+    }
+    let available = available_nations(db_conn, alias, &lobby_state, server.dom_version)?;
+    let chosen = choose_nations(&preferences, &available)?;
+    let assignments = chosen
+        .iter()
+        .map(|(user_id, nation)| (*user_id, nation.id))
+        .collect::<Vec<_>>();
+    db_conn.assign_nations(alias, &assignments)?;
+    Ok(chosen
+        .into_iter()
+        .map(|(user_id, nation)| {
+            let rank = preferences
+                .iter()
+                .find(|(player, _)| *player == user_id)
+                .and_then(|(_, nation_ids)| nation_ids.iter().position(|&id| id == nation.id));
+            (user_id, nation, rank)
+        })
+        .collect())
+}
+
+fn choice_text(option_rank: Option<usize>) -> String {
+    match option_rank {
+        Some(rank) => format!("choice #{}", rank + 1),
+        None => "none of your choices were left".to_owned(),
+    }
+}
+
+/// DMs everybody their nation, and says who got what (unless that's a secret)
+async fn publish_assignments(
+    http: &Http,
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    assigned: &[(UserId, StaticNation, Option<usize>)],
+) -> Result<String, CommandError> {
+    for (user_id, nation, option_rank) in assigned {
+        let text = format!(
+            "You've been given {} in {} ({})",
+            static_nation_name(nation),
+            alias,
+            choice_text(*option_rank)
+        );
+        let result = match user_id.create_dm_channel(http).await {
+            Ok(private_channel) => private_channel.say(http, &text).await.map(|_| ()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            error!("Could not DM {}: {:?}", user_id, err);
+        }
+    }
+    if db_conn.is_game_anonymous(alias)? {
+        return Ok(format!(
+            "Everybody waiting in {} has been given a nation. I've DMed them which.",
+            alias
+        ));
+    }
+    let lines = assigned
+        .iter()
+        .map(|(user_id, nation, option_rank)| {
+            format!(
+                "{}: {} ({})",
+                Mention::from(*user_id),
+                static_nation_name(nation),
+                choice_text(*option_rank)
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok(format!(
+        "Nations for {} have been assigned:\n{}",
+        alias,
+        lines.join("\n")
+    ))
+}
+
+/// Shares out the nations once everybody in the lobby has either ranked nations or joined
+/// as one. Returns what to tell the lobby, if it did.
+pub async fn assign_if_full(
+    context: &Context,
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<Option<String>, CommandError> {
+    let Ok((_, lobby_state)) = lobby_for_alias(db_conn, alias) else {
+        return Ok(None);
+    };
+    let waiting = db_conn.nation_preferences(alias)?.len();
+    let registered = db_conn.players_with_nations_for_game_alias(alias)?.len();
+    if waiting == 0 || ((waiting + registered) as i32) < lobby_state.player_count {
+        return Ok(None);
+    }
+    let assigned = assign_helper(db_conn, alias)?;
+    Ok(Some(
+        publish_assignments(&context.http, db_conn, alias, &assigned).await?,
+    ))
+}
+
+pub async fn prefs(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nations = args.required_string("nations")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_command_is_private(context, channel_id, &alias, &db_conn, "rank nations").await?;
+
+    let mut text = prefs_helper(&db_conn, &alias, user_id, &arg_nations)?;
+    if let Some(assigned_text) = assign_if_full(context, &db_conn, &alias).await? {
+        text = format!("{}\n{}", text, assigned_text);
+    }
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+pub async fn assign(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+
+    let assigned = assign_helper(&db_conn, &alias)?;
+    let text = publish_assignments(&context.http, &db_conn, &alias, &assigned).await?;
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        commands::servers::register_player::is_nation_list_word,
+        model::enums::{Era, Nations},
+        test_harness::TempDb,
+    };
+
+    #[test]
+    fn ranked_players_are_registered_as_their_nations() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = GameAlias::new(None, "fairgame");
+        temp_db.add_lobby(&alias, Era::Middle, 3);
+
+        prefs_helper(db_conn, &alias, UserId::new(2), "tc, bl").unwrap();
+        prefs_helper(db_conn, &alias, UserId::new(3), "tc").unwrap();
+        // only nations from the lobby's era
+        assert!(prefs_helper(db_conn, &alias, UserId::new(4), "ea tc").is_err());
+        // choosing again replaces the list, and puts them at the back
+        prefs_helper(db_conn, &alias, UserId::new(2), "bl, tc").unwrap();
+        assert_eq!(
+            vec![(UserId::new(3), vec![69]), (UserId::new(2), vec![68, 69])],
+            db_conn.nation_preferences(&alias).unwrap()
+        );
+
+        let mut assigned = assign_helper(db_conn, &alias)
+            .unwrap()
+            .into_iter()
+            .map(|(user_id, nation, rank)| (user_id.get(), nation.id, rank))
+            .collect::<Vec<_>>();
+        assigned.sort();
+        assert_eq!(vec![(2, 68, Some(0)), (3, 69, Some(0))], assigned);
+        assert!(db_conn.nation_preferences(&alias).unwrap().is_empty());
+        assert_eq!(
+            2,
+            db_conn
+                .players_with_nations_for_game_alias(&alias)
+                .unwrap()
+                .len()
+        );
+    }
+
+    fn total_cost(costs: &[Vec<i64>], assignment: &[usize]) -> i64 {
+        assignment
+            .iter()
+            .enumerate()
+            .map(|(row, &column)| costs[row][column])
+            .sum()
+    }
+
+    #[test]
+    fn assignment_has_the_smallest_total_cost() {
+        let costs = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        let assignment = min_cost_assignment(&costs);
+        assert_eq!(5, total_cost(&costs, &assignment));
+        assert_eq!(vec![1, 0, 2], assignment);
+
+        // more nations than players
+        let costs = vec![vec![0, 1, 10, 10], vec![0, 10, 10, 1]];
+        let assignment = min_cost_assignment(&costs);
+        assert_eq!(1, total_cost(&costs, &assignment));
+    }
+
+    #[test]
+    fn everybody_gets_a_different_nation_as_high_up_their_list_as_possible() {
+        let available = Nations::search_6("", Some(Era::Middle))
+            .into_iter()
+            .map(|nation_match| nation_match.nation)
+            .collect::<Vec<_>>();
+        // everybody wants TC, but only one of them has nothing else they like
+        let preferences = vec![
+            (UserId::new(1), vec![69, 68]),
+            (UserId::new(2), vec![69]),
+            (UserId::new(3), vec![69, 56]),
+        ];
+        for _ in 0..10 {
+            let mut chosen = choose_nations(&preferences, &available)
+                .unwrap()
+                .into_iter()
+                .map(|(user_id, nation)| (user_id.get(), nation.id))
+                .collect::<Vec<_>>();
+            chosen.sort();
+            assert_eq!(vec![(1, 68), (2, 69), (3, 56)], chosen);
+        }
+        assert!(choose_nations(&preferences, &available[..2]).is_err());
+    }
+
+    #[test]
+    fn nation_lists_stop_before_the_game_name() {
+        assert!(is_nation_list_word("tien,"));
+        assert!(is_nation_list_word("ermor"));
+        assert!(is_nation_list_word("LA"));
+        assert!(!is_nation_list_word("samog"));
+
+        let words = ["tien,", "ermor,", "LA", "rlyeh", "samog"]
+            .iter()
+            .map(|word| word.to_string())
+            .collect();
+        let args = crate::commands::servers::find_command("prefs")
+            .unwrap()
+            .args_from_words(words)
+            .unwrap();
+        assert_eq!(
+            Some("tien, ermor, LA rlyeh".to_owned()),
+            args.string("nations")
+        );
+        assert_eq!(Some("samog".to_owned()), args.string("name"));
+    }
+}
@@ -1,27 +1,98 @@
+use crate::commands::registry::{CommandArgs, CommandError, PrivateCommandError};
 use crate::commands::servers::CommandResponse;
 use crate::{
-    commands::servers::{alias_from_arg_or_channel_name, details::started_details_from_server},
+    commands::servers::{
+        alias_from_arg_or_channel_name, details::started_details_from_server,
+        draft::check_not_drafting, guild_for_channel, prefs::assign_if_full,
+        restrictions::check_nation_allowed, waitlist::join_waitlist_helper,
+    },
     db::{DbConnection, DbConnectionKey},
     model::{
         enums::*,
-        game_server::GameServerState,
+        game_server::{GameAlias, GameServerState, LobbyState, StartedState},
         game_state::*,
         nation::{BotNationIdentifier, GameNationIdentifier},
-        player::Player,
+        player::{NotificationSettings, Player},
     },
     snek::SnekGameStatus,
     DetailsCacheHandle,
 };
-use anyhow::anyhow;
 use either::Either;
 use log::*;
 use serenity::model::id::ChannelId;
-use serenity::{
-    framework::standard::{Args, CommandError},
-    model::id::UserId,
-    prelude::Context,
-};
-use std::{borrow::Cow, cmp::Ordering, str::FromStr, sync::Arc};
+use serenity::{model::id::UserId, prelude::Context};
+use std::{borrow::Cow, str::FromStr, sync::Arc};
+
+pub(super) fn search_nations(
+    arg_nation_name: &str,
+    era: Option<Era>,
+    dom_version: u8,
+) -> Result<Vec<NationMatch>, CommandError> {
+    match dom_version {
+        5 => Ok(Nations::search(arg_nation_name, era)),
+        6 => Ok(Nations::search_6(arg_nation_name, era)),
+        _ => Err(CommandError::from(format!("Dom {} lol", dom_version))),
+    }
+}
+
+pub(super) fn search_nations_in_game<'a>(
+    arg_nation_name: &str,
+    players: &'a [PotentialPlayer],
+    era: Option<Era>,
+    dom_version: u8,
+) -> Result<&'a PotentialPlayer, CommandError> {
+    let nation_matches = search_nations(arg_nation_name, era, dom_version)?
+        .into_iter()
+        .filter(|nation_match| {
+            players
+                .iter()
+                .any(|player| player.nation_id() == Some(nation_match.nation.id))
+        })
+        .collect();
+    match NationSearch::from_matches(nation_matches) {
+        NationSearch::Found(nation) => players
+            .iter()
+            .find(|player| player.nation_id() == Some(nation.id))
+            .ok_or_else(|| CommandError::from("nation vanished from the game")),
+        NationSearch::Ambiguous(nations) => Err(ambiguous_nation_error(
+            arg_nation_name,
+            nations.iter().map(static_nation_name),
+        )),
+        NationSearch::NotFound => Err(CommandError::from(format!(
+            "Could not find nation starting with \"{}\"",
+            arg_nation_name
+        ))),
+    }
+}
+
+fn unregistered_nation(
+    potential_player: &PotentialPlayer,
+) -> Result<GameNationIdentifier, CommandError> {
+    match potential_player {
+        PotentialPlayer::GameOnly(player_details) => Ok(player_details.nation_identifier.clone()),
+        PotentialPlayer::RegisteredAndGame(_, _) => Err("Nation already registered".into()),
+        PotentialPlayer::RegisteredOnly(_, _) => Err("Nation already registered".into()),
+    }
+}
+
+pub(super) fn static_nation_name(nation: &StaticNation) -> String {
+    format!("{} {} ({})", nation.era, nation.name, nation.id)
+}
+
+pub(super) fn ambiguous_nation_error<S: AsRef<str>>(
+    arg_nation_name: &str,
+    nation_names: impl Iterator<Item = S>,
+) -> CommandError {
+    let suggestions = nation_names
+        .take(MAX_NATION_SUGGESTIONS)
+        .map(|name| name.as_ref().to_owned())
+        .collect::<Vec<_>>()
+        .join(", ");
+    CommandError::from(format!(
+        "\"{}\" could be more than one nation. Did you mean one of: {}? Consider using !register-id if the name is ambiguous.",
+        arg_nation_name, suggestions
+    ))
+}
 
 // Find an uploaded/playing nation
 fn get_nation_for_started_server(
@@ -61,38 +132,34 @@ fn get_nation_for_started_server(
                                             None => false,
                                         }
                                     }) {
-                                        match nation {
-                                            PotentialPlayer::GameOnly(player_details) =>
-                                                Ok(player_details.nation_identifier.clone()),
-                                            PotentialPlayer::RegisteredAndGame(_, _) =>
-                                                Err("Nation already registered".into()),
-                                            PotentialPlayer::RegisteredOnly(_, _) =>
-                                                Err("Nation already registered".into()),
-                                        }
+                                        unregistered_nation(nation)
                                     } else {
                                         Err(CommandError::from(format!("Could not find nation starting with \"{}\"", arg_nation_name)))
                                     }
                                 }
                                 Err(_) => {
-                                    Err(CommandError::from(format!("Could not find nation starting with \"{}\"", arg_nation_name)))
+                                    // Maybe it's an acronym, nickname or typo of one of the nations in the game
+                                    let ingame_nation = search_nations_in_game(
+                                        arg_nation_name,
+                                        &playing_state.players,
+                                        era,
+                                        dom_version,
+                                    )?;
+                                    unregistered_nation(ingame_nation)
                                 }
                             }
                         }
                         // Found nation!
                         1 => {
-                            match possible_ingame_nations[0] {
-                                PotentialPlayer::GameOnly(player_details) => Ok(player_details.nation_identifier.clone()),
-                                PotentialPlayer::RegisteredAndGame(_, _) =>
-                                    Err("Nation already registered".into()),
-                                PotentialPlayer::RegisteredOnly(_, _) =>
-                                    Err("Nation already registered".into()),
-                            }
+                            unregistered_nation(possible_ingame_nations[0])
                         },
                         // Ambiguous nation. Error.
-                        _ => Err(CommandError::from(format!(
-                            "Found more than one nation starting with \"{}\". Consider using !register-id if the name is ambiguous.",
-                            arg_nation_name
-                        ))),
+                        _ => Err(ambiguous_nation_error(
+                            arg_nation_name,
+                            possible_ingame_nations.iter().map(|potential_player| {
+                                potential_player.nation_name(option_snek_state)
+                            }),
+                        )),
                     }
                 }
                 StartedStateDetails::Uploading(uploading_state) => {
@@ -112,18 +179,17 @@ fn get_nation_for_started_server(
                     match possible_ingame_nations.len() {
                         // Could not find nation. Try again with base nations.
                         0 => {
-                            let possible_base_nations = match dom_version {
-                                5 => Nations::from_name_prefix(arg_nation_name, era),
-                                6 => Nations::from_name_prefix_6(arg_nation_name, era),
-                                _ => return Err(CommandError::from(format!("Dom {} lol", dom_version))),
-                            };
-                            match possible_base_nations.len() {
-                                0 => Err(CommandError::from(format!("Could not find nation starting with \"{}\"", arg_nation_name))),
-                                1 => Ok(GameNationIdentifier::Existing(possible_base_nations[0])),
-                                _ => Err(CommandError::from(format!(
-                                    "Found more than one nation starting with \"{}\". Consider using !register-id if the name is ambiguous.",
-                                    arg_nation_name
-                                ))),
+                            let possible_base_nations =
+                                search_nations(arg_nation_name, era, dom_version)?;
+                            match NationSearch::from_matches(possible_base_nations) {
+                                NationSearch::NotFound => Err(CommandError::from(format!("Could not find nation starting with \"{}\"", arg_nation_name))),
+                                NationSearch::Found(nation) => {
+                                    Ok(GameNationIdentifier::Existing(nation))
+                                }
+                                NationSearch::Ambiguous(nations) => Err(ambiguous_nation_error(
+                                    arg_nation_name,
+                                    nations.iter().map(static_nation_name),
+                                )),
                             }
                         },
 
@@ -131,17 +197,15 @@ fn get_nation_for_started_server(
                         // Found nation!
                         1 => {
                             let found_nation = possible_ingame_nations[0];
-                            match found_nation {
-                                PotentialPlayer::GameOnly(player_details) => Ok(player_details.nation_identifier.clone()),
-                                PotentialPlayer::RegisteredAndGame(_, _) => Err("Nation already registered".into()),
-                                PotentialPlayer::RegisteredOnly(_, _) => Err("Nation already registered".into()),
-                            }
+                            unregistered_nation(found_nation)
                         },
                         // Ambiguous nation. Error.
-                        _ => Err(CommandError::from(format!(
-                            "Found more than one nation starting with \"{}\". Consider using !register-id if the name is ambiguous.",
-                            arg_nation_name
-                        ))),
+                        _ => Err(ambiguous_nation_error(
+                            arg_nation_name,
+                            possible_ingame_nations.iter().map(|potential_player| {
+                                potential_player.nation_name(option_snek_state)
+                            }),
+                        )),
 
                     }
                 }
@@ -197,24 +261,20 @@ fn get_nation_for_started_server(
     }
 }
 
-fn get_nation_for_lobby(
+pub(super) fn get_nation_for_lobby(
     arg_nation: Either<&str, u32>,
     era: Era,
     dom_version: u8,
 ) -> Result<GameNationIdentifier, CommandError> {
     match arg_nation {
         Either::Left(arg_nation_name) => {
-            let nations = match dom_version {
-                5 => Nations::from_name_prefix(arg_nation_name, Some(era)),
-                6 => Nations::from_name_prefix_6(arg_nation_name, Some(era)),
-                _ => return Err(CommandError::from(format!("Dom {} lol", dom_version))),
-            };
-            match nations.len().cmp(&1) {
-                Ordering::Greater => Err(CommandError::from(format!(
-                    "ambiguous nation name: {}",
-                    arg_nation_name
-                ))),
-                Ordering::Less => {
+            let nations = search_nations(arg_nation_name, Some(era), dom_version)?;
+            match NationSearch::from_matches(nations) {
+                NationSearch::Ambiguous(nations) => Err(ambiguous_nation_error(
+                    arg_nation_name,
+                    nations.iter().map(static_nation_name),
+                )),
+                NationSearch::NotFound => {
                     // try to parse the name as a number
                     let mk_err = || {
                         CommandError::from(format!("Could not find nation: {}. Use register-custom or register-id for mod nations", arg_nation_name))
@@ -227,9 +287,7 @@ fn get_nation_for_lobby(
                             _ => Err(CommandError::from(format!("Dom {} lol", dom_version))),
                         })
                 }
-                Ordering::Equal => Ok(GameNationIdentifier::Existing(
-                    *nations.first().ok_or_else(|| anyhow!("nation vec empty"))?,
-                )),
+                NationSearch::Found(nation) => Ok(GameNationIdentifier::Existing(nation)),
             }
         }
         Either::Right(arg_nation_id) => Ok(match dom_version {
@@ -240,10 +298,92 @@ fn get_nation_for_lobby(
     }
 }
 
+/// Whether a word could be part of a list of nations, rather than the game's name after
+/// it. The list is comma separated, so anything ending in a comma is part of it.
+pub fn is_nation_list_word(word: &str) -> bool {
+    let is_good_match = |quality| quality < MatchQuality::Typo(0);
+    word.ends_with(',')
+        || Era::from_string(word).is_some()
+        || u32::from_str(word).is_ok()
+        || Nations::search_6(word, None)
+            .into_iter()
+            .chain(Nations::search(word, None))
+            .any(|nation_match| is_good_match(nation_match.quality))
+}
+
+/// The lobby's nations in a comma separated list, e.g. "tien, ermor, pythium"
+pub(super) fn parse_nation_list(
+    arg_nations: &str,
+    lobby_state: &LobbyState,
+    dom_version: u8,
+) -> Result<Vec<StaticNation>, CommandError> {
+    let mut nations: Vec<StaticNation> = vec![];
+    for arg_nation in arg_nations
+        .split(',')
+        .map(str::trim)
+        .filter(|arg_nation| !arg_nation.is_empty())
+    {
+        let nation =
+            match get_nation_for_lobby(Either::Left(arg_nation), lobby_state.era, dom_version)? {
+                GameNationIdentifier::Existing(nation) if nation.era == lobby_state.era => nation,
+                _ => {
+                    return Err(format!(
+                        "\"{}\" isn't an {} nation in this lobby",
+                        arg_nation, lobby_state.era
+                    )
+                    .into())
+                }
+            };
+        if nations.contains(&nation) {
+            return Err(format!("{} is in the list twice", static_nation_name(&nation)).into());
+        }
+        nations.push(nation);
+    }
+    if nations.is_empty() {
+        return Err("Give at least one nation".into());
+    }
+    Ok(nations)
+}
+
+/// Whether there's no room for the player. Anybody waiting to be given a nation already has
+/// a place, and can swap to joining as a nation of their choice.
+fn lobby_is_full(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    user_id: UserId,
+    players_nations: &[(Player, BotNationIdentifier)],
+    lobby_state: &LobbyState,
+) -> Result<bool, CommandError> {
+    let preferences = db_conn.nation_preferences(alias)?;
+    if preferences.iter().any(|(player, _)| *player == user_id) {
+        return Ok(false);
+    }
+    Ok((players_nations.len() + preferences.len()) as i32 >= lobby_state.player_count)
+}
+
+/// Registering for an anonymous game where everybody can see it would give away who is
+/// playing which nation. `action` is what to do by DM instead, e.g. "register for it".
+pub(super) async fn check_command_is_private(
+    context: &Context,
+    channel_id: ChannelId,
+    alias: &GameAlias,
+    db_conn: &DbConnection,
+    action: &str,
+) -> Result<(), CommandError> {
+    if db_conn.is_game_anonymous(alias)? && guild_for_channel(context, channel_id).await?.is_some()
+    {
+        return Err(Box::new(PrivateCommandError(format!(
+            "{} is anonymous, so {} by DMing me instead",
+            alias, action
+        ))));
+    }
+    Ok(())
+}
+
 async fn register_custom_helper(
     user_id: UserId,
     arg_custom_nation: String,
-    alias: String,
+    alias: GameAlias,
     db_conn: DbConnection,
 ) -> Result<String, CommandError> {
     info!(
@@ -256,14 +396,14 @@ async fn register_custom_helper(
     }
 
     let server = db_conn.game_for_alias(&alias).map_err(CommandError::from)?;
+    // there's only a waitlist until the game starts
+    let has_waitlist = matches!(server.state, GameServerState::Lobby(_));
 
     match server.state {
         GameServerState::Lobby(lobby_state)
         | GameServerState::StartedState(_, Some(lobby_state)) => {
+            check_not_drafting(&db_conn, &alias)?;
             let players_nations = db_conn.players_with_nations_for_game_alias(&alias)?;
-            if players_nations.len() as i32 >= lobby_state.player_count {
-                return Err(CommandError::from("lobby already full"));
-            };
             if players_nations
                 .iter()
                 .any(|(p, _)| p.discord_user_id == user_id)
@@ -272,6 +412,17 @@ async fn register_custom_helper(
                     "you are already registered for this game",
                 ));
             }
+            if lobby_is_full(&db_conn, &alias, user_id, &players_nations, &lobby_state)? {
+                if !has_waitlist {
+                    return Err(CommandError::from("lobby already full"));
+                }
+                return join_waitlist_helper(
+                    &db_conn,
+                    &alias,
+                    user_id,
+                    BotNationIdentifier::CustomName(arg_custom_nation),
+                );
+            };
 
             let register_message = format!(
                 "Registered {}. Until Illwinter fix modded nation status, you will always show up as having not submitted and will still get pings when you go AI unless you unregister.",
@@ -281,6 +432,7 @@ async fn register_custom_helper(
             let player = Player {
                 discord_user_id: user_id,
                 turn_notifications: true,
+                notifications: NotificationSettings::all(true),
             };
             db_conn
                 .insert_player_into_server(&player, &server.alias, nation)
@@ -293,10 +445,38 @@ async fn register_custom_helper(
     }
 }
 
+/// Who is in a started game, from the last time we polled it
+pub async fn started_state_details(
+    db_conn: DbConnection,
+    started_state: &StartedState,
+    option_lobby_state: Option<&LobbyState>,
+    alias: &GameAlias,
+    details_read_handle: &DetailsCacheHandle,
+) -> Result<(StartedStateDetails, Option<SnekGameStatus>), CommandError> {
+    let cache = details_read_handle.get_clone(alias).await?;
+    let game_details: GameDetails = started_details_from_server(
+        db_conn,
+        started_state,
+        option_lobby_state,
+        alias,
+        &cache.game_data,
+        cache.option_snek_state.as_ref(),
+    )?;
+    match game_details.nations {
+        NationDetails::Lobby(_) => Err(CommandError::from(
+            "Somehow found lobby details in a started server? This should never happen!!!",
+        )),
+        NationDetails::Started(started_details) => Ok((
+            started_details.state,
+            game_details.cache_entry.and_then(|i| i.option_snek_state),
+        )),
+    }
+}
+
 async fn register_player_helper(
     user_id: UserId,
     arg_nation: Either<&str, u32>,
-    alias: &str,
+    alias: &GameAlias,
     db_conn: DbConnection,
     details_read_handle: DetailsCacheHandle,
 ) -> Result<String, CommandError> {
@@ -308,10 +488,8 @@ async fn register_player_helper(
 
     match server.state {
         GameServerState::Lobby(lobby_state) => {
+            check_not_drafting(&db_conn, alias)?;
             let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
-            if players_nations.len() as i32 >= lobby_state.player_count {
-                return Err(CommandError::from("lobby already full"));
-            };
             if players_nations
                 .iter()
                 .any(|(p, _)| p.discord_user_id == user_id)
@@ -322,6 +500,7 @@ async fn register_player_helper(
             }
 
             let nation = get_nation_for_lobby(arg_nation, lobby_state.era, server.dom_version)?;
+            check_nation_allowed(&db_conn, alias, nation.id(), &nation.name(None))?;
 
             if players_nations.iter().any(|(_, player_nation_id)| {
                 let nation_id: BotNationIdentifier = nation.clone().into();
@@ -329,9 +508,13 @@ async fn register_player_helper(
             }) {
                 return Err(CommandError::from("Nation already exists in lobby"));
             }
+            if lobby_is_full(&db_conn, alias, user_id, &players_nations, &lobby_state)? {
+                return join_waitlist_helper(&db_conn, alias, user_id, nation.into());
+            };
             let player = Player {
                 discord_user_id: user_id,
                 turn_notifications: true,
+                notifications: NotificationSettings::all(true),
             };
             db_conn
                 .insert_player_into_server(&player, &server.alias, nation.clone().into())
@@ -340,30 +523,14 @@ async fn register_player_helper(
         }
         GameServerState::StartedState(started_state, option_lobby_state) => {
             let option_lobby_state_ref = &option_lobby_state;
-            let started_db_conn = db_conn.clone();
-            let (started_details, option_snek_state) = details_read_handle
-                .get_clone(alias)
-                .await
-                .map_err(CommandError::from)
-                .and_then(move |cache| {
-                    let game_details: GameDetails = started_details_from_server(
-                        started_db_conn,
-                        &started_state,
-                        option_lobby_state_ref.as_ref(),
-                        alias,
-                        &cache.game_data,
-                        cache.option_snek_state.as_ref(),
-                    )?;
-                    Ok(game_details)
-                })
-                .map_err(CommandError::from)
-                .and_then(|game_details| match game_details.nations {
-                    NationDetails::Lobby(_) => Err(CommandError::from("Somehow found lobby details in a started server? This should never happen!!!")),
-                    NationDetails::Started(started_details) => Ok((
-                        started_details.state,
-                        game_details.cache_entry.and_then(|i| i.option_snek_state),
-                    )),
-                })?;
+            let (started_details, option_snek_state) = started_state_details(
+                db_conn.clone(),
+                &started_state,
+                option_lobby_state_ref.as_ref(),
+                alias,
+                &details_read_handle,
+            )
+            .await?;
 
             if started_details
                 .uploaded_players()
@@ -387,6 +554,7 @@ async fn register_player_helper(
             let player = Player {
                 discord_user_id: user_id,
                 turn_notifications: true,
+                notifications: NotificationSettings::all(true),
             };
             db_conn
                 .insert_player_into_server(&player, &server.alias, nation.clone().into())
@@ -401,9 +569,10 @@ pub async fn register_player_id(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let arg_nation_id: u32 = args.single_quoted::<u32>()?;
+    let arg_nation_id = u32::try_from(args.required_integer("nation_id")?)
+        .map_err(|_| "Nation IDs can't be negative")?;
     if arg_nation_id >= i32::MAX as u32 {
         return Err(format!(
             "Nation ID {} too large. Your hilarious joke will have to be less than 2^31-1.",
@@ -411,7 +580,7 @@ pub async fn register_player_id(
         )
         .into());
     }
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
     let details_read_handle = DetailsCacheHandle(Arc::clone(&context.data));
 
     let db_conn = {
@@ -420,15 +589,19 @@ pub async fn register_player_id(
             .ok_or("no db connection")?
             .clone()
     };
+    check_command_is_private(context, channel_id, &alias, &db_conn, "register for it").await?;
 
-    let reply = register_player_helper(
+    let mut reply = register_player_helper(
         user_id,
         Either::Right(arg_nation_id),
         &alias,
-        db_conn,
+        db_conn.clone(),
         details_read_handle,
     )
     .await?;
+    if let Some(assigned_text) = assign_if_full(context, &db_conn, &alias).await? {
+        reply = format!("{}\n{}", reply, assigned_text);
+    }
     Ok(CommandResponse::Reply(reply))
 }
 
@@ -436,10 +609,10 @@ pub async fn register_player_custom(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let arg_nation_name: String = args.single_quoted::<String>()?;
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let arg_nation_name = args.required_string("nation_text")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
 
     let db_conn = {
         let data = context.data.read().await;
@@ -447,8 +620,13 @@ pub async fn register_player_custom(
             .ok_or("no db connection")?
             .clone()
     };
+    check_command_is_private(context, channel_id, &alias, &db_conn, "register for it").await?;
 
-    let reply = register_custom_helper(user_id, arg_nation_name, alias, db_conn).await?;
+    let mut reply =
+        register_custom_helper(user_id, arg_nation_name, alias.clone(), db_conn.clone()).await?;
+    if let Some(assigned_text) = assign_if_full(context, &db_conn, &alias).await? {
+        reply = format!("{}\n{}", reply, assigned_text);
+    }
     Ok(CommandResponse::Reply(reply))
 }
 
@@ -456,10 +634,10 @@ pub async fn register_player(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let arg_nation_name: String = args.single_quoted::<String>()?.to_lowercase();
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let arg_nation_name = args.required_string("nation_name_prefix")?.to_lowercase();
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
 
     let details_read_handle = DetailsCacheHandle(Arc::clone(&context.data));
     let db_conn = {
@@ -468,14 +646,18 @@ pub async fn register_player(
             .ok_or("no db connection")?
             .clone()
     };
+    check_command_is_private(context, channel_id, &alias, &db_conn, "register for it").await?;
 
-    let reply = register_player_helper(
+    let mut reply = register_player_helper(
         user_id,
         Either::Left(&arg_nation_name),
         &alias,
-        db_conn,
+        db_conn.clone(),
         details_read_handle,
     )
     .await?;
+    if let Some(assigned_text) = assign_if_full(context, &db_conn, &alias).await? {
+        reply = format!("{}\n{}", reply, assigned_text);
+    }
     Ok(CommandResponse::Reply(reply))
 }
@@ -0,0 +1,149 @@
+use super::alias_from_arg_or_channel_name;
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::{DbConnection, DbConnectionKey},
+    model::game_server::GameAlias,
+};
+use serenity::{
+    model::id::{ChannelId, UserId},
+    prelude::Context,
+};
+use std::str::FromStr;
+
+const MAX_REMINDERS: usize = 5;
+const MAX_REMINDER_MINUTES: i32 = 14 * 24 * 60;
+
+/// What `!remind` takes: `off`, or some times before the deadline
+pub fn is_reminder_word(word: &str) -> bool {
+    word == "off" || parse_reminder_duration(word).is_some()
+}
+
+/// Parses things like "6h", "30m", "2d" or "1h30m" into a number of minutes
+fn parse_reminder_duration(text: &str) -> Option<i32> {
+    let mut total_minutes = 0;
+    let mut digits = String::new();
+    for c in text.to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+        } else {
+            let count = i32::from_str(&digits).ok()?;
+            digits.clear();
+            let mult = match c {
+                'd' => 24 * 60,
+                'h' => 60,
+                'm' => 1,
+                _ => return None,
+            };
+            total_minutes += count.checked_mul(mult)?;
+        }
+    }
+    if !digits.is_empty() || total_minutes <= 0 {
+        return None;
+    }
+    Some(total_minutes)
+}
+
+fn format_reminder_duration(minutes: i32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, mins) => format!("{}m", mins),
+        (hours, 0) => format!("{}h", hours),
+        (hours, mins) => format!("{}h{}m", hours, mins),
+    }
+}
+
+fn remind_helper(
+    db_conn: DbConnection,
+    user_id: UserId,
+    alias: &GameAlias,
+    mut minutes_before: Vec<i32>,
+) -> Result<String, CommandError> {
+    let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+    if !players_nations
+        .iter()
+        .any(|(player, _)| player.discord_user_id == user_id)
+    {
+        return Err(CommandError::from(format!(
+            "You are not registered in {}",
+            alias
+        )));
+    }
+
+    minutes_before.sort_unstable_by(|a, b| b.cmp(a));
+    minutes_before.dedup();
+    db_conn.set_reminders(alias, user_id, &minutes_before)?;
+
+    if minutes_before.is_empty() {
+        Ok(format!("Removed your reminders for {}", alias))
+    } else {
+        Ok(format!(
+            "You'll be reminded {} before the deadline in {} if you haven't submitted",
+            minutes_before
+                .into_iter()
+                .map(format_reminder_duration)
+                .collect::<Vec<_>>()
+                .join(" and "),
+            alias,
+        ))
+    }
+}
+
+pub async fn remind(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let before_deadline = args.required_string("before_deadline")?;
+    let mut minutes_before = vec![];
+    if before_deadline != "off" {
+        for word in before_deadline.split_whitespace() {
+            minutes_before.push(parse_reminder_duration(word).ok_or_else(|| {
+                CommandError::from(
+                    "Give at least one time before the deadline e.g. `!remind 6h 1h`, or `!remind off`",
+                )
+            })?);
+        }
+    }
+    if minutes_before.len() > MAX_REMINDERS {
+        return Err(CommandError::from(format!(
+            "You can have at most {} reminders per game",
+            MAX_REMINDERS
+        )));
+    }
+    if minutes_before
+        .iter()
+        .any(|&minutes| minutes > MAX_REMINDER_MINUTES)
+    {
+        return Err(CommandError::from(
+            "Reminders can be at most 14 days before the deadline",
+        ));
+    }
+
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = {
+        let data = context.data.read().await;
+        data.get::<DbConnectionKey>()
+            .ok_or("No DbConnection was created on startup. This is a bug.")?
+            .clone()
+    };
+    let reply = remind_helper(db_conn, user_id, &alias, minutes_before)?;
+    Ok(CommandResponse::Reply(reply))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Some(6 * 60), parse_reminder_duration("6h"));
+        assert_eq!(Some(30), parse_reminder_duration("30m"));
+        assert_eq!(Some(2 * 24 * 60), parse_reminder_duration("2D"));
+        assert_eq!(Some(90), parse_reminder_duration("1h30m"));
+        assert_eq!(None, parse_reminder_duration("6"));
+        assert_eq!(None, parse_reminder_duration("h"));
+        assert_eq!(None, parse_reminder_duration("0h"));
+        assert_eq!(None, parse_reminder_duration("mygame"));
+    }
+}
@@ -1,9 +1,12 @@
-use serenity::framework::standard::{Args, CommandError};
+use crate::commands::registry::{CommandArgs, CommandError};
 use serenity::prelude::Context;
 
-use super::alias_from_arg_or_channel_name;
+use super::{
+    alias_from_arg_or_channel_name, permissions::check_can_manage_game, pin::delete_pinned_status,
+};
 use crate::commands::servers::CommandResponse;
 use crate::db::*;
+use crate::model::game_server::GameAlias;
 use crate::{DetailsCacheHandle, DetailsCacheKey};
 use log::error;
 use serenity::model::id::{ChannelId, UserId};
@@ -12,7 +15,7 @@ use std::sync::Arc;
 async fn remove_server_helper(
     details_cache_handle: DetailsCacheHandle,
     db_conn: DbConnection,
-    alias: &str,
+    alias: &GameAlias,
 ) -> Result<(), CommandError> {
     // Okay there is a bit of a race condition here, where if the turn check gets the alias,
     // then this runs and deletes it from the db and cache, and then the turn check finishes
@@ -35,15 +38,10 @@ async fn remove_server_helper(
 pub async fn remove_server(
     context: &Context,
     channel_id: ChannelId,
-    _user_id: UserId,
-    mut args: Args,
+    user_id: UserId,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
 
     let write_handle_mutex = DetailsCacheHandle(Arc::clone(&context.data));
     let db_conn = {
@@ -52,7 +50,10 @@ pub async fn remove_server(
             .ok_or("No DB connection")?
             .clone()
     };
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    let option_pinned = db_conn.pinned_status_for_game(&alias)?;
     remove_server_helper(write_handle_mutex, db_conn, &alias).await?;
+    delete_pinned_status(&context.http, option_pinned).await;
     Ok(CommandResponse::Reply(format!(
         "successfully removed server {}",
         alias
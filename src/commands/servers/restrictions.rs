@@ -0,0 +1,237 @@
+//! Lobby owners banning nations, or only allowing some, e.g. for tournament rules
+
+use super::{
+    alias_from_arg_or_channel_name, db_connection,
+    draft::lobby_for_alias,
+    permissions::check_can_manage_game,
+    register_player::{is_nation_list_word, parse_nation_list, static_nation_name},
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::DbConnection,
+    model::{enums::StaticNation, game_server::GameAlias},
+};
+use log::*;
+use serenity::{
+    model::id::{ChannelId, UserId},
+    prelude::Context,
+};
+
+/// `off` clears the allowed list, so it's part of the option too
+pub fn is_allow_list_word(word: &str) -> bool {
+    word == "off" || is_nation_list_word(word)
+}
+
+/// Errors if the lobby's owner doesn't let players have the nation
+pub(super) fn check_nation_allowed(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    nation_id: u32,
+    nation_name: &str,
+) -> Result<(), CommandError> {
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    if restrictions.banned.contains(&nation_id) {
+        return Err(format!("{} is banned in {}", nation_name, alias).into());
+    }
+    if !restrictions.permits(nation_id) {
+        return Err(format!(
+            "{} isn't one of the nations allowed in {}. See !details for which are.",
+            nation_name, alias
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn nation_names(nations: &[StaticNation]) -> String {
+    nations
+        .iter()
+        .map(static_nation_name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Players who joined before the rules changed keep their nations, so the owner needs to
+/// know to sort them out
+fn disallowed_players_note(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<String, CommandError> {
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    let disallowed = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .filter(|(_, nation)| nation.id().is_some_and(|id| !restrictions.permits(id)))
+        .map(|(_, nation)| nation.name(None))
+        .collect::<Vec<_>>();
+    if disallowed.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!(
+        "\nSomebody already has {}, and keeps it unless they're kicked.",
+        disallowed.join(", ")
+    ))
+}
+
+fn ban_nations_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    arg_nations: &str,
+) -> Result<String, CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let nations = parse_nation_list(arg_nations, &lobby_state, server.dom_version)?;
+    for nation in &nations {
+        db_conn.ban_nation(alias, nation.id)?;
+    }
+    Ok(format!(
+        "Banned in {}: {}{}",
+        alias,
+        nation_names(&nations),
+        disallowed_players_note(db_conn, alias)?
+    ))
+}
+
+fn unban_nations_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    arg_nations: &str,
+) -> Result<String, CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    let nations = parse_nation_list(arg_nations, &lobby_state, server.dom_version)?;
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    if let Some(nation) = nations
+        .iter()
+        .find(|nation| !restrictions.banned.contains(&nation.id))
+    {
+        return Err(format!("{} isn't banned in {}", static_nation_name(nation), alias).into());
+    }
+    for nation in &nations {
+        db_conn.unban_nation(alias, nation.id)?;
+    }
+    Ok(format!(
+        "No longer banned in {}: {}",
+        alias,
+        nation_names(&nations)
+    ))
+}
+
+fn allow_nations_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    arg_nations: &str,
+) -> Result<String, CommandError> {
+    let (server, lobby_state) = lobby_for_alias(db_conn, alias)?;
+    if arg_nations == "off" {
+        db_conn.set_allowed_nations(alias, &[])?;
+        return Ok(format!(
+            "Any nation that isn't banned can be played in {} again",
+            alias
+        ));
+    }
+    let nations = parse_nation_list(arg_nations, &lobby_state, server.dom_version)?;
+    if (nations.len() as i32) < lobby_state.player_count {
+        return Err(format!(
+            "{} is for {} players, so allow at least that many nations",
+            alias, lobby_state.player_count
+        )
+        .into());
+    }
+    let nation_ids = nations.iter().map(|nation| nation.id).collect::<Vec<_>>();
+    db_conn.set_allowed_nations(alias, &nation_ids)?;
+    Ok(format!(
+        "Only these nations can be played in {} now: {}{}",
+        alias,
+        nation_names(&nations),
+        disallowed_players_note(db_conn, alias)?
+    ))
+}
+
+pub async fn ban_nation(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nations = args.required_string("nations")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+
+    let text = ban_nations_helper(&db_conn, &alias, &arg_nations)?;
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+pub async fn unban_nation(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nations = args.required_string("nations")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+
+    let text = unban_nations_helper(&db_conn, &alias, &arg_nations)?;
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+pub async fn allow_nations(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nations = args.required_string("nations")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+
+    let text = allow_nations_helper(&db_conn, &alias, &arg_nations)?;
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::{enums::Era, nation_restrictions::NationRestrictions},
+        test_harness::TempDb,
+    };
+
+    #[test]
+    fn owners_can_ban_nations_or_only_allow_some() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = GameAlias::new(None, "tourney");
+        temp_db.add_lobby(&alias, Era::Middle, 2);
+
+        ban_nations_helper(db_conn, &alias, "tc").unwrap();
+        assert!(check_nation_allowed(db_conn, &alias, 69, "TC").is_err());
+        assert!(check_nation_allowed(db_conn, &alias, 68, "BL").is_ok());
+        assert!(unban_nations_helper(db_conn, &alias, "bl").is_err());
+
+        // not enough nations for everybody
+        assert!(allow_nations_helper(db_conn, &alias, "bl").is_err());
+        allow_nations_helper(db_conn, &alias, "bl, tc").unwrap();
+        // allowing a banned nation unbans it
+        assert!(check_nation_allowed(db_conn, &alias, 69, "TC").is_ok());
+        assert!(check_nation_allowed(db_conn, &alias, 56, "Ermor").is_err());
+
+        ban_nations_helper(db_conn, &alias, "bl").unwrap();
+        assert_eq!(
+            vec![68],
+            db_conn.nation_restrictions(&alias).unwrap().banned
+        );
+        unban_nations_helper(db_conn, &alias, "bl").unwrap();
+        allow_nations_helper(db_conn, &alias, "off").unwrap();
+        assert_eq!(
+            NationRestrictions::default(),
+            db_conn.nation_restrictions(&alias).unwrap()
+        );
+    }
+}
@@ -1,29 +1,28 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::commands::servers::turn_check::{notify_player_for_new_turn, NewTurnNation};
 use crate::commands::servers::CommandResponse;
 use crate::{
     commands::servers::{
         alias_from_arg_or_channel_name,
         details::get_details_for_alias,
+        permissions::check_can_manage_game,
         // turn_check::{notify_player_for_new_turn, NewTurnNation},
     },
     db::*,
     model::{
-        game_server::{GameServerState, StartedState},
+        game_server::{GameAlias, GameServerState, StartedState},
         game_state::{NationDetails, PotentialPlayer, StartedStateDetails},
     },
     server::get_game_data_async,
     snek::snek_details_async,
 };
 use serenity::model::id::{ChannelId, UserId};
-use serenity::{
-    framework::standard::{Args, CommandError},
-    prelude::Context,
-};
+use serenity::prelude::Context;
 
 async fn start_helper(
     db_conn: DbConnection,
     address: &str,
-    alias: &str,
+    alias: &GameAlias,
     context: &Context,
 ) -> Result<(), CommandError> {
     let server = db_conn.game_for_alias(alias)?;
@@ -33,6 +32,20 @@ async fn start_helper(
             return Err(CommandError::from("game already started"))
         }
         GameServerState::Lobby(lobby_state) => {
+            if db_conn.draft_for_game(alias)?.is_some() {
+                return Err(format!(
+                    "{} is drafting nations. Finish the draft, or turn it off, before starting.",
+                    alias
+                )
+                .into());
+            }
+            if !db_conn.nation_preferences(alias)?.is_empty() {
+                return Err(format!(
+                    "Some players in {} are still waiting to be given a nation. Use !assign before starting.",
+                    alias
+                )
+                .into());
+            }
             let game_data = get_game_data_async(address, server.dom_version).await?;
             if game_data.nations.len() as i32 > lobby_state.player_count {
                 return Err(CommandError::from("game has more players than the lobby"));
@@ -54,6 +67,9 @@ async fn start_helper(
                         if let PotentialPlayer::RegisteredOnly(player, nation_id) =
                             player.potential_player
                         {
+                            if !player.notifications.uploading {
+                                continue;
+                            }
                             let message = NewTurnNation {
                                     user_id: player.discord_user_id,
                                     message: format!(
@@ -82,8 +98,8 @@ async fn start_helper(
 pub async fn start(
     context: &Context,
     channel_id: ChannelId,
-    _user_id: UserId,
-    mut args: Args,
+    user_id: UserId,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     let db_conn = {
         let data = context.data.read().await;
@@ -91,13 +107,9 @@ pub async fn start(
             .ok_or("No DbConnection was created on startup. This is a bug.")?
             .clone()
     };
-    let address = args.single_quoted::<String>()?;
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+    let address = args.required_string("address")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
     start_helper(db_conn, &address, &alias, context).await?;
     Ok(CommandResponse::Reply("started!".to_owned()))
 }
@@ -0,0 +1,298 @@
+//! Finding somebody new to play a nation when its player drops out
+
+use super::{
+    alias_from_arg_or_channel_name, db_connection, guild_for_channel,
+    permissions::check_can_manage_game,
+    register_player::{
+        ambiguous_nation_error, check_command_is_private, search_nations, search_nations_in_game,
+        started_state_details, static_nation_name,
+    },
+};
+use crate::commands::registry::{CommandArgs, CommandError};
+use crate::{
+    commands::servers::CommandResponse,
+    db::DbConnection,
+    model::{
+        enums::{Era, NationSearch, NationStatus},
+        game_server::{GameAlias, GameServerState},
+        game_state::{PotentialPlayer, StartedStateDetails},
+        player::{NotificationSettings, Player},
+        sub_request::SubRequest,
+    },
+    DetailsCacheHandle,
+};
+use log::*;
+use serenity::{
+    builder::CreateEmbed,
+    model::{
+        id::{ChannelId, GuildId, UserId},
+        mention::Mention,
+    },
+    prelude::Context,
+};
+use std::{str::FromStr, sync::Arc};
+
+/// The nation in a game that's being played, by ID or by name
+fn find_playing_nation<'a>(
+    arg_nation: &str,
+    players: &'a [PotentialPlayer],
+    lobby_era: Option<Era>,
+    dom_version: u8,
+) -> Result<&'a PotentialPlayer, CommandError> {
+    match u32::from_str(arg_nation) {
+        Ok(nation_id) => players
+            .iter()
+            .find(|player| player.nation_id() == Some(nation_id))
+            .ok_or_else(|| CommandError::from(format!("No nation with ID {}", nation_id))),
+        Err(_) => search_nations_in_game(arg_nation, players, lobby_era, dom_version),
+    }
+}
+
+pub async fn sub_needed(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nation = args.required_string("nation")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    let server = db_conn.game_for_alias(&alias)?;
+
+    let GameServerState::StartedState(started_state, option_lobby_state) = &server.state else {
+        return Err(format!("{} hasn't started yet, so anybody can just !join it", alias).into());
+    };
+    let details_read_handle = DetailsCacheHandle(Arc::clone(&context.data));
+    let (started_details, option_snek_state) = started_state_details(
+        db_conn.clone(),
+        started_state,
+        option_lobby_state.as_ref(),
+        &alias,
+        &details_read_handle,
+    )
+    .await?;
+    let StartedStateDetails::Playing(playing_state) = started_details else {
+        return Err(format!("{} is still uploading pretenders", alias).into());
+    };
+    let potential_player = find_playing_nation(
+        &arg_nation,
+        &playing_state.players,
+        option_lobby_state
+            .as_ref()
+            .map(|lobby_state| lobby_state.era),
+        server.dom_version,
+    )?;
+
+    match potential_player {
+        PotentialPlayer::RegisteredAndGame(player, player_details) => {
+            if matches!(
+                player_details.player_status,
+                NationStatus::Defeated | NationStatus::DefeatedThisTurn
+            ) {
+                return Err("That nation has been defeated".into());
+            }
+            if player.discord_user_id == user_id {
+                // asking for a sub in public says which nation you were
+                check_command_is_private(context, channel_id, &alias, &db_conn, "ask for a sub")
+                    .await?;
+            } else {
+                check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+            }
+        }
+        PotentialPlayer::GameOnly(player_details) => {
+            if player_details.player_status != NationStatus::AI {
+                return Err(
+                    "Nobody is registered as that nation, so it can only need a sub if it has gone AI"
+                        .into(),
+                );
+            }
+            check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+        }
+        PotentialPlayer::RegisteredOnly(_, _) => {
+            return Err("That nation isn't in the game".into());
+        }
+    }
+    let nation_id = potential_player
+        .nation_id()
+        .ok_or("That nation doesn't have an ID, so can't be subbed for")?;
+    db_conn.request_sub(&alias, nation_id)?;
+
+    let text = format!(
+        "{} in {} now needs a sub. Volunteers can take it over with !sub",
+        potential_player.nation_name(option_snek_state.as_ref()),
+        alias
+    );
+    info!("{}", text);
+    Ok(CommandResponse::Reply(text))
+}
+
+fn subs_helper(
+    db_conn: &DbConnection,
+    option_guild_id: Option<GuildId>,
+) -> Result<CreateEmbed, CommandError> {
+    let sub_requests = db_conn
+        .sub_requests()?
+        .into_iter()
+        .filter(|sub_request| sub_request.alias.is_visible_from(option_guild_id))
+        .collect::<Vec<_>>();
+    if sub_requests.is_empty() {
+        return Ok(CreateEmbed::default().title("NOBODY NEEDS A SUB"));
+    }
+
+    let mut aliases = String::new();
+    let mut nations = String::new();
+    let mut turns = String::new();
+    for sub_request in sub_requests {
+        aliases.push_str(&format!("{}\n", sub_request.alias));
+        nations.push_str(&format!("{}\n", sub_request_nation(&sub_request)));
+        turns.push_str(&match sub_request.last_seen_turn {
+            Some(turn) => format!("{}\n", turn),
+            None => "?\n".to_owned(),
+        });
+    }
+    Ok(CreateEmbed::default()
+        .title("Nations needing a sub:")
+        .field("Alias", aliases, true)
+        .field("Nation", nations, true)
+        .field("Turn", turns, true))
+}
+
+fn sub_request_nation(sub_request: &SubRequest) -> String {
+    let era = sub_request
+        .era
+        .map(|era| format!("{} ", era))
+        .unwrap_or_default();
+    // nobody to hand over from, so it has been played by the AI for a while
+    let ai = if sub_request.player.is_none() {
+        " (AI)"
+    } else {
+        ""
+    };
+    format!("{}{}{}", era, sub_request.nation.name(None), ai)
+}
+
+pub async fn subs(
+    context: &Context,
+    channel_id: ChannelId,
+    _user_id: UserId,
+    _args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let option_guild_id = guild_for_channel(context, channel_id).await?;
+    let db_conn = db_connection(context).await?;
+    let embed = subs_helper(&db_conn, option_guild_id)?;
+    Ok(CommandResponse::Embed(Box::new(embed)))
+}
+
+/// The sub request for the nation, by ID or by name
+fn find_sub_request(
+    arg_nation: &str,
+    sub_requests: Vec<SubRequest>,
+    dom_version: u8,
+) -> Result<SubRequest, CommandError> {
+    if let Ok(nation_id) = u32::from_str(arg_nation) {
+        return sub_requests
+            .into_iter()
+            .find(|sub_request| sub_request.nation.id() == Some(nation_id))
+            .ok_or_else(|| CommandError::from(format!("Nation {} doesn't need a sub", nation_id)));
+    }
+    let nation_matches = search_nations(arg_nation, None, dom_version)?
+        .into_iter()
+        .filter(|nation_match| {
+            sub_requests
+                .iter()
+                .any(|sub_request| sub_request.nation.id() == Some(nation_match.nation.id))
+        })
+        .collect();
+    match NationSearch::from_matches(nation_matches) {
+        NationSearch::Found(nation) => sub_requests
+            .into_iter()
+            .find(|sub_request| sub_request.nation.id() == Some(nation.id))
+            .ok_or_else(|| CommandError::from("nation vanished from the sub requests")),
+        NationSearch::Ambiguous(nations) => Err(ambiguous_nation_error(
+            arg_nation,
+            nations.iter().map(static_nation_name),
+        )),
+        NationSearch::NotFound => Err(CommandError::from(format!(
+            "Could not find a nation starting with \"{}\" that needs a sub",
+            arg_nation
+        ))),
+    }
+}
+
+fn sub_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    arg_nation: &str,
+    user_id: UserId,
+) -> Result<(SubRequest, Option<UserId>), CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    if db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .iter()
+        .any(|(player, _)| player.discord_user_id == user_id)
+    {
+        return Err("You are already playing in this game".into());
+    }
+    let sub_requests = db_conn
+        .sub_requests()?
+        .into_iter()
+        .filter(|sub_request| &sub_request.alias == alias)
+        .collect::<Vec<_>>();
+    let sub_request = find_sub_request(arg_nation, sub_requests, server.dom_version)?;
+    let nation_id = sub_request
+        .nation
+        .id()
+        .ok_or("That nation doesn't have an ID, so can't be subbed for")?;
+
+    let player = Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+        notifications: NotificationSettings::all(true),
+    };
+    let option_previous_player = db_conn.take_over_nation(alias, nation_id, &player)?;
+    Ok((sub_request, option_previous_player))
+}
+
+pub async fn sub(
+    context: &Context,
+    channel_id: ChannelId,
+    user_id: UserId,
+    args: CommandArgs,
+) -> Result<CommandResponse, CommandError> {
+    let arg_nation = args.required_string("nation")?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    let db_conn = db_connection(context).await?;
+    check_command_is_private(context, channel_id, &alias, &db_conn, "sub in").await?;
+
+    let (sub_request, option_previous_player) = sub_helper(&db_conn, &alias, &arg_nation, user_id)?;
+    let nation_name = sub_request.nation.name(None);
+    info!(
+        "{} took over {} in {} from {:?}",
+        user_id, nation_name, alias, option_previous_player
+    );
+
+    if let Some(previous_player) = option_previous_player {
+        // don't say who it was in an anonymous game
+        let volunteer = if sub_request.anonymous {
+            "Somebody".to_owned()
+        } else {
+            Mention::from(user_id).to_string()
+        };
+        let text = format!(
+            "{} has taken over {} in {}, so you're no longer registered for it. Thanks for playing!",
+            volunteer, nation_name, alias
+        );
+        let dm_result = match previous_player.create_dm_channel(&context.http).await {
+            Ok(private_channel) => private_channel.say(&context.http, &text).await.map(|_| ()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = dm_result {
+            error!("Could not DM {}: {:?}", previous_player, err);
+        }
+    }
+
+    Ok(CommandResponse::Reply(format!(
+        "You are now playing {} in {}. If it has gone AI, ask the game's host to give it back to a human.",
+        nation_name, alias
+    )))
+}
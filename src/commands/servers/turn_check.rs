@@ -1,52 +1,94 @@
 use crate::{
-    commands::servers::{details::started_details_from_server, discord_date_format},
+    commands::servers::{
+        details::{details_to_embed, started_details_from_server},
+        discord_date_format,
+        poll_schedule::{PollOutcome, PollSchedule},
+    },
+    config::{self, TurnCheckSettings},
     db::*,
     model::{
         enums::*,
         game_data::GameData,
-        game_server::{GameServerState, LobbyState, StartedState},
+        game_server::{GameAlias, GameServerState, LobbyState, StartedState},
         game_state::{
             CacheEntry, GameDetails, NationDetails, PlayerDetails, PlayingState, PotentialPlayer,
             StartedDetails, StartedStateDetails,
         },
         nation::{BotNationIdentifier, Nation},
         player::Player,
+        reminder::Reminder,
+        server_health::ServerHealth,
     },
     server::get_game_data_async,
     snek::{snek_details_async, SnekGameStatus},
-    DetailsCacheHandle, DetailsCacheKey, SERVER_POLL_INTERVAL,
+    DetailsCacheHandle, DetailsCacheKey,
 };
 use anyhow::{anyhow, Context};
 use chrono::{DateTime, Duration, Utc};
-use futures::future;
+use futures::{future, stream, StreamExt as _};
 use log::*;
 use serenity::{
+    builder::EditMessage,
     cache::Cache,
     http::{CacheHttp, Http},
-    model::id::UserId,
+    model::{
+        id::{ChannelId, MessageId, UserId},
+        mention::Mention,
+    },
 };
-use std::sync::Arc;
+use std::{borrow::Cow, collections::HashSet, sync::Arc};
 
+/// Polls each game whenever its schedule says it's due (see `PollSchedule`), talking to at
+/// most `max_concurrent_polls` servers at once
 pub async fn update_details_cache_loop(
     db_conn: DbConnection,
     write_handle_mutex: DetailsCacheHandle,
     cache_and_http: (Arc<Cache>, Arc<Http>),
+    settings: TurnCheckSettings,
 ) {
+    let mut schedule = PollSchedule::default();
     loop {
         info!("Checking for new turns!");
 
-        match update_details_cache_for_all_games(db_conn.clone(), write_handle_mutex.clone()).await
+        if let Err(e) = update_details_cache_for_due_games(
+            db_conn.clone(),
+            write_handle_mutex.clone(),
+            &mut schedule,
+            settings,
+            cache_and_http.clone(),
+        )
+        .await
         {
-            Err(e) => error!("Error updating all games: {:#?}", e),
-            Ok(new_turn_nations) => {
-                notify_all_players_for_new_turn(new_turn_nations, cache_and_http.clone()).await
-            }
+            error!("Error updating all games: {:#?}", e);
         }
 
-        tokio::time::sleep(SERVER_POLL_INTERVAL).await;
+        let now = Utc::now();
+        let sleep_for = (schedule.next_wakeup(now) - now)
+            .to_std()
+            .unwrap_or_default()
+            .max(std::time::Duration::from_secs(1));
+        tokio::time::sleep(sleep_for).await;
     }
 }
 
+async fn send_pending_messages(
+    pending_messages: PendingMessages,
+    db_conn: DbConnection,
+    cache_and_http: (Arc<Cache>, Arc<Http>),
+) {
+    future::join4(
+        notify_all_players_for_new_turn(pending_messages.dms, cache_and_http.clone()),
+        send_all_reminders(
+            pending_messages.reminders,
+            db_conn.clone(),
+            cache_and_http.clone(),
+        ),
+        post_all_announcements(pending_messages.announcements, cache_and_http.clone()),
+        update_all_pinned_statuses(pending_messages.pinned_statuses, db_conn, cache_and_http),
+    )
+    .await;
+}
+
 async fn notify_all_players_for_new_turn(
     new_turn_nations: Vec<NewTurnNation>,
     cache_and_http: (Arc<Cache>, Arc<Http>),
@@ -65,6 +107,125 @@ async fn notify_all_players_for_new_turn(
     .await;
 }
 
+async fn send_all_reminders(
+    reminder_messages: Vec<ReminderMessage>,
+    db_conn: DbConnection,
+    cache_and_http: (Arc<Cache>, Arc<Http>),
+) {
+    future::join_all(reminder_messages.into_iter().map(|reminder_message| async {
+        let cache_http = (&cache_and_http.0.clone(), cache_and_http.1.as_ref());
+        send_reminder(reminder_message, &db_conn, cache_http).await
+    }))
+    .await;
+}
+
+/// Reminders are only marked as sent once the DM goes out, so a failed send is tried again
+/// on the next poll
+async fn send_reminder(
+    reminder_message: ReminderMessage,
+    db_conn: &DbConnection,
+    cache_http: impl CacheHttp + Clone,
+) {
+    let ReminderMessage {
+        dm,
+        alias,
+        turn,
+        reminders,
+    } = reminder_message;
+    let user_id = dm.user_id;
+    if let Err(e) = notify_player_for_new_turn(dm, cache_http).await {
+        error!(
+            "Failed to send reminders {:?} for {} turn {} to user {:?} with error: {:#?}",
+            reminders, alias, turn, user_id, e
+        );
+        return;
+    }
+    for reminder in &reminders {
+        if let Err(e) = db_conn.mark_reminder_sent(&alias, reminder, turn) {
+            error!(
+                "Sent reminder {:?} for {} turn {} but couldn't mark it: {:#?}",
+                reminder, alias, turn, e
+            );
+        }
+    }
+}
+
+async fn post_all_announcements(
+    announcements: Vec<ChannelAnnouncement>,
+    cache_and_http: (Arc<Cache>, Arc<Http>),
+) {
+    let http = cache_and_http.1;
+    future::join_all(announcements.into_iter().map(|announcement| {
+        let http = http.clone();
+        async move {
+            let channel_id = announcement.channel_id;
+            if let Err(e) = channel_id.say(http.as_ref(), &announcement.message).await {
+                error!(
+                    "Failed to post announcement in channel {:?} with error: {:#?}",
+                    channel_id, e
+                );
+            }
+        }
+    }))
+    .await;
+}
+
+async fn update_all_pinned_statuses(
+    pinned_statuses: Vec<PinnedStatusUpdate>,
+    db_conn: DbConnection,
+    cache_and_http: (Arc<Cache>, Arc<Http>),
+) {
+    future::join_all(pinned_statuses.into_iter().map(|pinned_status| async {
+        let alias = pinned_status.details.alias.clone();
+        let cache_http = (&cache_and_http.0, cache_and_http.1.as_ref());
+        match update_pinned_status(pinned_status, cache_http).await {
+            Ok(()) => (),
+            Err(PinnedStatusError::MessageGone) => {
+                // somebody deleted the message, so stop trying to edit it
+                info!("Pinned status message for {} was deleted", alias);
+                if let Err(e) = db_conn.set_pinned_status(&alias, None) {
+                    error!("Failed to forget pinned status for {}: {:#?}", alias, e);
+                }
+            }
+            Err(PinnedStatusError::Other(e)) => {
+                error!("Failed to update pinned status for {}: {:#?}", alias, e)
+            }
+        }
+    }))
+    .await;
+}
+
+enum PinnedStatusError {
+    MessageGone,
+    Other(anyhow::Error),
+}
+
+async fn update_pinned_status(
+    pinned_status: PinnedStatusUpdate,
+    cache_http: (&Arc<Cache>, &Http),
+) -> Result<(), PinnedStatusError> {
+    let embed = details_to_embed(pinned_status.details, cache_http)
+        .await
+        .map_err(|e| PinnedStatusError::Other(anyhow!(e)))?;
+    match pinned_status
+        .channel_id
+        .edit_message(
+            cache_http,
+            pinned_status.message_id,
+            EditMessage::new().embed(embed),
+        )
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(serenity::Error::Http(http_error))
+            if http_error.status_code().map(|code| code.as_u16()) == Some(404) =>
+        {
+            Err(PinnedStatusError::MessageGone)
+        }
+        Err(e) => Err(PinnedStatusError::Other(anyhow!(e))),
+    }
+}
+
 pub async fn notify_player_for_new_turn(
     new_turn: NewTurnNation,
     cache_and_http: impl CacheHttp + Clone,
@@ -83,67 +244,241 @@ pub async fn notify_player_for_new_turn(
 ///   1) update the db
 ///   2) work out which players need to be notified (but don't actually send any messages yet)
 ///   3) update the in-mem cache with the new details
+///
+/// Also says what we found, so that the game's next poll can be scheduled
 async fn fetch_new_state_and_update_details_cache_for_game(
-    alias: &str,
+    alias: &GameAlias,
     db_conn: DbConnection,
     write_handle_mutex: DetailsCacheHandle,
-) -> anyhow::Result<Vec<NewTurnNation>> {
+    down_alert_after_failures: u32,
+) -> anyhow::Result<(PendingMessages, PollOutcome)> {
     info!("Checking turn for {}", alias);
 
     let details = db_conn.game_for_alias(alias)?;
 
-    let messages = if let GameServerState::StartedState(started_state, option_lobby_state) =
-        &details.state
-    {
+    let GameServerState::StartedState(started_state, option_lobby_state) = &details.state else {
+        // game is still a lobby
+        info!("Checking turn for {}: SUCCESS", alias);
+        return Ok((PendingMessages::default(), PollOutcome::Lobby));
+    };
+
+    let fetched = async {
         let new_game_data =
             get_game_data_async(&started_state.address, details.dom_version).await?;
         let option_new_snek_data = snek_details_async(&started_state.address).await?;
+        anyhow::Ok((new_game_data, option_new_snek_data))
+    }
+    .await;
+    let (new_game_data, option_new_snek_data) = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            warn!("Could not reach the server for {}: {:?}", alias, e);
+            let health = db_conn.record_server_unreachable(alias)?;
+            let messages = if health.consecutive_failures == down_alert_after_failures {
+                server_alert(alias, &db_conn, mk_server_down_message(alias, &health))?
+            } else {
+                PendingMessages::default()
+            };
+            return Ok((messages, PollOutcome::Unreachable));
+        }
+    };
+    let previous_health = db_conn.record_server_reachable(alias, Utc::now())?;
 
-        let is_new_turn = db_conn.update_game_with_possibly_new_turn(alias, new_game_data.turn)?;
-        let messages = if is_new_turn {
-            process_game_data(
-                alias,
-                db_conn,
-                &write_handle_mutex,
-                started_state,
-                option_lobby_state.as_ref(),
-                &new_game_data,
-                option_new_snek_data.as_ref(),
-            )
-            .await?
-        } else {
-            // not a new turn
-            vec![]
-        };
+    let option_pinned_status = pinned_status_if_changed(
+        alias,
+        db_conn.clone(),
+        &write_handle_mutex,
+        started_state,
+        option_lobby_state.as_ref(),
+        &new_game_data,
+        option_new_snek_data.as_ref(),
+    )
+    .await?;
 
-        update_cache(
+    let is_new_turn = db_conn.update_game_with_possibly_new_turn(alias, new_game_data.turn)?;
+    if new_game_data.turn >= 0 {
+        db_conn.upsert_turn_history(alias, &new_game_data, Utc::now())?;
+    }
+    let mut messages = if is_new_turn {
+        process_game_data(
             alias,
-            write_handle_mutex,
-            CacheEntry {
-                game_data: new_game_data,
-                option_snek_state: option_new_snek_data,
-            },
+            db_conn.clone(),
+            &write_handle_mutex,
+            started_state,
+            option_lobby_state.as_ref(),
+            &new_game_data,
+            option_new_snek_data.as_ref(),
         )
-        .await?;
-
-        messages
+        .await?
     } else {
-        // game is still a lobby
-        vec![]
+        // not a new turn
+        PendingMessages::default()
     };
+    let mut reminder_messages = create_reminder_messages(
+        alias,
+        db_conn.clone(),
+        started_state,
+        option_lobby_state.as_ref(),
+        &new_game_data,
+        option_new_snek_data.as_ref(),
+        is_new_turn,
+        Utc::now(),
+    )?;
+    messages.reminders.append(&mut reminder_messages);
+    messages.pinned_statuses.extend(option_pinned_status);
+    if previous_health.consecutive_failures >= down_alert_after_failures {
+        let message = mk_server_back_message(alias, &previous_health);
+        messages.append(&mut server_alert(alias, &db_conn, message)?);
+    }
+    if is_new_turn && new_game_data.looks_finished() {
+        let message = mk_looks_finished_message(alias);
+        messages.append(&mut server_alert(alias, &db_conn, message)?);
+    }
+    let outcome = PollOutcome::from_game_data(&new_game_data, is_new_turn);
+
+    if new_game_data.finished {
+        let mut game_over_messages =
+            archive_finished_game(alias, &db_conn, &write_handle_mutex, &new_game_data).await?;
+        messages.append(&mut game_over_messages);
+        info!("Checking turn for {}: FINISHED", alias);
+        return Ok((messages, outcome));
+    }
+
+    update_cache(
+        alias,
+        write_handle_mutex,
+        CacheEntry {
+            game_data: new_game_data,
+            option_snek_state: option_new_snek_data,
+        },
+    )
+    .await?;
+
     info!("Checking turn for {}: SUCCESS", alias);
+    Ok((messages, outcome))
+}
+
+/// Tell everybody who won, then move the game into the archive so that it stops being polled.
+/// The result goes to the game's announcement channel if it has one, otherwise to every
+/// registered player.
+async fn archive_finished_game(
+    alias: &GameAlias,
+    db_conn: &DbConnection,
+    write_handle_mutex: &DetailsCacheHandle,
+    game_data: &GameData,
+) -> anyhow::Result<PendingMessages> {
+    // the game's settings go with it into the archive
+    let players = db_conn.players_with_nations_for_game_alias(alias)?;
+    let option_announce_channel = db_conn.announce_channel_for_game(alias)?;
+    let message = archive_game(alias, db_conn, write_handle_mutex, game_data).await?;
+    let mut messages = PendingMessages::default();
+    match option_announce_channel {
+        Some(channel_id) => messages.announcements.push(ChannelAnnouncement {
+            channel_id,
+            message,
+        }),
+        None => {
+            for (player, _) in &players {
+                messages.dms.push(NewTurnNation {
+                    user_id: player.discord_user_id,
+                    message: message.clone(),
+                });
+            }
+        }
+    }
     Ok(messages)
 }
 
+/// Moves the game into the archive so that it stops being polled, and says who won
+pub(super) async fn archive_game(
+    alias: &GameAlias,
+    db_conn: &DbConnection,
+    write_handle_mutex: &DetailsCacheHandle,
+    game_data: &GameData,
+) -> anyhow::Result<String> {
+    let players = db_conn.players_with_nations_for_game_alias(alias)?;
+    let anonymous = db_conn.is_game_anonymous(alias)?;
+    let message = mk_game_over_message(alias, anonymous, game_data, &players);
+
+    db_conn.archive_game(alias, game_data, Utc::now())?;
+    let mut guard = write_handle_mutex.0.write().await;
+    if let Some(write_handle) = guard.get_mut::<DetailsCacheKey>() {
+        write_handle.remove(alias);
+    }
+    Ok(message)
+}
+
+/// Trouble with the server goes to the game's announcement channel if it has one,
+/// otherwise to its owner
+fn server_alert(
+    alias: &GameAlias,
+    db_conn: &DbConnection,
+    message: String,
+) -> anyhow::Result<PendingMessages> {
+    let mut messages = PendingMessages::default();
+    if let Some(channel_id) = db_conn.announce_channel_for_game(alias)? {
+        messages.announcements.push(ChannelAnnouncement {
+            channel_id,
+            message,
+        });
+    } else if let Some(user_id) = db_conn.owner_for_game(alias)? {
+        messages.dms.push(NewTurnNation { user_id, message });
+    } else {
+        info!("Nobody to tell about {}: {}", alias, message);
+    }
+    Ok(messages)
+}
+
+/// If the game has a pinned status message and anything has changed since we last looked,
+/// then work out what the message should now say
+async fn pinned_status_if_changed(
+    alias: &GameAlias,
+    db_conn: DbConnection,
+    read_handle: &DetailsCacheHandle,
+    started_state: &StartedState,
+    option_lobby_state: Option<&LobbyState>,
+    new_game_data: &GameData,
+    option_new_snek_data: Option<&SnekGameStatus>,
+) -> anyhow::Result<Option<PinnedStatusUpdate>> {
+    let (channel_id, message_id) = match db_conn.pinned_status_for_game(alias)? {
+        Some(pinned) => pinned,
+        None => return Ok(None),
+    };
+    // if we've got nothing cached (e.g. we've just restarted) then update it anyway
+    let unchanged = read_handle
+        .get_clone(alias)
+        .await
+        .is_ok_and(|old_cache_entry| old_cache_entry.game_data.same_status_as(new_game_data));
+    if unchanged {
+        return Ok(None);
+    }
+    let details = started_details_from_server(
+        db_conn,
+        started_state,
+        option_lobby_state,
+        alias,
+        new_game_data,
+        option_new_snek_data,
+    )
+    .map_err(|e| anyhow!(e))
+    .with_context(|| format!("Error when updating pinned status for {}", alias))?;
+    Ok(Some(PinnedStatusUpdate {
+        channel_id,
+        message_id,
+        details,
+    }))
+}
+
 async fn process_game_data(
-    alias: &str,
+    alias: &GameAlias,
     db_conn: DbConnection,
     write_handle_mutex: &DetailsCacheHandle,
     started_state: &StartedState,
     option_lobby_state: Option<&LobbyState>,
     new_game_data: &GameData,
     option_new_snek_data: Option<&SnekGameStatus>,
-) -> anyhow::Result<Vec<NewTurnNation>> {
+) -> anyhow::Result<PendingMessages> {
+    let option_announce_channel = db_conn.announce_channel_for_game(alias)?;
     let new_game_details: GameDetails = started_details_from_server(
         db_conn,
         started_state,
@@ -172,13 +507,33 @@ async fn process_game_data(
             .filter(|nation| nation.status == NationStatus::DefeatedThisTurn)
             .collect::<Vec<_>>();
 
-        Ok(create_messages_for_new_turn(
-            alias,
-            new_started_details,
-            option_new_snek_data,
-            possible_stales.as_ref(),
-            defeated_this_turn.as_ref(),
-        ))
+        let announcements = option_announce_channel
+            .map(|channel_id| ChannelAnnouncement {
+                channel_id,
+                message: create_announcement_for_new_turn(
+                    alias,
+                    new_game_details.anonymous,
+                    new_started_details,
+                    option_new_snek_data,
+                    possible_stales.as_ref(),
+                    defeated_this_turn.as_ref(),
+                ),
+            })
+            .into_iter()
+            .collect();
+
+        Ok(PendingMessages {
+            dms: create_messages_for_new_turn(
+                alias,
+                new_started_details,
+                option_new_snek_data,
+                possible_stales.as_ref(),
+                defeated_this_turn.as_ref(),
+            ),
+            reminders: vec![],
+            announcements,
+            pinned_statuses: vec![],
+        })
     } else {
         // sign of a bad abstraction tbh
         Err(anyhow!(
@@ -187,8 +542,139 @@ async fn process_game_data(
     }
 }
 
+/// Work out which players are due a reminder that the deadline is coming up. Due reminders
+/// that don't need a DM (it's a new turn, or they've already submitted) are marked as sent
+/// straight away. The rest are marked once their DM has gone out (see `send_all_reminders`),
+/// so a failed send is tried again on the next poll.
+#[allow(clippy::too_many_arguments)]
+fn create_reminder_messages(
+    alias: &GameAlias,
+    db_conn: DbConnection,
+    started_state: &StartedState,
+    option_lobby_state: Option<&LobbyState>,
+    new_game_data: &GameData,
+    option_new_snek_data: Option<&SnekGameStatus>,
+    is_new_turn: bool,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Vec<ReminderMessage>> {
+    if new_game_data.turn < 0 {
+        // still uploading, there's no deadline to remind anybody about
+        return Ok(vec![]);
+    }
+    let reminders = db_conn.reminders_for_game(alias)?;
+    let due_reminders = reminders
+        .iter()
+        .filter(|reminder| {
+            is_reminder_due(
+                reminder,
+                new_game_data.turn,
+                new_game_data.turn_deadline,
+                now,
+            )
+        })
+        .collect::<Vec<_>>();
+    if due_reminders.is_empty() {
+        return Ok(vec![]);
+    }
+    if is_new_turn {
+        // they're getting the new turn message with the deadline in it anyway
+        for reminder in &due_reminders {
+            db_conn.mark_reminder_sent(alias, reminder, new_game_data.turn)?;
+        }
+        return Ok(vec![]);
+    }
+    let users_to_remind = due_reminders
+        .iter()
+        .map(|reminder| reminder.discord_user_id)
+        .collect::<HashSet<_>>();
+
+    let game_details = started_details_from_server(
+        db_conn.clone(),
+        started_state,
+        option_lobby_state,
+        alias,
+        new_game_data,
+        option_new_snek_data,
+    )
+    .map_err(|e| anyhow!(e))
+    .with_context(|| format!("Error when checking reminders for {}", alias))?;
+    let playing_state = match game_details.nations {
+        NationDetails::Started(StartedDetails {
+            state: StartedStateDetails::Playing(playing_state),
+            ..
+        }) => playing_state,
+        _ => return Ok(vec![]),
+    };
+
+    let deadline = discord_date_format(playing_state.turn_deadline);
+    let dms = playing_state
+        .players
+        .iter()
+        .filter_map(|potential_player| match potential_player {
+            PotentialPlayer::RegisteredAndGame(player, details)
+                if users_to_remind.contains(&player.discord_user_id)
+                    && player.notifications.reminders
+                    && details.player_status.is_human() =>
+            {
+                let submission_text = match details.submitted {
+                    SubmissionStatus::NotSubmitted => "haven't submitted yet",
+                    SubmissionStatus::PartiallySubmitted => "have only partially submitted",
+                    _ => return None,
+                };
+                Some(NewTurnNation {
+                    user_id: player.discord_user_id,
+                    message: format!(
+                        "Reminder: turn {} in {} is due in {}! You are {} and {}",
+                        playing_state.turn,
+                        alias,
+                        deadline,
+                        details.nation_identifier.name(option_new_snek_data),
+                        submission_text,
+                    ),
+                })
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // nothing to send to these people, so they're done for this turn
+    let users_messaged = dms.iter().map(|dm| dm.user_id).collect::<HashSet<_>>();
+    for reminder in &due_reminders {
+        if !users_messaged.contains(&reminder.discord_user_id) {
+            db_conn.mark_reminder_sent(alias, reminder, new_game_data.turn)?;
+        }
+    }
+    Ok(dms
+        .into_iter()
+        .map(|dm| ReminderMessage {
+            alias: alias.clone(),
+            turn: new_game_data.turn,
+            reminders: due_reminders
+                .iter()
+                .filter(|reminder| reminder.discord_user_id == dm.user_id)
+                .map(|&reminder| reminder.clone())
+                .collect(),
+            dm,
+        })
+        .collect())
+}
+
+fn is_reminder_due(
+    reminder: &Reminder,
+    turn: i32,
+    turn_deadline: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> bool {
+    reminder
+        .last_sent_turn
+        .is_none_or(|last_sent_turn| last_sent_turn < turn)
+        && now < turn_deadline
+        && turn_deadline.signed_duration_since(now)
+            <= Duration::minutes(reminder.minutes_before as i64)
+}
+
 async fn possible_stales_from_old_cache(
-    alias: &str,
+    alias: &GameAlias,
     write_handle_mutex: &DetailsCacheHandle,
 ) -> Option<Vec<Nation>> {
     let guard = write_handle_mutex.0.read().await;
@@ -219,7 +705,7 @@ async fn possible_stales_from_old_cache(
 }
 
 async fn update_cache(
-    alias: &str,
+    alias: &GameAlias,
     write_handle_mutex: DetailsCacheHandle,
     cache_entry: CacheEntry,
 ) -> anyhow::Result<()> {
@@ -237,37 +723,103 @@ pub struct NewTurnNation {
     pub message: String,
 }
 
-async fn update_details_cache_for_all_games(
+/// A reminder DM, and the reminders it covers, which are marked as sent once it's delivered
+#[derive(Debug)]
+pub struct ReminderMessage {
+    pub dm: NewTurnNation,
+    pub alias: GameAlias,
+    pub turn: i32,
+    pub reminders: Vec<Reminder>,
+}
+
+#[derive(Debug)]
+pub struct ChannelAnnouncement {
+    pub channel_id: ChannelId,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct PinnedStatusUpdate {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub details: GameDetails,
+}
+
+/// Everything we want to tell people after checking the servers
+#[derive(Debug, Default)]
+pub struct PendingMessages {
+    pub dms: Vec<NewTurnNation>,
+    pub reminders: Vec<ReminderMessage>,
+    pub announcements: Vec<ChannelAnnouncement>,
+    pub pinned_statuses: Vec<PinnedStatusUpdate>,
+}
+impl PendingMessages {
+    fn append(&mut self, other: &mut PendingMessages) {
+        self.dms.append(&mut other.dms);
+        self.reminders.append(&mut other.reminders);
+        self.announcements.append(&mut other.announcements);
+        self.pinned_statuses.append(&mut other.pinned_statuses);
+    }
+}
+
+/// Each game's messages are sent as soon as its own poll is done, rather than waiting for
+/// the slowest server
+async fn update_details_cache_for_due_games(
     db_conn: DbConnection,
     write_handle_mutex: DetailsCacheHandle,
-) -> Result<Vec<NewTurnNation>, anyhow::Error> {
+    schedule: &mut PollSchedule,
+    settings: TurnCheckSettings,
+    cache_and_http: (Arc<Cache>, Arc<Http>),
+) -> Result<(), anyhow::Error> {
     let servers = db_conn
         .retrieve_all_servers()
         .context("Could not query the db for all servers")?;
     info!("retrieve_all_servers done, found {} entries", servers.len());
+    let due_games = schedule.due_games(
+        servers.into_iter().map(|server| server.alias).collect(),
+        Utc::now(),
+    );
+    info!("{} games are due a poll", due_games.len());
     let write_handle_mutex = &write_handle_mutex;
     let db_conn = &db_conn;
-    let futs = servers.into_iter().map(|server| async move {
-        debug!("starting to update: '{}'", server.alias);
-        match fetch_new_state_and_update_details_cache_for_game(
-            &server.alias,
-            db_conn.clone(),
-            write_handle_mutex.clone(),
-        )
-        .await
-        {
-            Ok(new_turns) => new_turns,
+    let cache_and_http = &cache_and_http;
+    let results = stream::iter(due_games)
+        .map(|alias| async move {
+            debug!("starting to update: '{}'", alias);
+            let result = fetch_new_state_and_update_details_cache_for_game(
+                &alias,
+                db_conn.clone(),
+                write_handle_mutex.clone(),
+                settings.down_alert_after_failures,
+            )
+            .await;
+            let result = match result {
+                Ok((messages, outcome)) => {
+                    send_pending_messages(messages, db_conn.clone(), cache_and_http.clone()).await;
+                    Ok(outcome)
+                }
+                Err(e) => Err(e),
+            };
+            (alias, result)
+        })
+        .buffer_unordered(settings.max_concurrent_polls)
+        .collect::<Vec<_>>()
+        .await;
+    for (alias, result) in results {
+        match result {
+            Ok(outcome) => schedule.record(&alias, &outcome, Utc::now()),
             Err(e) => {
-                error!("Could not update game {} with error {:?}", server.alias, e);
-                vec![]
+                error!("Could not update game {} with error {:?}", alias, e);
+                // failing to reach the server is an Ok outcome, so this is our own fault
+                schedule.record_error(&alias, Utc::now());
             }
         }
-    });
-    Ok(future::join_all(futs).await.into_iter().flatten().collect())
+    }
+    Ok(())
 }
 
 pub fn create_messages_for_new_turn(
-    alias: &str,
+    alias: &GameAlias,
     new_started_details: &StartedDetails,
     option_snek_state: Option<&SnekGameStatus>,
     possible_stales: &[Nation],
@@ -295,7 +847,7 @@ pub fn create_messages_for_new_turn(
         }
         StartedStateDetails::Uploading(ref new_uploading_details) => {
             new_uploading_details.uploading_players.iter().flat_map(|player| {
-                player.option_player_id().filter(|_| !player.uploaded).map(|discord_player|  {
+                player.option_player_id().filter(|discord_player| !player.uploaded && discord_player.notifications.uploading).map(|discord_player|  {
                         NewTurnNation {
                                 user_id: discord_player.discord_user_id,
                                 message: format!(
@@ -309,6 +861,82 @@ pub fn create_messages_for_new_turn(
     }
 }
 
+/// The summary of a new turn that gets posted in the game's announcement channel, if it has one.
+/// Registered players that still need to do something are pinged, unless the game is anonymous.
+pub fn create_announcement_for_new_turn(
+    alias: &GameAlias,
+    anon_game: bool,
+    new_started_details: &StartedDetails,
+    option_snek_state: Option<&SnekGameStatus>,
+    possible_stales: &[Nation],
+    defeated_this_turn: &[&Nation],
+) -> String {
+    let mention_or_name = |player: &Player, nation_name: Cow<'static, str>| {
+        if !anon_game && player.notifications.new_turn {
+            Mention::from(player.discord_user_id).to_string()
+        } else {
+            nation_name.into_owned()
+        }
+    };
+
+    let (mut message, waiting_on) = match &new_started_details.state {
+        StartedStateDetails::Playing(playing_state) => {
+            let message = format!(
+                "Turn {} in {}! Timer is in {}{}{}",
+                playing_state.turn,
+                alias,
+                discord_date_format(playing_state.turn_deadline),
+                mk_possible_stale_message(possible_stales, option_snek_state),
+                mk_possible_dead_message(defeated_this_turn, option_snek_state),
+            );
+            let waiting_on = playing_state
+                .players
+                .iter()
+                .filter_map(|potential_player| match potential_player {
+                    PotentialPlayer::RegisteredAndGame(player, details)
+                        if details.player_status.is_human()
+                            && details.submitted != SubmissionStatus::Submitted =>
+                    {
+                        Some(mention_or_name(
+                            player,
+                            details.nation_identifier.name(option_snek_state),
+                        ))
+                    }
+                    PotentialPlayer::RegisteredOnly(player, mod_nation)
+                        if playing_state.modded_nations =>
+                    {
+                        Some(mention_or_name(player, mod_nation.name(option_snek_state)))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            (message, waiting_on)
+        }
+        StartedStateDetails::Uploading(uploading_state) => {
+            let message = format!(
+                "Uploading has started in {}! Server address is '{}'.",
+                alias, new_started_details.address
+            );
+            let waiting_on = uploading_state
+                .uploading_players
+                .iter()
+                .filter(|uploading_player| !uploading_player.uploaded)
+                .filter_map(|uploading_player| {
+                    uploading_player.option_player_id().map(|player| {
+                        mention_or_name(player, uploading_player.nation_name(option_snek_state))
+                    })
+                })
+                .collect::<Vec<_>>();
+            (message, waiting_on)
+        }
+    };
+    if !waiting_on.is_empty() {
+        message.push_str("\nWaiting on: ");
+        message.push_str(&waiting_on.join(", "));
+    }
+    message
+}
+
 fn mk_possible_stale_message(
     possible_stales: &[Nation],
     option_snek_state: Option<&SnekGameStatus>,
@@ -344,7 +972,7 @@ fn mk_possible_dead_message(
 }
 
 fn create_playing_message_for_mod_player(
-    alias: &str,
+    alias: &GameAlias,
     new_playing_details: &PlayingState,
     option_snek_state: Option<&SnekGameStatus>,
     player: &Player,
@@ -352,31 +980,19 @@ fn create_playing_message_for_mod_player(
     possible_stales: &[Nation],
     defeated_this_turn: &[&Nation],
 ) -> Option<NewTurnNation> {
-    if player.turn_notifications {
-        let deadline = discord_date_format(new_playing_details.turn_deadline);
-
-        let possible_stale_message = mk_possible_stale_message(possible_stales, option_snek_state);
-        let possible_dead_message = mk_possible_dead_message(defeated_this_turn, option_snek_state);
-
-        return Some(NewTurnNation {
-            user_id: player.discord_user_id,
-            message: format!(
-                "Turn {} in {}! You are \"{}\" and timer is in {}{}{}",
-                new_playing_details.turn,
-                alias,
-                mod_nation.name(option_snek_state),
-                deadline,
-                possible_stale_message,
-                possible_dead_message,
-            ),
-        });
-    } else {
-        None
-    }
+    mk_new_turn_message(
+        alias,
+        new_playing_details,
+        option_snek_state,
+        player,
+        format!("\"{}\"", mod_nation.name(option_snek_state)),
+        possible_stales,
+        defeated_this_turn,
+    )
 }
 
 fn create_playing_message(
-    alias: &str,
+    alias: &GameAlias,
     new_playing_details: &PlayingState,
     option_snek_state: Option<&SnekGameStatus>,
     player: &Player,
@@ -388,32 +1004,140 @@ fn create_playing_message(
     // n.b. Illwinter's dom6 server status has a bug where players can show as partialed on a new turn
     if let SubmissionStatus::NotSubmitted | SubmissionStatus::PartiallySubmitted = details.submitted
     {
-        // and if they're actually playing and haven't turned notifications off
-        if details.player_status.is_human() && player.turn_notifications {
-            let deadline = discord_date_format(new_playing_details.turn_deadline);
-
-            let possible_stale_message =
-                mk_possible_stale_message(possible_stales, option_snek_state);
-            let possible_dead_message =
-                mk_possible_dead_message(defeated_this_turn, option_snek_state);
-
-            return Some(NewTurnNation {
-                user_id: player.discord_user_id,
-                message: format!(
-                    "Turn {} in {}! You are {} and timer is in {}{}{}",
-                    new_playing_details.turn,
-                    alias,
-                    details.nation_identifier.name(option_snek_state),
-                    deadline,
-                    possible_stale_message,
-                    possible_dead_message,
-                ),
-            });
+        // and if they're actually playing
+        if details.player_status.is_human() {
+            return mk_new_turn_message(
+                alias,
+                new_playing_details,
+                option_snek_state,
+                player,
+                details
+                    .nation_identifier
+                    .name(option_snek_state)
+                    .into_owned(),
+                possible_stales,
+                defeated_this_turn,
+            );
         }
     }
     None
 }
 
+/// Builds the message for a new turn out of whichever parts the player hasn't turned off.
+/// If they only want to hear about stales and defeats, they only get a message when there are some.
+fn mk_new_turn_message(
+    alias: &GameAlias,
+    new_playing_details: &PlayingState,
+    option_snek_state: Option<&SnekGameStatus>,
+    player: &Player,
+    nation_name: String,
+    possible_stales: &[Nation],
+    defeated_this_turn: &[&Nation],
+) -> Option<NewTurnNation> {
+    let notifications = &player.notifications;
+    let possible_stale_message = if notifications.stales {
+        mk_possible_stale_message(possible_stales, option_snek_state)
+    } else {
+        String::new()
+    };
+    let possible_dead_message = if notifications.defeats {
+        mk_possible_dead_message(defeated_this_turn, option_snek_state)
+    } else {
+        String::new()
+    };
+
+    let message = if notifications.new_turn {
+        let deadline = discord_date_format(new_playing_details.turn_deadline);
+        format!(
+            "Turn {} in {}! You are {} and timer is in {}{}{}",
+            new_playing_details.turn,
+            alias,
+            nation_name,
+            deadline,
+            possible_stale_message,
+            possible_dead_message,
+        )
+    } else if !possible_stale_message.is_empty() || !possible_dead_message.is_empty() {
+        format!(
+            "Turn {} in {}{}{}",
+            new_playing_details.turn, alias, possible_stale_message, possible_dead_message,
+        )
+    } else {
+        return None;
+    };
+    Some(NewTurnNation {
+        user_id: player.discord_user_id,
+        message,
+    })
+}
+
+fn mk_game_over_message(
+    alias: &GameAlias,
+    anon_game: bool,
+    game_data: &GameData,
+    players: &[(Player, BotNationIdentifier)],
+) -> String {
+    let winners = game_data
+        .survivors()
+        .map(|nation| {
+            let option_player = players
+                .iter()
+                .find(|(_, bot_nation)| bot_nation.id() == Some(nation.identifier.id()))
+                .filter(|_| !anon_game);
+            match option_player {
+                Some((player, _)) => format!(
+                    "{} ({})",
+                    nation.identifier.name(None),
+                    Mention::from(player.discord_user_id)
+                ),
+                None => nation.identifier.name(None).into_owned(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let result = if winners.is_empty() {
+        "Nobody is left standing.".to_owned()
+    } else {
+        format!("Won by: {}", winners.join(", "))
+    };
+    format!(
+        "{} is over after {} turns! {} The game has been archived, see `!archive {}`.",
+        alias, game_data.turn, result, alias
+    )
+}
+
+fn mk_looks_finished_message(alias: &GameAlias) -> String {
+    format!(
+        "Only one nation is left standing in {}, so it looks like the game is over. \
+        If it is, use `!finish {}` to archive it and stop checking on it.",
+        alias, alias
+    )
+}
+
+fn mk_server_down_message(alias: &GameAlias, health: &ServerHealth) -> String {
+    format!(
+        "The server for {} hasn't answered the last {} checks{}. Is it still running?",
+        alias,
+        health.consecutive_failures,
+        last_seen(health)
+    )
+}
+
+fn mk_server_back_message(alias: &GameAlias, previous_health: &ServerHealth) -> String {
+    format!(
+        "The server for {} is answering again, after {} failed checks{}.",
+        alias,
+        previous_health.consecutive_failures,
+        last_seen(previous_health)
+    )
+}
+
+fn last_seen(health: &ServerHealth) -> String {
+    match health.last_success {
+        Some(last_success) => format!(" (last seen <t:{}:R>)", last_success.timestamp()),
+        None => String::new(),
+    }
+}
+
 fn finished_early(now: DateTime<Utc>, deadline: DateTime<Utc>) -> bool {
     // 4 possible cases:
     //    now ------ >1m ----- deadline
@@ -428,6 +1152,451 @@ fn finished_early(now: DateTime<Utc>, deadline: DateTime<Utc>) -> bool {
     );
     now < deadline
         && remaining_time
-            < Duration::from_std(SERVER_POLL_INTERVAL)
+            < Duration::from_std(config::timings().poll_interval)
                 .expect("okay now THIS really can never happen")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::{
+            game_server::GameServer, nation::GameNationIdentifier, player::NotificationSettings,
+        },
+        server::packet::{Compression, ProtocolVersion},
+        test_harness::*,
+    };
+    use serenity::model::id::GuildId;
+
+    fn alias() -> GameAlias {
+        GameAlias::new(Some(GuildId::new(1)), "fakegame")
+    }
+    const ARCOSCEPHALE: u32 = 5;
+    const MEKONE: u32 = 6;
+    const PANGAEA: u32 = 7;
+
+    fn add_game(db_conn: &DbConnection, address: &str, dom_version: u8) {
+        db_conn
+            .insert_game_server(&GameServer {
+                alias: alias(),
+                state: GameServerState::StartedState(
+                    StartedState {
+                        address: address.to_owned(),
+                        last_seen_turn: -1,
+                    },
+                    None,
+                ),
+                dom_version,
+                anonymous: false,
+            })
+            .unwrap();
+    }
+
+    fn register(db_conn: &DbConnection, user_id: u64, nation_id: u32) {
+        let player = Player {
+            discord_user_id: UserId::new(user_id),
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        };
+        db_conn
+            .insert_player_into_server(&player, &alias(), BotNationIdentifier::from_id_6(nation_id))
+            .unwrap();
+    }
+
+    async fn poll_all(db_conn: &DbConnection, cache: &DetailsCacheHandle) -> PendingMessages {
+        fetch_new_state_and_update_details_cache_for_game(
+            &alias(),
+            db_conn.clone(),
+            cache.clone(),
+            TurnCheckSettings::default().down_alert_after_failures,
+        )
+        .await
+        .unwrap()
+        .0
+    }
+
+    async fn poll(db_conn: &DbConnection, cache: &DetailsCacheHandle) -> Vec<NewTurnNation> {
+        poll_all(db_conn, cache).await.dms
+    }
+
+    async fn poll_pinned(
+        db_conn: &DbConnection,
+        cache: &DetailsCacheHandle,
+    ) -> Vec<PinnedStatusUpdate> {
+        poll_all(db_conn, cache).await.pinned_statuses
+    }
+
+    fn message_for(messages: &[NewTurnNation], user_id: u64) -> &str {
+        &messages
+            .iter()
+            .find(|message| message.user_id == UserId::new(user_id))
+            .unwrap_or_else(|| panic!("no message for {} in {:?}", user_id, messages))
+            .message
+    }
+
+    fn dom6_name(nation_id: u32) -> String {
+        GameNationIdentifier::from_id_6(nation_id)
+            .name(None)
+            .into_owned()
+    }
+
+    #[tokio::test]
+    async fn new_turns_stales_and_defeats() {
+        let game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE)
+            .with_ai(PANGAEA);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.address, 6);
+        register(db_conn, 1, ARCOSCEPHALE);
+        register(db_conn, 2, MEKONE);
+
+        // nothing to say while it's being set up
+        assert!(poll(db_conn, &cache).await.is_empty());
+
+        server.game().host_turn();
+        let messages = poll(db_conn, &cache).await;
+        assert_eq!(2, messages.len());
+        assert!(message_for(&messages, 1).starts_with("Turn 1 in fakegame! You are"));
+        assert!(!message_for(&messages, 1).contains("Possible stales"));
+
+        // submitting isn't a new turn
+        server.game().submit(ARCOSCEPHALE);
+        assert!(poll(db_conn, &cache).await.is_empty());
+
+        // the timer runs out with mekone still not submitted
+        server.game().time_out();
+        assert!(poll(db_conn, &cache).await.is_empty());
+        server.game().host_turn();
+        let messages = poll(db_conn, &cache).await;
+        assert_eq!(2, messages.len());
+        assert!(message_for(&messages, 1).starts_with("Turn 2 in fakegame!"));
+        assert!(
+            message_for(&messages, 1).contains(&format!("Possible stales: {}", dom6_name(MEKONE)))
+        );
+
+        // everybody submits early and mekone gets wiped out
+        server.game().submit(ARCOSCEPHALE);
+        server.game().submit(MEKONE);
+        assert!(poll(db_conn, &cache).await.is_empty());
+        server.game().defeat(MEKONE);
+        server.game().host_turn();
+        let messages = poll(db_conn, &cache).await;
+        let message = message_for(&messages, 1);
+        assert!(message.starts_with("Turn 3 in fakegame!"));
+        assert!(!message.contains("Possible stales"));
+        assert!(message.contains(&format!("Defeated this turn (rip): {}", dom6_name(MEKONE))));
+
+        assert_eq!(
+            3,
+            db_conn
+                .game_for_alias(&alias())
+                .map(|game| match game.state {
+                    GameServerState::StartedState(started_state, _) => started_state.last_seen_turn,
+                    GameServerState::Lobby(_) => panic!("game turned back into a lobby"),
+                })
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn pinned_status_only_updates_when_something_changes() {
+        let game = FakeGame::new("samog").with_human(ARCOSCEPHALE);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.address, 6);
+        db_conn
+            .set_pinned_status(&alias(), Some((ChannelId::new(1), MessageId::new(2))))
+            .unwrap();
+        server.game().host_turn();
+        // nothing cached yet
+        assert_eq!(1, poll_pinned(db_conn, &cache).await.len());
+        // same turn, same timer
+        assert!(poll_pinned(db_conn, &cache).await.is_empty());
+
+        server.game().submit(ARCOSCEPHALE);
+        assert_eq!(1, poll_pinned(db_conn, &cache).await.len());
+    }
+
+    #[tokio::test]
+    async fn owner_hears_when_the_server_goes_down_and_comes_back() {
+        let mut game = FakeGame::new("samog").with_human(ARCOSCEPHALE);
+        game.host_turn();
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.address, 6);
+        db_conn.set_game_owner(&alias(), UserId::new(9)).unwrap();
+
+        assert!(poll(db_conn, &cache).await.is_empty());
+        let last_success = db_conn
+            .server_health_for_game(&alias())
+            .unwrap()
+            .last_success;
+        assert!(last_success.is_some());
+
+        server.game().crashed = true;
+        let failures = TurnCheckSettings::default().down_alert_after_failures;
+        for _ in 1..failures {
+            assert!(poll(db_conn, &cache).await.is_empty());
+        }
+        let messages = poll(db_conn, &cache).await;
+        assert!(message_for(&messages, 9).starts_with(&format!(
+            "The server for fakegame hasn't answered the last {} checks",
+            failures
+        )));
+        // only tell them once
+        assert!(poll(db_conn, &cache).await.is_empty());
+        assert_eq!(
+            ServerHealth {
+                consecutive_failures: failures + 1,
+                last_success,
+            },
+            db_conn.server_health_for_game(&alias()).unwrap()
+        );
+
+        server.game().crashed = false;
+        let messages = poll(db_conn, &cache).await;
+        assert!(message_for(&messages, 9).starts_with("The server for fakegame is answering again"));
+        assert!(!db_conn
+            .server_health_for_game(&alias())
+            .unwrap()
+            .is_unreachable());
+    }
+
+    #[tokio::test]
+    async fn finished_games_are_announced_and_archived() {
+        let game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.status_url, 6);
+        register(db_conn, 1, ARCOSCEPHALE);
+        register(db_conn, 2, MEKONE);
+
+        server.game().host_turn();
+        poll(db_conn, &cache).await;
+        server.game().defeat(MEKONE);
+        server.game().host_turn();
+        server.game().finished = true;
+        let messages = poll(db_conn, &cache).await;
+        let game_over = messages
+            .iter()
+            .filter(|message| message.message.contains("fakegame is over"))
+            .collect::<Vec<_>>();
+        assert_eq!(2, game_over.len());
+        assert!(game_over[0]
+            .message
+            .contains(&format!("Won by: {} (<@1>)", dom6_name(ARCOSCEPHALE))));
+
+        // it's gone from the games we poll, and into the archive
+        assert!(db_conn.game_for_alias(&alias()).is_err());
+        assert!(cache.get_clone(&alias()).await.is_err());
+        let archived = db_conn.archived_games().unwrap();
+        assert_eq!(1, archived.len());
+        assert_eq!(alias(), archived[0].alias);
+        assert_eq!(2, archived[0].final_turn);
+        let winners = archived[0].winners().collect::<Vec<_>>();
+        assert_eq!(1, winners.len());
+        assert_eq!(Some(UserId::new(1)), winners[0].player);
+        assert!(!db_conn
+            .archived_turn_history(archived[0].id)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn reminders_are_only_marked_as_sent_once_they_have_been_delivered() {
+        let game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.address, 6);
+        register(db_conn, 1, ARCOSCEPHALE);
+        register(db_conn, 2, MEKONE);
+
+        server.game().host_turn();
+        poll(db_conn, &cache).await;
+        // the turn timer is a day, so these are already due
+        db_conn
+            .set_reminders(&alias(), UserId::new(1), &[2 * 24 * 60])
+            .unwrap();
+        db_conn
+            .set_reminders(&alias(), UserId::new(2), &[2 * 24 * 60])
+            .unwrap();
+        server.game().submit(MEKONE);
+
+        for _ in 0..2 {
+            // until it's delivered, the reminder for user 1 comes back on every poll
+            let reminders = poll_all(db_conn, &cache).await.reminders;
+            assert_eq!(1, reminders.len());
+            assert_eq!(UserId::new(1), reminders[0].dm.user_id);
+            assert!(reminders[0].dm.message.starts_with("Reminder: turn 1"));
+            assert_eq!(1, reminders[0].reminders.len());
+        }
+        let last_sent_turn = |user_id: u64| {
+            db_conn
+                .reminders_for_game(&alias())
+                .unwrap()
+                .into_iter()
+                .find(|reminder| reminder.discord_user_id == UserId::new(user_id))
+                .unwrap()
+                .last_sent_turn
+        };
+        assert_eq!(None, last_sent_turn(1));
+        // user 2 has submitted, so there's nothing to send them
+        assert_eq!(Some(1), last_sent_turn(2));
+    }
+
+    #[tokio::test]
+    async fn direct_connect_games_with_one_survivor_are_not_archived() {
+        let game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let cache = empty_details_cache();
+        add_game(db_conn, &server.address, 6);
+        register(db_conn, 1, ARCOSCEPHALE);
+        register(db_conn, 2, MEKONE);
+        db_conn.set_game_owner(&alias(), UserId::new(3)).unwrap();
+
+        server.game().host_turn();
+        poll(db_conn, &cache).await;
+        server.game().defeat(MEKONE);
+        server.game().host_turn();
+        let messages = poll(db_conn, &cache).await;
+        assert!(messages
+            .iter()
+            .all(|message| !message.message.contains("fakegame is over")));
+        assert!(message_for(&messages, 3).contains("!finish fakegame"));
+
+        // still polled, and nothing in the archive
+        assert!(db_conn.game_for_alias(&alias()).is_ok());
+        assert!(cache.get_clone(&alias()).await.is_ok());
+        assert!(db_conn.archived_games().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn status_page_says_when_the_game_is_finished() {
+        let mut game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        game.host_turn();
+        game.defeat(MEKONE);
+        game.host_turn();
+        game.finished = true;
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+
+        let game_data = get_game_data_async(&server.status_url, 6).await.unwrap();
+        assert!(game_data.finished);
+        assert_eq!(
+            vec![GameNationIdentifier::from_id_6(ARCOSCEPHALE)],
+            game_data
+                .survivors()
+                .map(|nation| nation.identifier.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn plain_frames_for_dom5() {
+        let mut game = FakeGame::new("curtains")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        game.host_turn();
+        game.go_ai(MEKONE);
+        game.partially_submit(ARCOSCEPHALE);
+        game.connect(ARCOSCEPHALE, true);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom5, Compression::Plain)
+            .await
+            .unwrap();
+
+        let game_data = get_game_data_async(&server.address, 5).await.unwrap();
+        assert_eq!("curtains", game_data.game_name);
+        assert_eq!(1, game_data.turn);
+        let nations = game_data.nations.into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Nation {
+                    identifier: GameNationIdentifier::from_id(ARCOSCEPHALE),
+                    status: NationStatus::Human,
+                    submitted: SubmissionStatus::PartiallySubmitted,
+                    connected: true,
+                },
+                Nation {
+                    identifier: GameNationIdentifier::from_id(MEKONE),
+                    status: NationStatus::AI,
+                    submitted: SubmissionStatus::Submitted,
+                    connected: false,
+                },
+            ],
+            nations
+        );
+    }
+
+    #[tokio::test]
+    async fn status_page() {
+        let game = FakeGame::new("samog")
+            .with_human(ARCOSCEPHALE)
+            .with_human(MEKONE);
+        let server = FakeDominionsServer::start(game, ProtocolVersion::Dom6, Compression::Zlib)
+            .await
+            .unwrap();
+
+        let game_data = get_game_data_async(&server.status_url, 6).await.unwrap();
+        assert_eq!(-1, game_data.turn);
+
+        server.game().host_turn();
+        server.game().submit(MEKONE);
+        let game_data = get_game_data_async(&server.status_url, 6).await.unwrap();
+        assert_eq!("samog", game_data.game_name);
+        assert_eq!(1, game_data.turn);
+        let submissions = game_data
+            .nations
+            .into_iter()
+            .flatten()
+            .map(|nation| (nation.identifier, nation.submitted))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (
+                    GameNationIdentifier::from_id_6(ARCOSCEPHALE),
+                    SubmissionStatus::NotSubmitted
+                ),
+                (
+                    GameNationIdentifier::from_id_6(MEKONE),
+                    SubmissionStatus::Submitted
+                ),
+            ],
+            submissions
+        );
+    }
+}
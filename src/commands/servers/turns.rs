@@ -1,3 +1,4 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use crate::{
     commands::servers::{
         details::started_details_from_server, discord_date_format, CommandResponse,
@@ -5,7 +6,7 @@ use crate::{
     db::*,
     model::{
         enums::*,
-        game_server::GameServerState,
+        game_server::{GameAlias, GameServerState},
         game_state::{
             GameDetails, NationDetails, PlayingState, PotentialPlayer, StartedStateDetails,
             UploadingState,
@@ -16,7 +17,6 @@ use crate::{
 };
 use log::*;
 use serenity::{
-    framework::standard::{Args, CommandError},
     model::id::{ChannelId, UserId},
     prelude::Context,
 };
@@ -33,7 +33,7 @@ async fn turns_helper(
 
     lines.push("Your turns:".to_string());
     let db_conn = &db_conn;
-    for (server, _) in servers_and_nations_for_player {
+    for (server, _, notifications) in servers_and_nations_for_player {
         if let GameServerState::StartedState(started_state, option_lobby_state) = server.state {
             match read_handle.get_clone(&server.alias).await {
                 Ok(cache) => {
@@ -46,10 +46,10 @@ async fn turns_helper(
                         cache.option_snek_state.as_ref(),
                     )?;
 
-                    match details.nations {
+                    let mut texts = match details.nations {
                         NationDetails::Started(started_state) => match started_state.state {
                             StartedStateDetails::Uploading(uploading_state) => {
-                                let mut texts = turns_for_uploading_state(
+                                turns_for_uploading_state(
                                     &uploading_state,
                                     user_id,
                                     &server.alias,
@@ -57,23 +57,23 @@ async fn turns_helper(
                                         .cache_entry
                                         .and_then(|cache_entry| cache_entry.option_snek_state)
                                         .as_ref(),
-                                );
-                                lines.append(&mut texts);
-                            }
-                            StartedStateDetails::Playing(playing_state) => {
-                                let mut texts = turns_for_playing_state(
-                                    &playing_state,
-                                    user_id,
-                                    &server.alias,
-                                    details
-                                        .cache_entry
-                                        .and_then(|cache_entry| cache_entry.option_snek_state)
-                                        .as_ref(),
-                                );
-                                lines.append(&mut texts);
+                                )
                             }
+                            StartedStateDetails::Playing(playing_state) => turns_for_playing_state(
+                                &playing_state,
+                                user_id,
+                                &server.alias,
+                                details
+                                    .cache_entry
+                                    .and_then(|cache_entry| cache_entry.option_snek_state)
+                                    .as_ref(),
+                            ),
                         },
                         NationDetails::Lobby(_) => continue,
+                    };
+                    if !texts.is_empty() {
+                        lines.append(&mut texts);
+                        lines.push(format!("    notifications: {}", notifications.show()));
                     }
                 }
                 Err(err) => {
@@ -88,7 +88,7 @@ async fn turns_helper(
 fn turns_for_uploading_state(
     uploading_state: &UploadingState,
     user_id: UserId,
-    alias: &str,
+    alias: &GameAlias,
     option_snek_state: Option<&SnekGameStatus>,
 ) -> Vec<String> {
     let mut texts = vec![];
@@ -148,7 +148,7 @@ fn turns_for_uploading_state(
 fn turns_for_playing_state(
     playing_state: &PlayingState,
     user_id: UserId,
-    alias: &str,
+    alias: &GameAlias,
     option_snek_state: Option<&SnekGameStatus>,
 ) -> Vec<String> {
     let mut texts = vec![];
@@ -159,45 +159,42 @@ fn turns_for_playing_state(
         match playing_player {
             // if they're registered, and there's bot nations, notify them anyway
             PotentialPlayer::RegisteredOnly(potential_player, bot_nation)
-                if playing_state.modded_nations =>
+                if playing_state.modded_nations && potential_player.discord_user_id == user_id =>
             {
-                if potential_player.discord_user_id == user_id {
-                    let deadline = discord_date_format(playing_state.turn_deadline);
-                    let turn_str = format!(
-                        "{} turn {} ({}): {} (submitted: {}, {}/{})",
-                        alias,
-                        playing_state.turn,
-                        deadline,
-                        bot_nation.name(option_snek_state),
-                        SubmissionStatus::NotSubmitted.show(),
-                        submitted_players,
-                        playing_players,
-                    );
-                    texts.push(turn_str);
-                }
+                let deadline = discord_date_format(playing_state.turn_deadline);
+                let turn_str = format!(
+                    "{} turn {} ({}): {} (submitted: {}, {}/{})",
+                    alias,
+                    playing_state.turn,
+                    deadline,
+                    bot_nation.name(option_snek_state),
+                    SubmissionStatus::NotSubmitted.show(),
+                    submitted_players,
+                    playing_players,
+                );
+                texts.push(turn_str);
             }
-            PotentialPlayer::RegisteredAndGame(potential_player, potential_player_details) => {
-                // FIXME: there used to be a nation_id check on here. What is this for?
-                //        does it fail only when people are registered multiple times?
+            // FIXME: there used to be a nation_id check on here. What is this for?
+            //        does it fail only when people are registered multiple times?
+            PotentialPlayer::RegisteredAndGame(potential_player, potential_player_details)
                 if potential_player.discord_user_id == user_id
-                    && potential_player_details.player_status.is_human()
-                {
-                    let deadline = discord_date_format(playing_state.turn_deadline);
+                    && potential_player_details.player_status.is_human() =>
+            {
+                let deadline = discord_date_format(playing_state.turn_deadline);
 
-                    let turn_str = format!(
-                        "{} turn {} ({}): {} (submitted: {}, {}/{})",
-                        alias,
-                        playing_state.turn,
-                        deadline,
-                        potential_player_details
-                            .nation_identifier
-                            .name(option_snek_state),
-                        potential_player_details.submitted.show(),
-                        submitted_players,
-                        playing_players,
-                    );
-                    texts.push(turn_str);
-                }
+                let turn_str = format!(
+                    "{} turn {} ({}): {} (submitted: {}, {}/{})",
+                    alias,
+                    playing_state.turn,
+                    deadline,
+                    potential_player_details
+                        .nation_identifier
+                        .name(option_snek_state),
+                    potential_player_details.submitted.show(),
+                    submitted_players,
+                    playing_players,
+                );
+                texts.push(turn_str);
             }
             _ => (),
         }
@@ -236,7 +233,7 @@ pub async fn turns(
     context: &Context,
     _channel_id: ChannelId,
     user_id: UserId,
-    _args: Args,
+    _args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     let read_handle = DetailsCacheHandle(Arc::clone(&context.data));
     let db_conn = {
@@ -245,6 +242,7 @@ pub async fn turns(
             .ok_or_else(|| CommandError::from("No db connection"))?
             .clone()
     };
+    // this only ever goes to their DMs, since it includes anonymous games
     let lines = turns_helper(user_id, db_conn, read_handle).await?;
     let messages = lines.chunks(20);
     let private_channel = user_id.create_dm_channel(&context.http).await?;
@@ -1,15 +1,16 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use log::*;
-use serenity::framework::standard::{Args, CommandError};
 use serenity::model::id::{ChannelId, UserId};
 use serenity::prelude::Context;
 
-use super::alias_from_arg_or_channel_name;
+use super::{alias_from_arg_or_channel_name, waitlist::promote_from_waitlist};
 use crate::commands::servers::CommandResponse;
 use crate::db::{DbConnection, DbConnectionKey};
+use crate::model::game_server::GameAlias;
 
 fn unregister_player_helper(
     user_id: UserId,
-    alias: &str,
+    alias: &GameAlias,
     db_conn: DbConnection,
 ) -> Result<(), CommandError> {
     let rows_affected = db_conn
@@ -27,18 +28,21 @@ pub async fn unregister_player(
     context: &Context,
     channel_id: ChannelId,
     user_id: UserId,
-    mut args: Args,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
     let db_conn = {
         let data = context.data.read().await;
         data.get::<DbConnectionKey>()
             .ok_or("No db connection")?
             .clone()
     };
-    unregister_player_helper(user_id, &alias, db_conn)?;
+    unregister_player_helper(user_id, &alias, db_conn.clone())?;
 
-    let text = format!("Removed from all nations in game {}", alias);
+    let mut text = format!("Removed from all nations in game {}", alias);
+    if let Some(promoted_text) = promote_from_waitlist(&context.http, &db_conn, &alias).await? {
+        text = format!("{}\n{}", text, promoted_text);
+    }
     info!("{}", text);
     Ok(CommandResponse::Reply(text))
 }
@@ -1,13 +1,16 @@
+use crate::commands::registry::{CommandArgs, CommandError};
 use anyhow::anyhow;
-use serenity::{
-    framework::standard::{Args, CommandError},
-    prelude::Context,
-};
+use serenity::prelude::Context;
 use std::sync::Arc;
 
 use crate::commands::servers::CommandResponse;
 use crate::{
-    commands::servers::alias_from_arg_or_channel_name, db::*, model::game_server::GameServerState,
+    commands::servers::{
+        alias_from_arg_or_channel_name, permissions::check_can_manage_game,
+        pin::delete_pinned_status,
+    },
+    db::*,
+    model::game_server::{GameAlias, GameServerState},
     DetailsCacheHandle, DetailsCacheKey,
 };
 use serenity::model::id::{ChannelId, UserId};
@@ -15,7 +18,7 @@ use serenity::model::id::{ChannelId, UserId};
 async fn unstart_helper(
     db_conn: DbConnection,
     handle: DetailsCacheHandle,
-    alias: &str,
+    alias: &GameAlias,
 ) -> Result<(), CommandError> {
     let server = db_conn.game_for_alias(alias)?;
 
@@ -38,8 +41,8 @@ async fn unstart_helper(
 pub async fn unstart(
     context: &Context,
     channel_id: ChannelId,
-    _user_id: UserId,
-    mut args: Args,
+    user_id: UserId,
+    args: CommandArgs,
 ) -> Result<CommandResponse, CommandError> {
     let data_handle = DetailsCacheHandle(Arc::clone(&context.data));
 
@@ -49,13 +52,11 @@ pub async fn unstart(
             .ok_or("No DbConnection was created on startup. This is a bug.")?
             .clone()
     };
-    let alias = alias_from_arg_or_channel_name(context, channel_id, &mut args).await?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+    let alias = alias_from_arg_or_channel_name(context, channel_id, args.string("name")).await?;
+    check_can_manage_game(context, channel_id, user_id, &db_conn, &alias).await?;
+    let option_pinned = db_conn.pinned_status_for_game(&alias)?;
     unstart_helper(db_conn, data_handle, &alias).await?;
+    delete_pinned_status(&context.http, option_pinned).await;
     Ok(CommandResponse::Reply(format!(
         "Successfully turned '{}' back into a lobby",
         alias
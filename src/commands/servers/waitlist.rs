@@ -0,0 +1,193 @@
+//! Players queueing for a place in a full lobby, who get in when somebody leaves
+
+use crate::commands::registry::CommandError;
+use crate::{
+    db::DbConnection,
+    model::{
+        game_server::{GameAlias, GameServerState},
+        nation::BotNationIdentifier,
+        player::{NotificationSettings, Player},
+    },
+};
+use log::*;
+use serenity::{
+    http::Http,
+    model::{id::UserId, mention::Mention},
+};
+
+pub(super) fn join_waitlist_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+    user_id: UserId,
+    nation: BotNationIdentifier,
+) -> Result<String, CommandError> {
+    let waitlist = db_conn.waitlist(alias)?;
+    if waitlist.iter().any(|(player, _)| *player == user_id) {
+        return Err(format!(
+            "You are already on the waitlist for {}. Use !unregister first to change your nation.",
+            alias
+        )
+        .into());
+    }
+    let nation_name = nation.name(None);
+    let player = Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+        notifications: NotificationSettings::all(true),
+    };
+    db_conn.join_waitlist(alias, &player, nation)?;
+    Ok(format!(
+        "{} is full, so you're #{} on the waitlist as {}. I'll DM you if a place opens up.",
+        alias,
+        waitlist.len() + 1,
+        nation_name
+    ))
+}
+
+/// Fills any free places in the lobby from the waitlist in order, skipping anybody whose
+/// nation has been taken or banned since they joined it. Returns who got in.
+fn promote_helper(
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<Vec<(UserId, BotNationIdentifier)>, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    let GameServerState::Lobby(lobby_state) = server.state else {
+        return Ok(vec![]);
+    };
+    let restrictions = db_conn.nation_restrictions(alias)?;
+    let mut players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+    let waiting_for_nation = db_conn.nation_preferences(alias)?.len();
+
+    let mut promoted = vec![];
+    for (user_id, nation) in db_conn.waitlist(alias)? {
+        if (players_nations.len() + waiting_for_nation) as i32 >= lobby_state.player_count {
+            break;
+        }
+        let is_available = nation.id().is_none_or(|nation_id| {
+            restrictions.permits(nation_id)
+                && !players_nations
+                    .iter()
+                    .any(|(_, taken)| taken.id() == Some(nation_id))
+        });
+        if !is_available {
+            continue;
+        }
+        let player = Player {
+            discord_user_id: user_id,
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        };
+        db_conn.insert_player_into_server(&player, alias, nation.clone())?;
+        players_nations.push((player, nation.clone()));
+        promoted.push((user_id, nation));
+    }
+    Ok(promoted)
+}
+
+/// Gives any free places in the lobby to the waitlist, and DMs whoever got in. Returns what
+/// to tell the lobby, if anybody did.
+pub async fn promote_from_waitlist(
+    http: &Http,
+    db_conn: &DbConnection,
+    alias: &GameAlias,
+) -> Result<Option<String>, CommandError> {
+    let promoted = promote_helper(db_conn, alias)?;
+    if promoted.is_empty() {
+        return Ok(None);
+    }
+    for (user_id, nation) in &promoted {
+        let text = format!(
+            "A place opened up in {}, so you're off the waitlist and registered as {}",
+            alias,
+            nation.name(None)
+        );
+        let result = match user_id.create_dm_channel(http).await {
+            Ok(private_channel) => private_channel.say(http, &text).await.map(|_| ()),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            error!("Could not DM {}: {:?}", user_id, err);
+        }
+    }
+    if db_conn.is_game_anonymous(alias)? {
+        return Ok(Some(format!(
+            "The free place in {} has gone to the waitlist. I've DMed whoever got in.",
+            alias
+        )));
+    }
+    let lines = promoted
+        .iter()
+        .map(|(user_id, nation)| {
+            format!(
+                "{} is off the waitlist and registered as {}",
+                Mention::from(*user_id),
+                nation.name(None)
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok(Some(lines.join("\n")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{model::enums::Era, test_harness::TempDb};
+
+    #[test]
+    fn places_go_to_the_waitlist_in_order() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = GameAlias::new(None, "popular");
+        temp_db.add_lobby(&alias, Era::Middle, 1);
+        let player = Player {
+            discord_user_id: UserId::new(2),
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        };
+        db_conn
+            .insert_player_into_server(&player, &alias, BotNationIdentifier::from_id_6(69))
+            .unwrap();
+
+        join_waitlist_helper(
+            db_conn,
+            &alias,
+            UserId::new(3),
+            BotNationIdentifier::from_id_6(68),
+        )
+        .unwrap();
+        join_waitlist_helper(
+            db_conn,
+            &alias,
+            UserId::new(4),
+            BotNationIdentifier::from_id_6(68),
+        )
+        .unwrap();
+        assert!(join_waitlist_helper(
+            db_conn,
+            &alias,
+            UserId::new(4),
+            BotNationIdentifier::from_id_6(56)
+        )
+        .is_err());
+        // still full
+        assert!(promote_helper(db_conn, &alias).unwrap().is_empty());
+
+        db_conn
+            .remove_player_from_game(&alias, UserId::new(2))
+            .unwrap();
+        assert_eq!(
+            vec![(UserId::new(3), BotNationIdentifier::from_id_6(68))],
+            promote_helper(db_conn, &alias).unwrap()
+        );
+        assert_eq!(
+            vec![(UserId::new(4), BotNationIdentifier::from_id_6(68))],
+            db_conn.waitlist(&alias).unwrap()
+        );
+
+        db_conn
+            .remove_player_from_game(&alias, UserId::new(3))
+            .unwrap();
+        assert_eq!(1, promote_helper(db_conn, &alias).unwrap().len());
+        assert!(db_conn.waitlist(&alias).unwrap().is_empty());
+    }
+}
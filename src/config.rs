@@ -0,0 +1,443 @@
+//! Settings for the bot, read from a TOML file (`resources/config.toml`, or wherever
+//! `DOM5BOT_CONFIG` points) and then overridden by `DOM5BOT_*` environment variables.
+//! See `config.example.toml` for every setting.
+
+use anyhow::{anyhow, Context as _};
+use serde::Deserialize;
+use serenity::model::id::ApplicationId;
+use simplelog::LevelFilter;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::OnceLock,
+    time::Duration,
+};
+
+pub const DEFAULT_CONFIG_PATH: &str = "resources/config.toml";
+const ENV_PREFIX: &str = "DOM5BOT_";
+
+/// Exactly what's in the file, before defaults and overrides
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    token: Option<String>,
+    application_id: Option<u64>,
+    db_path: Option<PathBuf>,
+    poll_interval_secs: Option<u64>,
+    server_timeout_secs: Option<u64>,
+    max_concurrent_polls: Option<usize>,
+    down_alert_after_failures: Option<u32>,
+    prefix: Option<String>,
+    slash_commands: Option<String>,
+    log_level: Option<String>,
+    http_api_address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// `None` means fall back to the old `resources/token` file
+    pub token: Option<String>,
+    /// `None` means fall back to the old `resources/application` file
+    pub application_id: Option<ApplicationId>,
+    pub db_path: PathBuf,
+    pub prefix: String,
+    pub slash_commands: SlashCommandScope,
+    pub log_level: LevelFilter,
+    /// The http api is off unless this is set
+    pub http_api_address: Option<SocketAddr>,
+    pub turn_check: TurnCheckSettings,
+    pub timings: Timings,
+}
+
+/// Where slash commands get created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlashCommandScope {
+    /// In every server the bot is in. These update straight away, but don't work in DMs.
+    #[default]
+    Guild,
+    /// Once for the whole application, so they work in DMs too
+    Global,
+    Both,
+}
+impl SlashCommandScope {
+    pub fn has_guild_commands(self) -> bool {
+        matches!(self, SlashCommandScope::Guild | SlashCommandScope::Both)
+    }
+
+    pub fn has_global_commands(self) -> bool {
+        matches!(self, SlashCommandScope::Global | SlashCommandScope::Both)
+    }
+}
+impl FromStr for SlashCommandScope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "guild" => Ok(SlashCommandScope::Guild),
+            "global" => Ok(SlashCommandScope::Global),
+            "both" => Ok(SlashCommandScope::Both),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnCheckSettings {
+    /// How many game servers we'll talk to at once when checking for new turns
+    pub max_concurrent_polls: usize,
+    /// Tell people a game's server is down once this many checks in a row have failed
+    pub down_alert_after_failures: u32,
+}
+impl Default for TurnCheckSettings {
+    fn default() -> Self {
+        TurnCheckSettings {
+            max_concurrent_polls: 10,
+            down_alert_after_failures: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timings {
+    /// How long to wait between checking every game for new turns
+    pub poll_interval: Duration,
+    /// How long to wait for a game's server (or its status page) to answer
+    pub server_timeout: Duration,
+}
+impl Default for Timings {
+    fn default() -> Self {
+        Timings {
+            poll_interval: Duration::from_secs(60),
+            server_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+static TIMINGS: OnceLock<Timings> = OnceLock::new();
+
+/// The timings from the config, or the defaults if it hasn't been loaded (e.g. in tests)
+pub fn timings() -> Timings {
+    *TIMINGS.get_or_init(Timings::default)
+}
+
+impl Config {
+    /// Read the config file and environment, and check everything makes sense
+    pub fn load() -> anyhow::Result<Self> {
+        let (path, must_exist) = match std::env::var(format!("{}CONFIG", ENV_PREFIX)) {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+        };
+        let option_file_text = read_config_file(&path, must_exist)?;
+        let config =
+            Config::from_sources(option_file_text.as_deref(), |name| std::env::var(name).ok())
+                .context(format!(
+                    "Invalid config in '{}' or the environment",
+                    path.display()
+                ))?;
+        Ok(config)
+    }
+
+    /// Remember the timings for code that has no way to be handed the config
+    pub fn install_timings(&self) {
+        if TIMINGS.set(self.timings).is_err() {
+            log::warn!("Timings were already set, ignoring the new ones");
+        }
+    }
+
+    fn from_sources(
+        option_file_text: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        let file: ConfigFile = match option_file_text {
+            Some(text) => toml::from_str(text).context("Could not parse the config file")?,
+            None => ConfigFile::default(),
+        };
+        // the environment wins over the file, and says which one a bad value came from
+        let setting = |name: &str, from_file: Option<String>| -> Option<(String, String)> {
+            let env_name = format!("{}{}", ENV_PREFIX, name.to_uppercase());
+            match env(&env_name) {
+                Some(value) => Some((value, env_name)),
+                None => from_file.map(|value| (value, format!("'{}' in the config file", name))),
+            }
+        };
+        let defaults = Timings::default();
+
+        let token = setting("token", file.token)
+            .map(|(token, source)| {
+                let token = token.trim().to_owned();
+                if token.is_empty() {
+                    Err(anyhow!("{} is empty", source))
+                } else {
+                    Ok(token)
+                }
+            })
+            .transpose()?;
+
+        let application_id = setting(
+            "application_id",
+            file.application_id.map(|id| id.to_string()),
+        )
+        .map(|(id, source)| match u64::from_str(id.trim()) {
+            Ok(0) | Err(_) => Err(anyhow!(
+                "{} should be the bot's discord application id, but was '{}'",
+                source,
+                id
+            )),
+            Ok(id) => Ok(ApplicationId::new(id)),
+        })
+        .transpose()?;
+
+        let db_path = setting(
+            "db_path",
+            file.db_path.map(|path| path.display().to_string()),
+        )
+        .map_or_else(
+            || PathBuf::from("resources/dom5bot.db"),
+            |(path, _)| path.into(),
+        );
+
+        let poll_interval = parse_seconds(
+            setting(
+                "poll_interval_secs",
+                file.poll_interval_secs.map(|secs| secs.to_string()),
+            ),
+            defaults.poll_interval,
+        )?;
+        let server_timeout = parse_seconds(
+            setting(
+                "server_timeout_secs",
+                file.server_timeout_secs.map(|secs| secs.to_string()),
+            ),
+            defaults.server_timeout,
+        )?;
+        if server_timeout >= poll_interval {
+            return Err(anyhow!(
+                "The server timeout ({}s) has to be shorter than the poll interval ({}s)",
+                server_timeout.as_secs(),
+                poll_interval.as_secs()
+            ));
+        }
+
+        let default_turn_check = TurnCheckSettings::default();
+        let max_concurrent_polls = parse_count(
+            setting(
+                "max_concurrent_polls",
+                file.max_concurrent_polls.map(|max| max.to_string()),
+            ),
+            default_turn_check.max_concurrent_polls,
+        )?;
+        let down_alert_after_failures = parse_count(
+            setting(
+                "down_alert_after_failures",
+                file.down_alert_after_failures
+                    .map(|count| count.to_string()),
+            ),
+            default_turn_check.down_alert_after_failures,
+        )?;
+
+        let prefix = match setting("prefix", file.prefix) {
+            None => "!".to_owned(),
+            Some((prefix, source)) => {
+                if prefix.is_empty() || prefix.contains(char::is_whitespace) {
+                    return Err(anyhow!(
+                        "{} should be something like '!' with no spaces, but was '{}'",
+                        source,
+                        prefix
+                    ));
+                }
+                prefix
+            }
+        };
+
+        let slash_commands = match setting("slash_commands", file.slash_commands) {
+            None => SlashCommandScope::default(),
+            Some((scope, source)) => SlashCommandScope::from_str(scope.trim()).map_err(|_| {
+                anyhow!(
+                    "{} should be one of guild, global or both, but was '{}'",
+                    source,
+                    scope
+                )
+            })?,
+        };
+
+        let log_level = match setting("log_level", file.log_level) {
+            None => LevelFilter::Warn,
+            Some((level, source)) => LevelFilter::from_str(level.trim()).map_err(|_| {
+                anyhow!(
+                    "{} should be one of off, error, warn, info, debug or trace, but was '{}'",
+                    source,
+                    level
+                )
+            })?,
+        };
+
+        let http_api_address = setting("http_api_address", file.http_api_address)
+            .map(|(address, source)| {
+                SocketAddr::from_str(address.trim()).map_err(|_| {
+                    anyhow!(
+                        "{} should be an address like 127.0.0.1:8080, but was '{}'",
+                        source,
+                        address
+                    )
+                })
+            })
+            .transpose()?;
+
+        Ok(Config {
+            token,
+            application_id,
+            db_path,
+            prefix,
+            slash_commands,
+            log_level,
+            http_api_address,
+            turn_check: TurnCheckSettings {
+                max_concurrent_polls,
+                down_alert_after_failures,
+            },
+            timings: Timings {
+                poll_interval,
+                server_timeout,
+            },
+        })
+    }
+}
+
+fn read_config_file(path: &Path, must_exist: bool) -> anyhow::Result<Option<String>> {
+    if !must_exist && !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(path)
+        .context(format!("Reading config file '{}'", path.display()))?;
+    Ok(Some(text))
+}
+
+fn parse_seconds(
+    option_setting: Option<(String, String)>,
+    default: Duration,
+) -> anyhow::Result<Duration> {
+    match option_setting {
+        None => Ok(default),
+        Some((secs, source)) => match u64::from_str(secs.trim()) {
+            Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(anyhow!(
+                "{} should be a whole number of seconds above 0, but was '{}'",
+                source,
+                secs
+            )),
+        },
+    }
+}
+
+fn parse_count<T: FromStr + Default + PartialOrd>(
+    option_setting: Option<(String, String)>,
+    default: T,
+) -> anyhow::Result<T> {
+    match option_setting {
+        None => Ok(default),
+        Some((count, source)) => match T::from_str(count.trim()) {
+            Ok(count) if count > T::default() => Ok(count),
+            _ => Err(anyhow!(
+                "{} should be a whole number above 0, but was '{}'",
+                source,
+                count
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn load(file_text: Option<&str>, env: &[(&str, &str)]) -> anyhow::Result<Config> {
+        let env = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        Config::from_sources(file_text, |name| env.get(name).cloned())
+    }
+
+    #[test]
+    fn defaults_without_a_file() {
+        let config = load(None, &[]).unwrap();
+        assert_eq!(None, config.token);
+        assert_eq!(PathBuf::from("resources/dom5bot.db"), config.db_path);
+        assert_eq!("!", config.prefix);
+        assert_eq!(SlashCommandScope::Guild, config.slash_commands);
+        assert_eq!(LevelFilter::Warn, config.log_level);
+        assert_eq!(None, config.http_api_address);
+        assert_eq!(TurnCheckSettings::default(), config.turn_check);
+        assert_eq!(Timings::default(), config.timings);
+    }
+
+    #[test]
+    fn environment_overrides_file() {
+        let file = r#"
+            token = "file-token"
+            application_id = 1234
+            poll_interval_secs = 120
+            prefix = "?"
+        "#;
+        let config = load(
+            Some(file),
+            &[
+                ("DOM5BOT_TOKEN", "env-token"),
+                ("DOM5BOT_LOG_LEVEL", "debug"),
+                ("DOM5BOT_SLASH_COMMANDS", "both"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(Some("env-token".to_owned()), config.token);
+        assert_eq!(Some(ApplicationId::new(1234)), config.application_id);
+        assert_eq!(Duration::from_secs(120), config.timings.poll_interval);
+        assert_eq!("?", config.prefix);
+        assert_eq!(LevelFilter::Debug, config.log_level);
+        assert_eq!(SlashCommandScope::Both, config.slash_commands);
+    }
+
+    #[test]
+    fn bad_values_say_where_they_came_from() {
+        let err = load(None, &[("DOM5BOT_POLL_INTERVAL_SECS", "soon")]).unwrap_err();
+        assert!(err.to_string().contains("DOM5BOT_POLL_INTERVAL_SECS"));
+
+        let err = load(Some("log_level = \"loud\""), &[]).unwrap_err();
+        assert!(err.to_string().contains("'log_level' in the config file"));
+
+        let err = load(Some("server_timeout_secs = 90"), &[]).unwrap_err();
+        assert!(err.to_string().contains("shorter than the poll interval"));
+
+        assert!(load(Some("tokn = \"typo\""), &[]).is_err());
+        assert!(load(Some("application_id = 0"), &[]).is_err());
+        assert!(load(None, &[("DOM5BOT_HTTP_API_ADDRESS", "localhost")]).is_err());
+        assert!(load(Some("slash_commands = \"everywhere\""), &[]).is_err());
+    }
+    #[test]
+    fn slash_command_scopes() {
+        let scopes = ["guild", "Global", " both "]
+            .iter()
+            .map(|scope| {
+                load(None, &[("DOM5BOT_SLASH_COMMANDS", scope)])
+                    .unwrap()
+                    .slash_commands
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                SlashCommandScope::Guild,
+                SlashCommandScope::Global,
+                SlashCommandScope::Both
+            ],
+            scopes
+        );
+        assert_eq!(
+            vec![(true, false), (false, true), (true, true)],
+            scopes
+                .iter()
+                .map(|scope| (scope.has_guild_commands(), scope.has_global_commands()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,18 +1,27 @@
+use crate::model::archived_game::{ArchivedGame, ArchivedNation};
+use crate::model::draft::{Draft, DraftOrder, DraftPick};
 use crate::model::enums::*;
-use crate::model::game_server::{GameServer, GameServerState, LobbyState, StartedState};
-use crate::model::player::Player;
+use crate::model::game_data::GameData;
+use crate::model::game_server::{GameAlias, GameServer, GameServerState, LobbyState, StartedState};
+use crate::model::nation_restrictions::NationRestrictions;
+use crate::model::player::{NotificationSettings, Player};
+use crate::model::reminder::Reminder;
+use crate::model::server_health::ServerHealth;
+use crate::model::sub_request::SubRequest;
+use crate::model::turn_history::NationTurnRecord;
 
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use log::*;
 use migrant_lib::{Config, EmbeddedMigration, Migratable, Migrator, Settings};
 use num_traits::{FromPrimitive, ToPrimitive};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
-use serenity::model::id::UserId;
+use rusqlite::{params, OptionalExtension};
+use serenity::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
 
-use crate::model::nation::BotNationIdentifier;
+use crate::model::nation::{BotNationIdentifier, GameNationIdentifier, Nation};
 use serenity::prelude::TypeMapKey;
 use std::path::Path;
 
@@ -22,7 +31,7 @@ impl TypeMapKey for DbConnectionKey {
 }
 
 lazy_static! {
-    static ref MIGRATIONS: [EmbeddedMigration; 4] = {
+    static ref MIGRATIONS: [EmbeddedMigration; 19] = {
         let mut m1 = EmbeddedMigration::with_tag("001-baseline");
         m1.up(include_str!("db/sql/migrations/001_baseline.sql"));
 
@@ -35,7 +44,56 @@ lazy_static! {
         let mut m4 = EmbeddedMigration::with_tag("004-dom-version");
         m4.up(include_str!("db/sql/migrations/004_dom_game.sql"));
 
-        [m1, m2, m3, m4]
+        let mut m5 = EmbeddedMigration::with_tag("005-turn-history");
+        m5.up(include_str!("db/sql/migrations/005_turn_history.sql"));
+
+        let mut m6 = EmbeddedMigration::with_tag("006-reminders");
+        m6.up(include_str!("db/sql/migrations/006_reminders.sql"));
+
+        let mut m7 = EmbeddedMigration::with_tag("007-game-notifications");
+        m7.up(include_str!("db/sql/migrations/007_game_notifications.sql"));
+
+        let mut m8 = EmbeddedMigration::with_tag("008-announce-channel");
+        m8.up(include_str!("db/sql/migrations/008_announce_channel.sql"));
+
+        let mut m9 = EmbeddedMigration::with_tag("009-pinned-status");
+        m9.up(include_str!("db/sql/migrations/009_pinned_status.sql"));
+
+        let mut m10 = EmbeddedMigration::with_tag("010-permissions");
+        m10.up(include_str!("db/sql/migrations/010_permissions.sql"));
+
+        let mut m11 = EmbeddedMigration::with_tag("011-guild-aliases");
+        m11.up(include_str!("db/sql/migrations/011_guild_aliases.sql"));
+
+        let mut m12 = EmbeddedMigration::with_tag("012-server-health");
+        m12.up(include_str!("db/sql/migrations/012_server_health.sql"));
+
+        let mut m13 = EmbeddedMigration::with_tag("013-archive");
+        m13.up(include_str!("db/sql/migrations/013_archive.sql"));
+
+        let mut m14 = EmbeddedMigration::with_tag("014-anonymous-games");
+        m14.up(include_str!("db/sql/migrations/014_anonymous_games.sql"));
+
+        let mut m15 = EmbeddedMigration::with_tag("015-sub-needed");
+        m15.up(include_str!("db/sql/migrations/015_sub_needed.sql"));
+
+        let mut m16 = EmbeddedMigration::with_tag("016-drafts");
+        m16.up(include_str!("db/sql/migrations/016_drafts.sql"));
+
+        let mut m17 = EmbeddedMigration::with_tag("017-nation-preferences");
+        m17.up(include_str!("db/sql/migrations/017_nation_preferences.sql"));
+
+        let mut m18 = EmbeddedMigration::with_tag("018-nation-restrictions");
+        m18.up(include_str!(
+            "db/sql/migrations/018_nation_restrictions.sql"
+        ));
+
+        let mut m19 = EmbeddedMigration::with_tag("019-waitlist");
+        m19.up(include_str!("db/sql/migrations/019_waitlist.sql"));
+
+        [
+            m1, m2, m3, m4, m5, m6, m7, m8, m9, m10, m11, m12, m13, m14, m15, m16, m17, m18, m19,
+        ]
     };
 }
 
@@ -43,14 +101,26 @@ lazy_static! {
 pub struct DbConnection(Pool<SqliteConnectionManager>);
 impl DbConnection {
     pub fn new<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::with_migrations(path, &MIGRATIONS[..])
+    }
+
+    /// Tests use fewer migrations to make a database like an old version of the bot's
+    fn with_migrations<P: AsRef<Path>>(
+        path: P,
+        migrations: &[EmbeddedMigration],
+    ) -> anyhow::Result<Self> {
         let manager = SqliteConnectionManager::file(&path);
         let pool = Pool::new(manager)?;
         let db_conn = DbConnection(pool);
-        db_conn.initialise(&path)?;
+        db_conn.initialise(&path, migrations)?;
         Ok(db_conn)
     }
 
-    fn initialise<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+    fn initialise<P: AsRef<Path>>(
+        &self,
+        path: P,
+        migrations: &[EmbeddedMigration],
+    ) -> anyhow::Result<()> {
         info!("db::initialise");
         let settings = Settings::configure_sqlite()
             .database_path(path)
@@ -65,10 +135,10 @@ impl DbConnection {
         config
             .use_migrations(
                 // there has GOT to be a better way
-                MIGRATIONS[..]
+                migrations
                     .iter()
                     .cloned()
-                    .map(|migration| -> Box<(dyn Migratable + 'static)> { Box::new(migration) })
+                    .map(|migration| -> Box<dyn Migratable + 'static> { Box::new(migration) })
                     .collect::<Vec<_>>(),
             )
             .map_err(|e| anyhow!(e.to_string()))?;
@@ -114,11 +184,13 @@ impl DbConnection {
                 tx.execute(
                     include_str!("db/sql/insert_game_server_from_lobby.sql"),
                     params![
-                        &game_server.alias,
+                        &game_server.alias.name,
                         &(game_server.dom_version as i32),
                         &lobby_state.era.to_i32(),
                         &(lobby_state.owner.get() as i64),
                         &lobby_state.player_count,
+                        &game_server.alias.guild_key(),
+                        &game_server.anonymous,
                     ],
                 )?;
                 tx.commit()?;
@@ -133,9 +205,11 @@ impl DbConnection {
                 tx.execute(
                     include_str!("db/sql/insert_started_game_server.sql"),
                     params![
-                        &game_server.alias,
+                        &game_server.alias.name,
                         &(game_server.dom_version as i32),
-                        &started_state.address
+                        &started_state.address,
+                        &game_server.alias.guild_key(),
+                        &game_server.anonymous,
                     ],
                 )?;
                 tx.commit()?;
@@ -160,11 +234,13 @@ impl DbConnection {
                 tx.execute(
                     include_str!("db/sql/insert_game_server_from_lobby.sql"),
                     params![
-                        &game_server.alias,
+                        &game_server.alias.name,
                         &(game_server.dom_version as i32),
                         &lobby_state.era.to_i32(),
                         &(lobby_state.owner.get() as i64),
                         &lobby_state.player_count,
+                        &game_server.alias.guild_key(),
+                        &game_server.anonymous,
                     ],
                 )?;
                 tx.execute(
@@ -177,7 +253,8 @@ impl DbConnection {
                     params![
                         &started_state.address,
                         &started_state.last_seen_turn,
-                        &game_server.alias,
+                        &game_server.alias.name,
+                        &game_server.alias.guild_key(),
                     ],
                 )?;
                 tx.commit()?;
@@ -189,7 +266,7 @@ impl DbConnection {
     pub fn insert_player_into_server(
         &self,
         player: &Player,
-        server_alias: &str,
+        server_alias: &GameAlias,
         nation_identifier: BotNationIdentifier,
     ) -> anyhow::Result<()> {
         // We actually still want custom_nation_name = None for static nations here even when we know the name
@@ -209,13 +286,39 @@ impl DbConnection {
                 &player.turn_notifications,
             ],
         )?;
+        // somebody who picked their own nation doesn't need to be given one
+        tx.execute(
+            include_str!("db/sql/delete_player_nation_preferences.sql"),
+            params![
+                &server_alias.name,
+                &(player.discord_user_id.get() as i64),
+                &server_alias.guild_key()
+            ],
+        )?;
+        // anybody on the waitlist who gets a place is off it
+        tx.execute(
+            include_str!("db/sql/delete_waitlist_player.sql"),
+            params![
+                &server_alias.name,
+                &(player.discord_user_id.get() as i64),
+                &server_alias.guild_key()
+            ],
+        )?;
+        // registering as a nation that's waiting for a sub takes it over
+        if let Some(nation_id) = nation_id {
+            tx.execute(
+                include_str!("db/sql/delete_open_server_player.sql"),
+                params![&nation_id, &server_alias.name, &server_alias.guild_key()],
+            )?;
+        }
         tx.execute(
             include_str!("db/sql/insert_server_player.sql"),
             params![
                 &nation_id,
                 &custom_nation_name,
                 &(player.discord_user_id.get() as i64),
-                &server_alias
+                &server_alias.name,
+                &server_alias.guild_key(),
             ],
         )?;
         tx.commit()?;
@@ -230,12 +333,13 @@ impl DbConnection {
             .query_and_then(params![], |row| -> anyhow::Result<GameServer> {
                 let maybe_address: Option<String> = row.get(1)?;
                 let maybe_last_seen_turn: Option<i32> = row.get(2)?;
-                let alias: String = row.get(0)?;
+                let alias = GameAlias::from_guild_key(row.get(8)?, row.get(0)?);
                 let maybe_owner: Option<i64> = row.get(3)?;
                 let lobby_era: Option<i32> = row.get(4)?;
                 let maybe_player_count: Option<i32> = row.get(5)?;
                 let description: Option<String> = row.get(6)?;
                 let dom_version: Option<i32> = row.get(7)?;
+                let anonymous: bool = row.get(9)?;
 
                 let game_server = make_game_server(
                     alias,
@@ -246,6 +350,7 @@ impl DbConnection {
                     maybe_player_count,
                     description,
                     dom_version,
+                    anonymous,
                 )?;
 
                 Ok(game_server)
@@ -256,17 +361,24 @@ impl DbConnection {
 
     pub fn players_with_nations_for_game_alias(
         &self,
-        game_alias: &str,
+        game_alias: &GameAlias,
     ) -> anyhow::Result<Vec<(Player, BotNationIdentifier)>> {
         info!("players_with_nations_for_game_alias");
         let conn = &*self.0.clone().get()?;
         let mut stmt = conn.prepare(include_str!("db/sql/select_players_nations.sql"))?;
         let vec = stmt
-            .query_map([&game_alias], |row| {
+            .query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
                 let discord_user_id: i64 = row.get(0).unwrap();
                 let player = Player {
                     discord_user_id: UserId::new(discord_user_id as u64),
                     turn_notifications: row.get(3).unwrap(),
+                    notifications: NotificationSettings {
+                        new_turn: row.get(5).unwrap(),
+                        uploading: row.get(6).unwrap(),
+                        reminders: row.get(7).unwrap(),
+                        stales: row.get(8).unwrap(),
+                        defeats: row.get(9).unwrap(),
+                    },
                 };
                 let nation_id_i32: Option<i32> = row.get(1).unwrap();
                 let nation_id_u32 = nation_id_i32.map(|id| id as u32);
@@ -284,12 +396,12 @@ impl DbConnection {
         Ok(vec)
     }
 
-    pub fn game_for_alias(&self, game_alias: &str) -> anyhow::Result<GameServer> {
+    pub fn game_for_alias(&self, game_alias: &GameAlias) -> anyhow::Result<GameServer> {
         info!("db::game_for_alias");
         let conn = &*self.0.clone().get()?;
         let mut stmt = conn.prepare(include_str!("db/sql/select_game_server_for_alias.sql"))?;
         let vec = stmt
-            .query_map([&game_alias], |row| {
+            .query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
                 let maybe_address: Option<String> = row.get(0).unwrap();
                 let maybe_last_seen_turn: Option<i32> = row.get(1).unwrap();
                 let maybe_owner: Option<i64> = row.get(2).unwrap();
@@ -297,8 +409,9 @@ impl DbConnection {
                 let maybe_player_count: Option<i32> = row.get(4).unwrap();
                 let description: Option<String> = row.get(5).unwrap();
                 let dom_version: Option<i32> = row.get(6).unwrap();
+                let anonymous: bool = row.get(7).unwrap();
                 Ok(make_game_server(
-                    game_alias.to_owned(),
+                    game_alias.clone(),
                     maybe_address,
                     maybe_last_seen_turn,
                     maybe_owner,
@@ -306,6 +419,7 @@ impl DbConnection {
                     maybe_player_count,
                     description,
                     dom_version,
+                    anonymous,
                 )
                 .unwrap())
             })?
@@ -320,44 +434,112 @@ impl DbConnection {
         }
     }
 
+    /// Work out which game a command means by its alias. Games in the command's own guild win
+    /// over games that aren't tied to a guild. In a DM the alias has to be unambiguous.
+    /// If there's no such game yet, this is the alias a new game would be added with.
+    pub fn resolve_alias(
+        &self,
+        name: &str,
+        option_guild_id: Option<GuildId>,
+    ) -> anyhow::Result<GameAlias> {
+        info!("db::resolve_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_guilds_for_alias.sql"))?;
+        let visible = stmt
+            .query_map(params![&name], |row| row.get::<_, i64>(0))?
+            .map(|guild_key| Ok(GameAlias::from_guild_key(guild_key?, name.to_owned())))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|alias| alias.is_visible_from(option_guild_id))
+            .collect::<Vec<_>>();
+
+        match option_guild_id {
+            Some(guild_id) => Ok(visible
+                .iter()
+                .find(|alias| alias.guild_id == Some(guild_id))
+                .or_else(|| visible.first())
+                .cloned()
+                .unwrap_or_else(|| GameAlias::new(Some(guild_id), name))),
+            None => match visible.len() {
+                0 => Ok(GameAlias::new(None, name)),
+                1 => Ok(visible.into_iter().next().expect("we just checked the length")),
+                _ => Err(anyhow!(
+                    "There are games called {} in more than one server, use the command in the game's server instead",
+                    name
+                )),
+            },
+        }
+    }
+
     pub fn update_game_with_possibly_new_turn(
         &self,
-        game_alias: &str,
+        game_alias: &GameAlias,
         current_turn: i32,
     ) -> anyhow::Result<bool> {
         info!("db::update_game_with_possibly_new_turn");
         let conn = &mut *self.0.clone().get()?;
         let rows = conn.execute(
             include_str!("db/sql/update_game_with_turn.sql"),
-            params![&current_turn, &game_alias],
+            params![&current_turn, &game_alias.name, &game_alias.guild_key()],
         )?;
         info!("db::update_game_with_possibly_new_turn FINISHED");
         Ok(rows > 0)
     }
 
-    pub fn remove_player_from_game(&self, game_alias: &str, user: UserId) -> anyhow::Result<usize> {
+    pub fn remove_player_from_game(
+        &self,
+        game_alias: &GameAlias,
+        user: UserId,
+    ) -> anyhow::Result<usize> {
         info!("db::remove_player_from_game");
-        let conn = &*self.0.clone().get()?;
-        Ok(conn.execute(
-            include_str!("db/sql/delete_player_from_game.sql"),
-            params![&game_alias, &(user.get() as i64)],
-        )?)
-    }
-
-    pub fn remove_server(&self, game_alias: &str) -> anyhow::Result<()> {
-        info!("db::remove_server");
         let conn = &mut *self.0.clone().get()?;
         let tx = conn.transaction()?;
         tx.execute(
-            include_str!("db/sql/delete_server_players.sql"),
-            params![&game_alias],
+            include_str!("db/sql/delete_player_reminders.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
         )?;
         let rows_modified = tx.execute(
-            include_str!("db/sql/delete_game_server.sql"),
-            params![&game_alias],
+            include_str!("db/sql/delete_player_from_game.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )? + tx.execute(
+            include_str!("db/sql/delete_draft_player.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )? + tx.execute(
+            include_str!("db/sql/delete_player_nation_preferences.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )? + tx.execute(
+            include_str!("db/sql/delete_waitlist_player.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
         )?;
-        tx.execute(include_str!("db/sql/delete_started_server.sql"), params![])?;
-        tx.execute(include_str!("db/sql/delete_lobby.sql"), params![])?;
+        tx.commit()?;
+        Ok(rows_modified)
+    }
+
+    pub fn remove_server(&self, game_alias: &GameAlias) -> anyhow::Result<()> {
+        info!("db::remove_server");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let rows_modified = delete_game_server(&tx, game_alias)?;
         if rows_modified != 0 {
             tx.commit()?;
             Ok(())
@@ -369,13 +551,13 @@ impl DbConnection {
     pub fn servers_for_player(
         &self,
         user_id: UserId,
-    ) -> anyhow::Result<Vec<(GameServer, BotNationIdentifier)>> {
+    ) -> anyhow::Result<Vec<(GameServer, BotNationIdentifier, NotificationSettings)>> {
         info!("servers_for_player");
         let conn = &*self.0.clone().get()?;
         let mut stmt = conn.prepare(include_str!("db/sql/select_servers_for_player.sql"))?;
 
         let rows = stmt.query_map([&(user_id.get() as i64)], |row| {
-            let alias: String = row.get(1).unwrap();
+            let alias = GameAlias::from_guild_key(row.get(15).unwrap(), row.get(1).unwrap());
             let maybe_address: Option<String> = row.get(0).unwrap();
             let maybe_last_seen_turn: Option<i32> = row.get(2).unwrap();
             let maybe_owner: Option<i64> = row.get(5).unwrap();
@@ -383,6 +565,7 @@ impl DbConnection {
             let maybe_player_count: Option<i32> = row.get(7).unwrap();
             let description: Option<String> = row.get(8).unwrap();
             let dom_version: Option<i32> = row.get(9).unwrap();
+            let anonymous: bool = row.get(16).unwrap();
             let server = make_game_server(
                 alias,
                 maybe_address,
@@ -392,37 +575,42 @@ impl DbConnection {
                 maybe_player_count,
                 description,
                 dom_version,
+                anonymous,
             )
             .unwrap();
 
             let nation_id_i32: Option<i32> = row.get(3).unwrap();
             let nation_id_u32 = nation_id_i32.map(|id| id as u32);
             let custom_nation_name: Option<String> = row.get(4).unwrap();
-            Ok((server, nation_id_u32, custom_nation_name))
+            let notifications = NotificationSettings {
+                new_turn: row.get(10).unwrap(),
+                uploading: row.get(11).unwrap(),
+                reminders: row.get(12).unwrap(),
+                stales: row.get(13).unwrap(),
+                defeats: row.get(14).unwrap(),
+            };
+            Ok((server, nation_id_u32, custom_nation_name, notifications))
         })?;
 
-        let mut ret: Vec<(GameServer, BotNationIdentifier)> = vec![];
+        let mut ret: Vec<(GameServer, BotNationIdentifier, NotificationSettings)> = vec![];
         for row_result in rows {
-            let (game_server, option_nation_id, option_custom_nation_name) = row_result?;
-            let nation_identifier = match (option_nation_id, option_custom_nation_name) {
-                (Some(nation_id), None) => {
-                    if let Some(static_nation) = Nations::from_id(nation_id) {
-                        Ok(BotNationIdentifier::Existing(static_nation))
-                    } else {
-                        Ok(BotNationIdentifier::CustomId(nation_id))
-                    }
-                }
-                (None, Some(custom_nation_name)) => {
-                    Ok(BotNationIdentifier::CustomName(custom_nation_name))
-                }
-                _ => Err(anyhow!(
+            let (game_server, option_nation_id, option_custom_nation_name, notifications) =
+                row_result?;
+            // the same id is a different nation in dom5 and dom6
+            let nation_identifier = BotNationIdentifier::from_id_and_name(
+                option_nation_id,
+                option_custom_nation_name,
+                game_server.dom_version,
+            )
+            .ok_or_else(|| {
+                anyhow!(
                     "No nation info for user '{}' in game '{}'! This should not happen!",
                     user_id,
                     game_server.alias
-                )),
-            }?;
+                )
+            })?;
 
-            ret.push((game_server, nation_identifier));
+            ret.push((game_server, nation_identifier, notifications));
         }
 
         Ok(ret)
@@ -442,14 +630,42 @@ impl DbConnection {
         Ok(())
     }
 
-    pub fn remove_started_state(&self, alias: &str) -> anyhow::Result<()> {
+    /// Override the global `turn_notifications` for a single game. A `kind` of `None` sets every
+    /// kind at once, and a `desired` of `None` goes back to using the global setting.
+    pub fn set_game_notifications(
+        &self,
+        game_alias: &GameAlias,
+        player: UserId,
+        kind: Option<NotificationKind>,
+        desired: Option<bool>,
+    ) -> anyhow::Result<usize> {
+        info!("db::set_game_notifications");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/update_game_notifications.sql"),
+            params![
+                &game_alias.name,
+                &(player.get() as i64),
+                &kind.and_then(|kind| kind.to_i32()).unwrap_or(0),
+                &desired,
+                &game_alias.guild_key(),
+            ],
+        )?;
+        Ok(rows_modified)
+    }
+
+    pub fn remove_started_state(&self, alias: &GameAlias) -> anyhow::Result<()> {
         info!("remove_started_state");
         let conn = &mut *self.0.clone().get()?;
         let tx = conn.transaction()?;
 
         let rows_modified = tx.execute(
             include_str!("db/sql/update_game_with_null_started_state.sql"),
-            params![&alias],
+            params![&alias.name, &alias.guild_key()],
+        )?;
+        tx.execute(
+            include_str!("db/sql/update_game_with_pinned_status.sql"),
+            params![&alias.name, None::<i64>, None::<i64>, &alias.guild_key()],
         )?;
         tx.execute(include_str!("db/sql/delete_started_server.sql"), params![])?;
         tx.commit()?;
@@ -466,7 +682,7 @@ impl DbConnection {
 
     pub fn insert_started_state(
         &self,
-        alias: &str,
+        alias: &GameAlias,
         started_state: &StartedState,
     ) -> anyhow::Result<()> {
         info!("insert_started_state");
@@ -482,9 +698,15 @@ impl DbConnection {
             params![
                 &started_state.address,
                 &started_state.last_seen_turn,
-                &alias,
+                &alias.name,
+                &alias.guild_key(),
             ],
         )?;
+        // nobody else is getting in once the game is set up
+        tx.execute(
+            include_str!("db/sql/delete_waitlist.sql"),
+            params![&alias.name, &alias.guild_key()],
+        )?;
         tx.commit()?;
         Ok(())
     }
@@ -495,13 +717,14 @@ impl DbConnection {
         let mut stmt = conn.prepare(include_str!("db/sql/select_lobbies.sql"))?;
         let vec = stmt
             .query_map(params![], |row| {
-                let alias: String = row.get(0).unwrap();
+                let alias = GameAlias::from_guild_key(row.get(7).unwrap(), row.get(0).unwrap());
                 let maybe_owner: Option<i64> = row.get(1).unwrap();
                 let maybe_lobby_era: Option<i32> = row.get(2).unwrap();
                 let maybe_player_count: Option<i32> = row.get(3).unwrap();
                 let registered_player_count: i32 = row.get(4).unwrap();
                 let description: Option<String> = row.get(5).unwrap();
                 let dom_version = row.get(6).unwrap();
+                let anonymous: bool = row.get(8).unwrap();
                 let server = make_game_server(
                     alias,
                     None,
@@ -511,6 +734,7 @@ impl DbConnection {
                     maybe_player_count,
                     description,
                     dom_version,
+                    anonymous,
                 )
                 .unwrap();
                 Ok((server, registered_player_count))
@@ -521,14 +745,14 @@ impl DbConnection {
 
     pub fn update_lobby_with_description(
         &self,
-        alias: &str,
+        alias: &GameAlias,
         description: &str,
     ) -> anyhow::Result<()> {
         info!("update_lobby_with_description");
         let conn = &*self.0.clone().get()?;
         let rows_modified = conn.execute(
             include_str!("db/sql/update_lobby_with_description.sql"),
-            [&alias, &description],
+            params![&alias.name, &description, &alias.guild_key()],
         )?;
         if rows_modified != 0 {
             Ok(())
@@ -537,12 +761,16 @@ impl DbConnection {
         }
     }
 
-    pub fn update_lobby_with_alias(&self, old_alias: &str, new_alias: &str) -> anyhow::Result<()> {
+    pub fn update_lobby_with_alias(
+        &self,
+        old_alias: &GameAlias,
+        new_alias: &str,
+    ) -> anyhow::Result<()> {
         info!("update_lobby_with_alias");
         let conn = &*self.0.clone().get()?;
         let rows_modified = conn.execute(
             include_str!("db/sql/update_game_with_alias.sql"),
-            [old_alias, new_alias],
+            params![&old_alias.name, &new_alias, &old_alias.guild_key()],
         )?;
         if rows_modified != 0 {
             Ok(())
@@ -550,63 +778,1422 @@ impl DbConnection {
             Err(anyhow!("Could not find game with name {}", old_alias))
         }
     }
-}
-#[allow(clippy::too_many_arguments)] // fuck off clippy
-fn make_game_server(
-    alias: String,
-    maybe_address: Option<String>,
-    maybe_last_seen_turn: Option<i32>,
-    maybe_owner: Option<i64>,
-    maybe_era: Option<i32>,
-    maybe_player_count: Option<i32>,
-    description: Option<String>,
-    dominions_version: Option<i32>,
-) -> anyhow::Result<GameServer> {
-    let dom_version = dominions_version.unwrap_or(6) as u8;
 
-    let state = match (
-        maybe_address,
-        maybe_last_seen_turn,
-        maybe_owner,
-        maybe_player_count,
-        maybe_era,
-    ) {
-        (Some(address), Some(last_seen_turn), None, None, None) => GameServerState::StartedState(
-            StartedState {
-                address,
-                last_seen_turn,
-            },
-            None,
-        ),
-        (Some(address), Some(last_seen_turn), Some(owner), Some(player_count), Some(era)) => {
-            GameServerState::StartedState(
-                StartedState {
-                    address,
-                    last_seen_turn,
-                },
-                Some(LobbyState {
-                    owner: UserId::new(owner as u64),
-                    era: Era::from_i32(era).ok_or_else(|| anyhow!("unknown era"))?,
-                    player_count,
-                    description,
-                }),
-            )
+    /// Move a game from before games were per-server into a server. Fails if that server
+    /// already has a game with the same alias.
+    pub fn move_game_to_guild(
+        &self,
+        old_alias: &GameAlias,
+        guild_id: GuildId,
+    ) -> anyhow::Result<GameAlias> {
+        info!("move_game_to_guild");
+        let new_alias = GameAlias::new(Some(guild_id), &old_alias.name);
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/update_game_with_guild.sql"),
+            params![
+                &old_alias.name,
+                &old_alias.guild_key(),
+                &new_alias.guild_key()
+            ],
+        )?;
+        if rows_modified != 0 {
+            Ok(new_alias)
+        } else {
+            Err(anyhow!("Could not find game with name {}", old_alias))
         }
-        (None, None, Some(owner), Some(player_count), Some(era)) => {
-            GameServerState::Lobby(LobbyState {
-                owner: UserId::new(owner as u64),
-                era: Era::from_i32(era).ok_or_else(|| anyhow!("unknown era"))?,
-                player_count,
-                description,
-            })
+    }
+
+    /// Record the latest state of every nation for the current turn. Called on every poll,
+    /// so each turn ends up with the last state we saw before it rolled over.
+    pub fn upsert_turn_history(
+        &self,
+        game_alias: &GameAlias,
+        game_data: &GameData,
+        recorded_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        info!("db::upsert_turn_history");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        for nation in game_data.nations.iter().flatten() {
+            tx.execute(
+                include_str!("db/sql/upsert_turn_history.sql"),
+                params![
+                    &game_alias.name,
+                    &game_data.turn,
+                    &game_data.turn_deadline.timestamp(),
+                    &nation.identifier.id(),
+                    &(nation.status as u8),
+                    &nation.submitted.to_int(),
+                    &nation.connected,
+                    &recorded_at.timestamp(),
+                    &game_alias.guild_key(),
+                ],
+            )?;
         }
-        _ => return Err(anyhow!("invalid db state for {}", alias)),
-    };
+        tx.commit()?;
+        Ok(())
+    }
 
-    let server = GameServer {
-        dom_version,
-        alias,
-        state,
-    };
-    Ok(server)
+    pub fn select_turn_history(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<Vec<NationTurnRecord>> {
+        info!("db::select_turn_history");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_turn_history.sql"))?;
+        let vec = stmt
+            .query_and_then(
+                params![&game_alias.name, &game_alias.guild_key()],
+                nation_turn_record_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    /// Move a finished game and its history into the archive, and forget everything else
+    /// about it (players, reminders etc.)
+    pub fn archive_game(
+        &self,
+        game_alias: &GameAlias,
+        game_data: &GameData,
+        finished_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        info!("db::archive_game");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let rows_modified = tx.execute(
+            include_str!("db/sql/insert_archived_game.sql"),
+            params![
+                &game_alias.name,
+                &game_alias.guild_key(),
+                &game_data.game_name,
+                &game_data.turn,
+                &finished_at.timestamp(),
+            ],
+        )?;
+        if rows_modified == 0 {
+            return Err(anyhow!(
+                "Could not find started game with name {}",
+                game_alias
+            ));
+        }
+        let archived_game_id = tx.last_insert_rowid();
+        for nation in game_data.nations.iter().flatten() {
+            tx.execute(
+                include_str!("db/sql/insert_archived_nation.sql"),
+                params![
+                    &archived_game_id,
+                    &nation.identifier.id(),
+                    &(nation.status as u8),
+                    &matches!(nation.status, NationStatus::Human | NationStatus::AI),
+                    &game_alias.name,
+                    &game_alias.guild_key(),
+                ],
+            )?;
+        }
+        tx.execute(
+            include_str!("db/sql/insert_archived_turn_history.sql"),
+            params![&game_alias.name, &game_alias.guild_key(), &archived_game_id],
+        )?;
+        delete_game_server(&tx, game_alias)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every archived game, most recently finished first
+    pub fn archived_games(&self) -> anyhow::Result<Vec<ArchivedGame>> {
+        info!("db::archived_games");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_archived_games.sql"))?;
+        let mut nations_stmt = conn.prepare(include_str!("db/sql/select_archived_nations.sql"))?;
+        let vec = stmt
+            .query_and_then(params![], |row| -> anyhow::Result<ArchivedGame> {
+                let id: i64 = row.get(0)?;
+                let dom_version: u8 = row.get(5)?;
+                let finished_at: i64 = row.get(7)?;
+                let owner: Option<i64> = row.get(8)?;
+                let nations = nations_stmt
+                    .query_and_then(params![&id], |row| -> anyhow::Result<ArchivedNation> {
+                        let nation_id: u32 = row.get(0)?;
+                        let nation_status: u8 = row.get(1)?;
+                        let player: Option<i64> = row.get(2)?;
+                        Ok(ArchivedNation {
+                            identifier: match dom_version {
+                                5 => GameNationIdentifier::from_id(nation_id),
+                                _ => GameNationIdentifier::from_id_6(nation_id),
+                            },
+                            status: NationStatus::from_int(nation_status).ok_or_else(|| {
+                                anyhow!("Unknown nation status {}", nation_status)
+                            })?,
+                            player: player.map(|player| UserId::new(player as u64)),
+                            won: row.get(3)?,
+                        })
+                    })?
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(ArchivedGame {
+                    id,
+                    alias: GameAlias::from_guild_key(row.get(1)?, row.get(2)?),
+                    game_name: row.get(3)?,
+                    address: row.get(4)?,
+                    dom_version,
+                    final_turn: row.get(6)?,
+                    finished_at: timestamp_to_datetime(finished_at)?,
+                    owner: owner.map(|owner| UserId::new(owner as u64)),
+                    anonymous: row.get(9)?,
+                    nations,
+                })
+            })?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(vec)
+    }
+
+    pub fn archived_turn_history(
+        &self,
+        archived_game_id: i64,
+    ) -> anyhow::Result<Vec<NationTurnRecord>> {
+        info!("db::archived_turn_history");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_archived_turn_history.sql"))?;
+        let vec = stmt
+            .query_and_then(params![&archived_game_id], nation_turn_record_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    /// Replaces all of the player's reminders for this game
+    pub fn set_reminders(
+        &self,
+        game_alias: &GameAlias,
+        user: UserId,
+        minutes_before: &[i32],
+    ) -> anyhow::Result<()> {
+        info!("db::set_reminders");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/delete_player_reminders.sql"),
+            params![
+                &game_alias.name,
+                &(user.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )?;
+        for minutes in minutes_before {
+            tx.execute(
+                include_str!("db/sql/insert_reminder.sql"),
+                params![
+                    &game_alias.name,
+                    &(user.get() as i64),
+                    minutes,
+                    &game_alias.guild_key()
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn reminders_for_game(&self, game_alias: &GameAlias) -> anyhow::Result<Vec<Reminder>> {
+        info!("db::reminders_for_game");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_reminders.sql"))?;
+        let vec = stmt
+            .query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
+                let discord_user_id: i64 = row.get(0)?;
+                Ok(Reminder {
+                    discord_user_id: UserId::new(discord_user_id as u64),
+                    minutes_before: row.get(1)?,
+                    last_sent_turn: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    pub fn mark_reminder_sent(
+        &self,
+        game_alias: &GameAlias,
+        reminder: &Reminder,
+        turn: i32,
+    ) -> anyhow::Result<()> {
+        info!("db::mark_reminder_sent");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("db/sql/update_reminder_sent.sql"),
+            params![
+                &game_alias.name,
+                &(reminder.discord_user_id.get() as i64),
+                &reminder.minutes_before,
+                &turn,
+                &game_alias.guild_key(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_announce_channel(
+        &self,
+        game_alias: &GameAlias,
+        option_channel_id: Option<ChannelId>,
+    ) -> anyhow::Result<()> {
+        info!("db::set_announce_channel");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/update_game_with_announce_channel.sql"),
+            params![
+                &game_alias.name,
+                &option_channel_id.map(|channel_id| channel_id.get() as i64),
+                &game_alias.guild_key(),
+            ],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(anyhow!("Could not find game with name {}", game_alias))
+        }
+    }
+
+    pub fn announce_channel_for_game(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<Option<ChannelId>> {
+        info!("db::announce_channel_for_game");
+        let conn = &*self.0.clone().get()?;
+        let option_channel_id: Option<i64> = conn.query_row(
+            include_str!("db/sql/select_announce_channel.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+            |row| row.get(0),
+        )?;
+        Ok(option_channel_id.map(|channel_id| ChannelId::new(channel_id as u64)))
+    }
+
+    pub fn set_pinned_status(
+        &self,
+        game_alias: &GameAlias,
+        option_pinned: Option<(ChannelId, MessageId)>,
+    ) -> anyhow::Result<()> {
+        info!("db::set_pinned_status");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/update_game_with_pinned_status.sql"),
+            params![
+                &game_alias.name,
+                &option_pinned.map(|(channel_id, _)| channel_id.get() as i64),
+                &option_pinned.map(|(_, message_id)| message_id.get() as i64),
+                &game_alias.guild_key(),
+            ],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(anyhow!("Could not find game with name {}", game_alias))
+        }
+    }
+
+    pub fn pinned_status_for_game(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<Option<(ChannelId, MessageId)>> {
+        info!("db::pinned_status_for_game");
+        let conn = &*self.0.clone().get()?;
+        let (option_channel_id, option_message_id): (Option<i64>, Option<i64>) = conn.query_row(
+            include_str!("db/sql/select_pinned_status.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(option_channel_id
+            .zip(option_message_id)
+            .map(|(channel_id, message_id)| {
+                (
+                    ChannelId::new(channel_id as u64),
+                    MessageId::new(message_id as u64),
+                )
+            }))
+    }
+
+    pub fn set_game_owner(&self, game_alias: &GameAlias, owner: UserId) -> anyhow::Result<()> {
+        info!("db::set_game_owner");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/insert_player.sql"),
+            params![&(owner.get() as i64), &true],
+        )?;
+        let rows_modified = tx.execute(
+            include_str!("db/sql/update_game_with_owner.sql"),
+            params![
+                &game_alias.name,
+                &(owner.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )?;
+        if rows_modified != 0 {
+            tx.commit()?;
+            Ok(())
+        } else {
+            Err(anyhow!("Could not find game with name {}", game_alias))
+        }
+    }
+
+    /// The lobby owner if there was a lobby, otherwise whoever added the game.
+    /// Games added before owners were recorded don't have one.
+    pub fn owner_for_game(&self, game_alias: &GameAlias) -> anyhow::Result<Option<UserId>> {
+        info!("db::owner_for_game");
+        let conn = &*self.0.clone().get()?;
+        let option_owner: Option<i64> = conn
+            .query_row(
+                include_str!("db/sql/select_game_owner.sql"),
+                params![&game_alias.name, &game_alias.guild_key()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("could not find the game with alias {}", game_alias))?;
+        Ok(option_owner.map(|owner| UserId::new(owner as u64)))
+    }
+
+    /// Flag a nation as needing somebody new to play it. Its player (if it has one) stays
+    /// registered until somebody takes over.
+    pub fn request_sub(&self, game_alias: &GameAlias, nation_id: u32) -> anyhow::Result<()> {
+        info!("db::request_sub");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/upsert_sub_needed.sql"),
+            params![&nation_id, &game_alias.name, &game_alias.guild_key()],
+        )?;
+        if rows_modified == 0 {
+            Err(anyhow!("could not find the game with alias {}", game_alias))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Every nation waiting for a sub, in every game
+    pub fn sub_requests(&self) -> anyhow::Result<Vec<SubRequest>> {
+        info!("db::sub_requests");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_sub_requests.sql"))?;
+        let vec = stmt
+            .query_and_then(params![], |row| -> anyhow::Result<SubRequest> {
+                let nation_id: u32 = row.get(2)?;
+                let dom_version: Option<i32> = row.get(3)?;
+                let lobby_era: Option<i32> = row.get(4)?;
+                let player: Option<i64> = row.get(6)?;
+                let nation = BotNationIdentifier::from_id_and_name(
+                    Some(nation_id),
+                    None,
+                    dom_version.unwrap_or(6) as u8,
+                )
+                .ok_or_else(|| anyhow!("unknown dom version {:?}", dom_version))?;
+                let era = lobby_era.and_then(Era::from_i32).or(match nation {
+                    BotNationIdentifier::Existing(static_nation) => Some(static_nation.era),
+                    _ => None,
+                });
+                Ok(SubRequest {
+                    alias: GameAlias::from_guild_key(row.get(1)?, row.get(0)?),
+                    nation,
+                    era,
+                    last_seen_turn: row.get(5)?,
+                    player: player.map(|player| UserId::new(player as u64)),
+                    anonymous: row.get(7)?,
+                })
+            })?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(vec)
+    }
+
+    /// Hand a nation that's waiting for a sub over to somebody new, all at once so that two
+    /// volunteers can't both get it. Returns whoever was playing it before, if anybody was.
+    pub fn take_over_nation(
+        &self,
+        game_alias: &GameAlias,
+        nation_id: u32,
+        new_player: &Player,
+    ) -> anyhow::Result<Option<UserId>> {
+        info!("db::take_over_nation");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let option_previous_player: Option<i64> = tx
+            .query_row(
+                include_str!("db/sql/select_sub_needed_player.sql"),
+                params![&nation_id, &game_alias.name, &game_alias.guild_key()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("Nobody needs a sub for that nation in {}", game_alias))?;
+        tx.execute(
+            include_str!("db/sql/insert_player.sql"),
+            params![
+                &(new_player.discord_user_id.get() as i64),
+                &new_player.turn_notifications,
+            ],
+        )?;
+        tx.execute(
+            include_str!("db/sql/update_server_player_with_sub.sql"),
+            params![
+                &(new_player.discord_user_id.get() as i64),
+                &nation_id,
+                &game_alias.name,
+                &game_alias.guild_key(),
+            ],
+        )?;
+        tx.commit()?;
+        Ok(option_previous_player.map(|player| UserId::new(player as u64)))
+    }
+
+    /// Turn a lobby into a draft, or change how an existing one will be run
+    pub fn set_draft(
+        &self,
+        game_alias: &GameAlias,
+        order: DraftOrder,
+        pick_minutes: i32,
+    ) -> anyhow::Result<()> {
+        info!("db::set_draft");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/upsert_draft.sql"),
+            params![
+                &order.to_i32(),
+                &pick_minutes,
+                &game_alias.name,
+                &game_alias.guild_key()
+            ],
+        )?;
+        if rows_modified == 0 {
+            Err(anyhow!("could not find the game with alias {}", game_alias))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stop drafting. Anybody who has picked keeps their nation.
+    pub fn remove_draft(&self, game_alias: &GameAlias) -> anyhow::Result<()> {
+        info!("db::remove_draft");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        delete_draft(&tx, game_alias)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn drafts(&self) -> anyhow::Result<Vec<Draft>> {
+        info!("db::drafts");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_drafts.sql"))?;
+        let mut drafts = stmt
+            .query_and_then(params![], |row| -> anyhow::Result<Draft> {
+                let draft_order: i32 = row.get(2)?;
+                let channel_id: Option<i64> = row.get(4)?;
+                let picking_player: Option<i64> = row.get(5)?;
+                let pick_deadline: Option<i64> = row.get(6)?;
+                let current_pick = match (picking_player, pick_deadline) {
+                    (Some(player), Some(deadline)) => Some(DraftPick {
+                        player: UserId::new(player as u64),
+                        deadline: timestamp_to_datetime(deadline)?,
+                    }),
+                    _ => None,
+                };
+                Ok(Draft {
+                    alias: GameAlias::from_guild_key(row.get(1)?, row.get(0)?),
+                    order: DraftOrder::from_i32(draft_order)
+                        .ok_or_else(|| anyhow!("Unknown draft order {}", draft_order))?,
+                    pick_minutes: row.get(3)?,
+                    channel_id: channel_id.map(|channel_id| ChannelId::new(channel_id as u64)),
+                    current_pick,
+                    players: vec![],
+                })
+            })?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(include_str!("db/sql/select_draft_players.sql"))?;
+        let draft_players = stmt
+            .query_map(params![], |row| {
+                let discord_user_id: i64 = row.get(2)?;
+                Ok((
+                    GameAlias::from_guild_key(row.get(1)?, row.get(0)?),
+                    UserId::new(discord_user_id as u64),
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (alias, user_id) in draft_players {
+            if let Some(draft) = drafts.iter_mut().find(|draft| draft.alias == alias) {
+                draft.players.push(user_id);
+            }
+        }
+        Ok(drafts)
+    }
+
+    pub fn draft_for_game(&self, game_alias: &GameAlias) -> anyhow::Result<Option<Draft>> {
+        Ok(self
+            .drafts()?
+            .into_iter()
+            .find(|draft| &draft.alias == game_alias))
+    }
+
+    pub fn join_draft(&self, game_alias: &GameAlias, player: &Player) -> anyhow::Result<()> {
+        info!("db::join_draft");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/insert_player.sql"),
+            params![
+                &(player.discord_user_id.get() as i64),
+                &player.turn_notifications,
+            ],
+        )?;
+        tx.execute(
+            include_str!("db/sql/insert_draft_player.sql"),
+            params![
+                &(player.discord_user_id.get() as i64),
+                &game_alias.name,
+                &game_alias.guild_key()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Fix the pick order and wait for the first player to pick
+    pub fn start_draft(
+        &self,
+        game_alias: &GameAlias,
+        pick_order: &[UserId],
+        channel_id: ChannelId,
+        first_deadline: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        info!("db::start_draft");
+        let first_player = pick_order
+            .first()
+            .ok_or_else(|| anyhow!("Nobody has joined the draft for {}", game_alias))?;
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        for (position, user_id) in pick_order.iter().enumerate() {
+            tx.execute(
+                include_str!("db/sql/update_draft_player_position.sql"),
+                params![
+                    &(position as i32),
+                    &(user_id.get() as i64),
+                    &game_alias.name,
+                    &game_alias.guild_key()
+                ],
+            )?;
+        }
+        tx.execute(
+            include_str!("db/sql/update_draft_channel.sql"),
+            params![
+                &(channel_id.get() as i64),
+                &game_alias.name,
+                &game_alias.guild_key()
+            ],
+        )?;
+        tx.execute(
+            include_str!("db/sql/update_draft_pick.sql"),
+            params![
+                &(first_player.get() as i64),
+                &first_deadline.timestamp(),
+                &game_alias.name,
+                &game_alias.guild_key()
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Wait for somebody else to pick, e.g. because whoever was picking left the lobby
+    pub fn set_draft_pick(&self, game_alias: &GameAlias, pick: &DraftPick) -> anyhow::Result<()> {
+        info!("db::set_draft_pick");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("db/sql/update_draft_pick.sql"),
+            params![
+                &(pick.player.get() as i64),
+                &pick.deadline.timestamp(),
+                &game_alias.name,
+                &game_alias.guild_key()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Register the player as the nation they picked, and move on to the next player.
+    /// Returns who picks next, or `None` if that was the last pick and the draft is over.
+    pub fn make_draft_pick(
+        &self,
+        game_alias: &GameAlias,
+        user_id: UserId,
+        nation: &StaticNation,
+        next_deadline: DateTime<Utc>,
+    ) -> anyhow::Result<Option<UserId>> {
+        info!("db::make_draft_pick");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let rows_modified = tx.execute(
+            include_str!("db/sql/delete_draft_player.sql"),
+            params![
+                &game_alias.name,
+                &(user_id.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )?;
+        if rows_modified == 0 {
+            return Err(anyhow!("{} isn't in the draft for {}", user_id, game_alias));
+        }
+        tx.execute(
+            include_str!("db/sql/insert_server_player.sql"),
+            params![
+                &nation.id,
+                &None::<String>,
+                &(user_id.get() as i64),
+                &game_alias.name,
+                &game_alias.guild_key(),
+            ],
+        )?;
+        let option_next_player: Option<i64> = tx
+            .query_row(
+                include_str!("db/sql/select_next_draft_player.sql"),
+                params![&game_alias.name, &game_alias.guild_key()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match option_next_player {
+            Some(next_player) => {
+                tx.execute(
+                    include_str!("db/sql/update_draft_pick.sql"),
+                    params![
+                        &next_player,
+                        &next_deadline.timestamp(),
+                        &game_alias.name,
+                        &game_alias.guild_key()
+                    ],
+                )?;
+            }
+            None => delete_draft(&tx, game_alias)?,
+        }
+        tx.commit()?;
+        Ok(option_next_player.map(|next_player| UserId::new(next_player as u64)))
+    }
+
+    /// Replaces the nations the player would like in the lobby, best first
+    pub fn set_nation_preferences(
+        &self,
+        game_alias: &GameAlias,
+        player: &Player,
+        nation_ids: &[u32],
+    ) -> anyhow::Result<()> {
+        info!("db::set_nation_preferences");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/insert_player.sql"),
+            params![
+                &(player.discord_user_id.get() as i64),
+                &player.turn_notifications,
+            ],
+        )?;
+        tx.execute(
+            include_str!("db/sql/delete_player_nation_preferences.sql"),
+            params![
+                &game_alias.name,
+                &(player.discord_user_id.get() as i64),
+                &game_alias.guild_key()
+            ],
+        )?;
+        for (rank, nation_id) in nation_ids.iter().enumerate() {
+            tx.execute(
+                include_str!("db/sql/insert_nation_preference.sql"),
+                params![
+                    &(rank as i32),
+                    nation_id,
+                    &(player.discord_user_id.get() as i64),
+                    &game_alias.name,
+                    &game_alias.guild_key()
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Everybody waiting to be given a nation in the lobby, in the order they asked, and
+    /// the nations they'd like best first
+    pub fn nation_preferences(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<Vec<(UserId, Vec<u32>)>> {
+        info!("db::nation_preferences");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_nation_preferences.sql"))?;
+        let rows = stmt
+            .query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
+                let discord_user_id: i64 = row.get(0)?;
+                let nation_id: u32 = row.get(1)?;
+                Ok((UserId::new(discord_user_id as u64), nation_id))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut preferences: Vec<(UserId, Vec<u32>)> = vec![];
+        for (user_id, nation_id) in rows {
+            match preferences
+                .iter_mut()
+                .find(|(player, _)| *player == user_id)
+            {
+                Some((_, nation_ids)) => nation_ids.push(nation_id),
+                None => preferences.push((user_id, vec![nation_id])),
+            }
+        }
+        Ok(preferences)
+    }
+
+    /// Register everybody as the nation they were given, and forget what they'd have liked
+    pub fn assign_nations(
+        &self,
+        game_alias: &GameAlias,
+        assignments: &[(UserId, u32)],
+    ) -> anyhow::Result<()> {
+        info!("db::assign_nations");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        for (user_id, nation_id) in assignments {
+            tx.execute(
+                include_str!("db/sql/insert_server_player.sql"),
+                params![
+                    nation_id,
+                    &None::<String>,
+                    &(user_id.get() as i64),
+                    &game_alias.name,
+                    &game_alias.guild_key(),
+                ],
+            )?;
+        }
+        tx.execute(
+            include_str!("db/sql/delete_nation_preferences.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Queues the player for a place in the full lobby, as the nation they asked for
+    pub fn join_waitlist(
+        &self,
+        game_alias: &GameAlias,
+        player: &Player,
+        nation_identifier: BotNationIdentifier,
+    ) -> anyhow::Result<()> {
+        let (nation_id, custom_nation_name) = match nation_identifier {
+            BotNationIdentifier::CustomId(nation_id) => (Some(nation_id), None),
+            BotNationIdentifier::Existing(existing) => (Some(existing.id), None),
+            BotNationIdentifier::CustomName(custom_name) => (None, Some(custom_name)),
+        };
+
+        info!("db::join_waitlist");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/insert_player.sql"),
+            params![
+                &(player.discord_user_id.get() as i64),
+                &player.turn_notifications,
+            ],
+        )?;
+        tx.execute(
+            include_str!("db/sql/insert_waitlist_player.sql"),
+            params![
+                &nation_id,
+                &custom_nation_name,
+                &(player.discord_user_id.get() as i64),
+                &game_alias.name,
+                &game_alias.guild_key(),
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Everybody waiting for a place in the lobby, first come first served
+    pub fn waitlist(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<Vec<(UserId, BotNationIdentifier)>> {
+        info!("db::waitlist");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_waitlist.sql"))?;
+        let rows = stmt
+            .query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
+                let discord_user_id: i64 = row.get(0)?;
+                let nation_id: Option<u32> = row.get(1)?;
+                let custom_nation_name: Option<String> = row.get(2)?;
+                let dom_version: i32 = row.get(3)?;
+                Ok((
+                    UserId::new(discord_user_id as u64),
+                    nation_id,
+                    custom_nation_name,
+                    dom_version as u8,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(user_id, nation_id, custom_nation_name, dom_version)| {
+                let nation_identifier = BotNationIdentifier::from_id_and_name(
+                    nation_id,
+                    custom_nation_name,
+                    dom_version,
+                )
+                .ok_or_else(|| anyhow!("invalid waitlist nation for {}", user_id))?;
+                Ok((user_id, nation_identifier))
+            })
+            .collect()
+    }
+
+    /// Stops anybody getting the nation in the lobby, even if it was on the allowed list
+    pub fn ban_nation(&self, game_alias: &GameAlias, nation_id: u32) -> anyhow::Result<()> {
+        info!("db::ban_nation");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("db/sql/upsert_nation_restriction.sql"),
+            params![nation_id, false, &game_alias.name, &game_alias.guild_key()],
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether the nation was banned
+    pub fn unban_nation(&self, game_alias: &GameAlias, nation_id: u32) -> anyhow::Result<bool> {
+        info!("db::unban_nation");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("db/sql/delete_nation_ban.sql"),
+            params![nation_id, &game_alias.name, &game_alias.guild_key()],
+        )?;
+        Ok(rows_modified > 0)
+    }
+
+    /// Replaces the only nations players can have in the lobby. Any of them that were
+    /// banned aren't any more, and an empty list allows everything that isn't banned.
+    pub fn set_allowed_nations(
+        &self,
+        game_alias: &GameAlias,
+        nation_ids: &[u32],
+    ) -> anyhow::Result<()> {
+        info!("db::set_allowed_nations");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/delete_allowed_nations.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+        )?;
+        for nation_id in nation_ids {
+            tx.execute(
+                include_str!("db/sql/upsert_nation_restriction.sql"),
+                params![nation_id, true, &game_alias.name, &game_alias.guild_key()],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn nation_restrictions(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<NationRestrictions> {
+        info!("db::nation_restrictions");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("db/sql/select_nation_restrictions.sql"))?;
+        let mut restrictions = NationRestrictions::default();
+        for row in stmt.query_map(params![&game_alias.name, &game_alias.guild_key()], |row| {
+            let nation_id: u32 = row.get(0)?;
+            let allowed: bool = row.get(1)?;
+            Ok((nation_id, allowed))
+        })? {
+            match row? {
+                (nation_id, true) => restrictions.allowed.push(nation_id),
+                (nation_id, false) => restrictions.banned.push(nation_id),
+            }
+        }
+        Ok(restrictions)
+    }
+
+    pub fn is_game_anonymous(&self, game_alias: &GameAlias) -> anyhow::Result<bool> {
+        info!("db::is_game_anonymous");
+        let conn = &*self.0.clone().get()?;
+        conn.query_row(
+            include_str!("db/sql/select_game_anonymous.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("could not find the game with alias {}", game_alias))
+    }
+
+    pub fn server_health_for_game(&self, game_alias: &GameAlias) -> anyhow::Result<ServerHealth> {
+        info!("db::server_health_for_game");
+        let conn = &*self.0.clone().get()?;
+        server_health_for_game(conn, game_alias)
+    }
+
+    /// Forget any failures, and return how things were before this check
+    pub fn record_server_reachable(
+        &self,
+        game_alias: &GameAlias,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<ServerHealth> {
+        info!("db::record_server_reachable");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let previous = server_health_for_game(&tx, game_alias)?;
+        tx.execute(
+            include_str!("db/sql/update_game_with_poll_success.sql"),
+            params![&game_alias.name, &now.timestamp(), &game_alias.guild_key()],
+        )?;
+        tx.commit()?;
+        Ok(previous)
+    }
+
+    /// Count another failure, and return how things are now
+    pub fn record_server_unreachable(
+        &self,
+        game_alias: &GameAlias,
+    ) -> anyhow::Result<ServerHealth> {
+        info!("db::record_server_unreachable");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("db/sql/update_game_with_poll_failure.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+        )?;
+        let health = server_health_for_game(&tx, game_alias)?;
+        tx.commit()?;
+        Ok(health)
+    }
+
+    pub fn set_manager_role(
+        &self,
+        guild_id: GuildId,
+        option_role_id: Option<RoleId>,
+    ) -> anyhow::Result<()> {
+        info!("db::set_manager_role");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("db/sql/upsert_guild_manager_role.sql"),
+            params![
+                &(guild_id.get() as i64),
+                &option_role_id.map(|role_id| role_id.get() as i64)
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn manager_role_for_guild(&self, guild_id: GuildId) -> anyhow::Result<Option<RoleId>> {
+        info!("db::manager_role_for_guild");
+        let conn = &*self.0.clone().get()?;
+        let option_role_id: Option<i64> = conn
+            .query_row(
+                include_str!("db/sql/select_guild_manager_role.sql"),
+                params![&(guild_id.get() as i64)],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(option_role_id.map(|role_id| RoleId::new(role_id as u64)))
+    }
+}
+#[allow(clippy::too_many_arguments)] // fuck off clippy
+fn make_game_server(
+    alias: GameAlias,
+    maybe_address: Option<String>,
+    maybe_last_seen_turn: Option<i32>,
+    maybe_owner: Option<i64>,
+    maybe_era: Option<i32>,
+    maybe_player_count: Option<i32>,
+    description: Option<String>,
+    dominions_version: Option<i32>,
+    anonymous: bool,
+) -> anyhow::Result<GameServer> {
+    let dom_version = dominions_version.unwrap_or(6) as u8;
+
+    let state = match (
+        maybe_address,
+        maybe_last_seen_turn,
+        maybe_owner,
+        maybe_player_count,
+        maybe_era,
+    ) {
+        (Some(address), Some(last_seen_turn), None, None, None) => GameServerState::StartedState(
+            StartedState {
+                address,
+                last_seen_turn,
+            },
+            None,
+        ),
+        (Some(address), Some(last_seen_turn), Some(owner), Some(player_count), Some(era)) => {
+            GameServerState::StartedState(
+                StartedState {
+                    address,
+                    last_seen_turn,
+                },
+                Some(LobbyState {
+                    owner: UserId::new(owner as u64),
+                    era: Era::from_i32(era).ok_or_else(|| anyhow!("unknown era"))?,
+                    player_count,
+                    description,
+                }),
+            )
+        }
+        (None, None, Some(owner), Some(player_count), Some(era)) => {
+            GameServerState::Lobby(LobbyState {
+                owner: UserId::new(owner as u64),
+                era: Era::from_i32(era).ok_or_else(|| anyhow!("unknown era"))?,
+                player_count,
+                description,
+            })
+        }
+        _ => return Err(anyhow!("invalid db state for {}", alias)),
+    };
+
+    let server = GameServer {
+        dom_version,
+        alias,
+        state,
+        anonymous,
+    };
+    Ok(server)
+}
+
+/// Everything about a game except the archive. Returns how many games were deleted.
+fn delete_game_server(tx: &rusqlite::Transaction, game_alias: &GameAlias) -> anyhow::Result<usize> {
+    tx.execute(
+        include_str!("db/sql/delete_server_players.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(
+        include_str!("db/sql/delete_turn_history.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(
+        include_str!("db/sql/delete_server_reminders.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    delete_draft(tx, game_alias)?;
+    tx.execute(
+        include_str!("db/sql/delete_nation_preferences.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(
+        include_str!("db/sql/delete_nation_restrictions.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(
+        include_str!("db/sql/delete_waitlist.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    let rows_modified = tx.execute(
+        include_str!("db/sql/delete_game_server.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(include_str!("db/sql/delete_started_server.sql"), params![])?;
+    tx.execute(include_str!("db/sql/delete_lobby.sql"), params![])?;
+    Ok(rows_modified)
+}
+
+fn delete_draft(tx: &rusqlite::Transaction, game_alias: &GameAlias) -> anyhow::Result<()> {
+    tx.execute(
+        include_str!("db/sql/delete_draft_players.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    tx.execute(
+        include_str!("db/sql/delete_draft.sql"),
+        params![&game_alias.name, &game_alias.guild_key()],
+    )?;
+    Ok(())
+}
+
+fn nation_turn_record_from_row(row: &rusqlite::Row) -> anyhow::Result<NationTurnRecord> {
+    let turn: i32 = row.get(0)?;
+    let turn_deadline: i64 = row.get(1)?;
+    let nation_id: u32 = row.get(2)?;
+    let nation_status: u8 = row.get(3)?;
+    let submitted: u8 = row.get(4)?;
+    let connected: bool = row.get(5)?;
+    let submitted_at: Option<i64> = row.get(6)?;
+    let recorded_at: i64 = row.get(7)?;
+    let dom_version: Option<i32> = row.get(8)?;
+
+    let identifier = match dom_version.unwrap_or(6) {
+        5 => GameNationIdentifier::from_id(nation_id),
+        _ => GameNationIdentifier::from_id_6(nation_id),
+    };
+    let nation = Nation {
+        identifier,
+        status: NationStatus::from_int(nation_status)
+            .ok_or_else(|| anyhow!("Unknown nation status {}", nation_status))?,
+        submitted: SubmissionStatus::from_int(submitted),
+        connected,
+    };
+    Ok(NationTurnRecord {
+        turn,
+        turn_deadline: timestamp_to_datetime(turn_deadline)?,
+        nation,
+        submitted_at: submitted_at.map(timestamp_to_datetime).transpose()?,
+        recorded_at: timestamp_to_datetime(recorded_at)?,
+    })
+}
+
+fn server_health_for_game(
+    conn: &rusqlite::Connection,
+    game_alias: &GameAlias,
+) -> anyhow::Result<ServerHealth> {
+    let (consecutive_failures, last_success_at): (u32, Option<i64>) = conn
+        .query_row(
+            include_str!("db/sql/select_server_health.sql"),
+            params![&game_alias.name, &game_alias.guild_key()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("could not find the game with alias {}", game_alias))?;
+    Ok(ServerHealth {
+        consecutive_failures,
+        last_success: last_success_at.map(timestamp_to_datetime).transpose()?,
+    })
+}
+
+fn timestamp_to_datetime(timestamp: i64) -> anyhow::Result<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp, 0)
+        .ok_or_else(|| anyhow!("invalid timestamp {} in db", timestamp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_harness::TempDb;
+
+    fn lobby(alias: GameAlias) -> GameServer {
+        GameServer {
+            alias,
+            state: GameServerState::Lobby(LobbyState {
+                owner: UserId::new(1),
+                era: Era::Early,
+                player_count: 4,
+                description: None,
+            }),
+            dom_version: 6,
+            anonymous: false,
+        }
+    }
+
+    #[test]
+    fn aliases_are_scoped_by_guild() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let guild_a = GuildId::new(10);
+        let guild_b = GuildId::new(20);
+
+        for alias in [
+            GameAlias::new(Some(guild_a), "samog"),
+            GameAlias::new(Some(guild_b), "samog"),
+            GameAlias::new(None, "oldgame"),
+        ] {
+            db_conn.insert_game_server(&lobby(alias)).unwrap();
+        }
+        assert!(db_conn
+            .insert_game_server(&lobby(GameAlias::new(Some(guild_a), "samog")))
+            .is_err());
+
+        assert_eq!(
+            GameAlias::new(Some(guild_b), "samog"),
+            db_conn.resolve_alias("samog", Some(guild_b)).unwrap()
+        );
+        // games from before guilds were tracked are visible everywhere
+        assert_eq!(
+            GameAlias::new(None, "oldgame"),
+            db_conn.resolve_alias("oldgame", Some(guild_a)).unwrap()
+        );
+        // nothing there yet, so that's where a new game would go
+        assert_eq!(
+            GameAlias::new(Some(guild_a), "newgame"),
+            db_conn.resolve_alias("newgame", Some(guild_a)).unwrap()
+        );
+        assert!(db_conn.resolve_alias("samog", None).is_err());
+
+        db_conn
+            .update_lobby_with_alias(&GameAlias::new(Some(guild_a), "samog"), "samog2")
+            .unwrap();
+        assert_eq!(
+            GameAlias::new(Some(guild_a), "samog2"),
+            db_conn.resolve_alias("samog2", None).unwrap()
+        );
+        assert_eq!(
+            GameAlias::new(Some(guild_b), "samog"),
+            db_conn.resolve_alias("samog", None).unwrap()
+        );
+    }
+
+    #[test]
+    fn games_without_a_guild_can_be_moved_into_one() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let guild = GuildId::new(10);
+        let old_alias = GameAlias::new(None, "oldgame");
+        db_conn
+            .insert_game_server(&lobby(old_alias.clone()))
+            .unwrap();
+        db_conn
+            .insert_game_server(&lobby(GameAlias::new(Some(guild), "taken")))
+            .unwrap();
+        db_conn
+            .insert_game_server(&lobby(GameAlias::new(None, "taken")))
+            .unwrap();
+
+        let new_alias = db_conn.move_game_to_guild(&old_alias, guild).unwrap();
+        assert_eq!(GameAlias::new(Some(guild), "oldgame"), new_alias);
+        assert_eq!(
+            Some(UserId::new(1)),
+            db_conn.owner_for_game(&new_alias).unwrap()
+        );
+        // no longer visible from other guilds
+        assert_eq!(
+            GameAlias::new(Some(GuildId::new(20)), "oldgame"),
+            db_conn
+                .resolve_alias("oldgame", Some(GuildId::new(20)))
+                .unwrap()
+        );
+        assert!(db_conn
+            .move_game_to_guild(&GameAlias::new(None, "taken"), guild)
+            .is_err());
+    }
+
+    #[test]
+    fn renaming_keeps_a_game_anonymous() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let secret = GameAlias::new(None, "secret");
+        let not_secret = GameAlias::new(None, "not_anon");
+        db_conn
+            .insert_game_server(&GameServer {
+                anonymous: true,
+                ..lobby(secret.clone())
+            })
+            .unwrap();
+        db_conn
+            .insert_game_server(&lobby(not_secret.clone()))
+            .unwrap();
+        assert!(!db_conn.is_game_anonymous(&not_secret).unwrap());
+
+        db_conn.update_lobby_with_alias(&secret, "public").unwrap();
+        let renamed = GameAlias::new(None, "public");
+        assert!(db_conn.is_game_anonymous(&renamed).unwrap());
+        assert!(db_conn.game_for_alias(&renamed).unwrap().anonymous);
+    }
+
+    fn player(user_id: u64) -> Player {
+        Player {
+            discord_user_id: UserId::new(user_id),
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        }
+    }
+
+    #[test]
+    fn subs_take_over_flagged_nations() {
+        let temp_db = TempDb::new();
+        let db_conn = &temp_db.db_conn;
+        let alias = GameAlias::new(Some(GuildId::new(10)), "samog");
+        db_conn.insert_game_server(&lobby(alias.clone())).unwrap();
+        db_conn
+            .insert_player_into_server(&player(2), &alias, BotNationIdentifier::from_id_6(5))
+            .unwrap();
+
+        // a nation somebody is playing, and one nobody registered as that went AI
+        db_conn.request_sub(&alias, 5).unwrap();
+        db_conn.request_sub(&alias, 6).unwrap();
+        let sub_requests = db_conn.sub_requests().unwrap();
+        assert_eq!(
+            vec![(5, Some(UserId::new(2))), (6, None)],
+            sub_requests
+                .iter()
+                .map(|sub_request| (sub_request.nation.id().unwrap(), sub_request.player))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(Era::Early), sub_requests[0].era);
+        // an open nation isn't a player in the game
+        assert_eq!(
+            1,
+            db_conn
+                .players_with_nations_for_game_alias(&alias)
+                .unwrap()
+                .len()
+        );
+
+        assert_eq!(
+            Some(UserId::new(2)),
+            db_conn.take_over_nation(&alias, 5, &player(3)).unwrap()
+        );
+        assert_eq!(
+            None,
+            db_conn.take_over_nation(&alias, 6, &player(4)).unwrap()
+        );
+        // nobody can take over a nation twice
+        assert!(db_conn.take_over_nation(&alias, 5, &player(5)).is_err());
+        assert!(db_conn.sub_requests().unwrap().is_empty());
+
+        let mut players = db_conn
+            .players_with_nations_for_game_alias(&alias)
+            .unwrap()
+            .into_iter()
+            .map(|(player, nation)| (player.discord_user_id.get(), nation.id().unwrap()))
+            .collect::<Vec<_>>();
+        players.sort();
+        assert_eq!(vec![(3, 5), (4, 6)], players);
+    }
+
+    #[test]
+    fn guild_aliases_migration_keeps_registered_players() {
+        let path = std::env::temp_dir().join(format!(
+            "dom5bot-test-{}-guild-aliases.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        // as it was before games were scoped by guild, with everything that refers to
+        // a game filled in
+        let old_db_conn = DbConnection::with_migrations(&path, &MIGRATIONS[..10]).unwrap();
+        old_db_conn
+            .0
+            .get()
+            .unwrap()
+            .execute_batch(
+                "insert into players (id, discord_user_id, turn_notifications) values (1, 100, 1);
+                insert into lobbies (id, owner_id, player_count, era) values (1, 1, 4, 1);
+                insert into game_servers (id, alias, lobby_id, dom_version, owner_id)
+                    values (1, 'oldgame', 1, 6, 1);
+                insert into server_players (server_id, player_id, nation_id) values (1, 1, 69);
+                insert into turn_history (server_id, turn, turn_deadline, nation_id,
+                    nation_status, submitted, connected, recorded_at)
+                    values (1, 1, 0, 69, 1, 0, 0, 0);
+                insert into reminders (server_id, player_id, minutes_before)
+                    values (1, 1, 60);",
+            )
+            .unwrap();
+        drop(old_db_conn);
+
+        let db_conn = DbConnection::new(&path).unwrap();
+        let alias = GameAlias::new(None, "oldgame");
+        assert_eq!(
+            vec![69],
+            db_conn
+                .players_with_nations_for_game_alias(&alias)
+                .unwrap()
+                .into_iter()
+                .filter_map(|(_, nation)| nation.id())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, db_conn.reminders_for_game(&alias).unwrap().len());
+        let conn = db_conn.0.get().unwrap();
+        let violations = conn
+            .prepare("PRAGMA foreign_key_check")
+            .unwrap()
+            .query_map([], |_| Ok(()))
+            .unwrap()
+            .count();
+        assert_eq!(0, violations);
+        drop(conn);
+        drop(db_conn);
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,4 @@
+DELETE FROM nation_restrictions
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?2 AND guild_id = ?3)
+AND nation_id = ?1 AND allowed = 0;
@@ -0,0 +1,3 @@
+DELETE FROM nation_restrictions
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?1 AND guild_id = ?2);
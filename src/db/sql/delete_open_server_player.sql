@@ -0,0 +1,4 @@
+DELETE FROM server_players
+WHERE player_id IS NULL AND nation_id = ?1
+AND server_id IN
+    (SELECT id from game_servers WHERE alias = ?2 AND guild_id = ?3);
@@ -1,3 +1,3 @@
 DELETE FROM server_players
 WHERE server_id IN
-    (SELECT id from game_servers WHERE alias = ?1);
+    (SELECT id from game_servers WHERE alias = ?1 AND guild_id = ?2);
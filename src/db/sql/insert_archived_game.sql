@@ -0,0 +1,8 @@
+INSERT INTO archived_games (guild_id, alias, game_name, address, dom_version, final_turn, finished_at, owner_discord_user_id, anonymous)
+SELECT g.guild_id, g.alias, ?3, s.address, COALESCE(g.dom_version, 6), ?4, ?5, COALESCE(lp.discord_user_id, gp.discord_user_id), g.anonymous
+FROM game_servers g
+JOIN started_servers s ON s.id = g.started_server_id
+LEFT JOIN lobbies l ON l.id = g.lobby_id
+LEFT JOIN players lp ON lp.id = l.owner_id
+LEFT JOIN players gp ON gp.id = g.owner_id
+WHERE g.alias = ?1 AND g.guild_id = ?2;
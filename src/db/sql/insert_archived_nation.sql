@@ -0,0 +1,8 @@
+INSERT INTO archived_nations (archived_game_id, nation_id, nation_status, discord_user_id, won)
+SELECT ?1, ?2, ?3,
+    (SELECT p.discord_user_id
+    FROM server_players sp
+    JOIN players p ON p.id = sp.player_id
+    JOIN game_servers g ON g.id = sp.server_id
+    WHERE g.alias = ?5 AND g.guild_id = ?6 AND sp.nation_id = ?2),
+    ?4;
@@ -0,0 +1,5 @@
+INSERT INTO archived_turn_history (archived_game_id, turn, turn_deadline, nation_id, nation_status, submitted, connected, submitted_at, recorded_at)
+SELECT ?3, h.turn, h.turn_deadline, h.nation_id, h.nation_status, h.submitted, h.connected, h.submitted_at, h.recorded_at
+FROM turn_history h
+JOIN game_servers g ON g.id = h.server_id
+WHERE g.alias = ?1 AND g.guild_id = ?2;
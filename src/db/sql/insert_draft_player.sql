@@ -0,0 +1,5 @@
+INSERT INTO draft_players (server_id, player_id)
+SELECT g.id, p.id
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?1
+WHERE g.alias = ?2 AND g.guild_id = ?3;
@@ -1,5 +1,5 @@
-INSERT INTO game_servers (alias, dom_version, lobby_id)
-    SELECT ?1, ?2, l.id
+INSERT INTO game_servers (alias, dom_version, lobby_id, guild_id, anonymous)
+    SELECT ?1, ?2, l.id, ?6, ?7
     FROM lobbies l
     WHERE l.era = ?3
     AND l.id = last_insert_rowid()
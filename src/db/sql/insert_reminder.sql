@@ -0,0 +1,5 @@
+INSERT INTO reminders (server_id, player_id, minutes_before, last_sent_turn)
+SELECT g.id, p.id, ?3, NULL
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?2
+WHERE g.alias = ?1 AND g.guild_id = ?4;
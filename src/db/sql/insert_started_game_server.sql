@@ -1,4 +1,4 @@
-INSERT INTO game_servers (alias, dom_version, started_server_id)
-SELECT ?1, ?2, id
+INSERT INTO game_servers (alias, dom_version, started_server_id, guild_id, anonymous)
+SELECT ?1, ?2, id, ?4, ?5
 FROM started_servers
 WHERE address = ?3;
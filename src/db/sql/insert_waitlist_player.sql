@@ -0,0 +1,5 @@
+INSERT INTO waitlist (server_id, player_id, nation_id, custom_nation_name)
+SELECT g.id, p.id, ?1, ?2
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?3
+WHERE g.alias = ?4 AND g.guild_id = ?5;
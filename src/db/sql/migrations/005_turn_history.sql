@@ -0,0 +1,13 @@
+create table if not exists turn_history (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    turn int NOT NULL,
+    turn_deadline int NOT NULL,
+    nation_id int NOT NULL,
+    nation_status int NOT NULL,
+    submitted int NOT NULL,
+    connected BOOLEAN NOT NULL,
+    submitted_at int,
+    recorded_at int NOT NULL,
+
+    CONSTRAINT turn_history_unique UNIQUE (server_id, turn, nation_id)
+);
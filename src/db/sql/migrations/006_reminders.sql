@@ -0,0 +1,8 @@
+create table if not exists reminders (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    minutes_before int NOT NULL,
+    last_sent_turn int,
+
+    CONSTRAINT reminder_unique UNIQUE (server_id, player_id, minutes_before)
+);
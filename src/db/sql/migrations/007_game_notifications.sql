@@ -0,0 +1,5 @@
+alter table server_players add column notify_new_turn BOOLEAN;
+alter table server_players add column notify_uploading BOOLEAN;
+alter table server_players add column notify_reminders BOOLEAN;
+alter table server_players add column notify_stales BOOLEAN;
+alter table server_players add column notify_defeats BOOLEAN;
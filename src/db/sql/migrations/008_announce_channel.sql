@@ -0,0 +1 @@
+alter table game_servers add column announce_channel_id int;
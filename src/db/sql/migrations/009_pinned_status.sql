@@ -0,0 +1,2 @@
+alter table game_servers add column pinned_channel_id int;
+alter table game_servers add column pinned_message_id int;
@@ -0,0 +1,6 @@
+alter table game_servers add column owner_id int REFERENCES players(id);
+
+create table if not exists guild_settings (
+    guild_id int NOT NULL PRIMARY KEY,
+    manager_role_id int
+);
@@ -0,0 +1,32 @@
+-- server_players, turn_history and reminders all refer to game_servers, so dropping it
+-- fails with foreign keys on. This isn't run in a transaction, so turning them off works.
+PRAGMA foreign_keys = OFF;
+
+-- left over if this failed part way through before
+drop table if exists game_servers_new;
+
+create table if not exists game_servers_new (
+    id INTEGER NOT NULL PRIMARY KEY,
+    -- 0 for games that aren't tied to a discord server
+    guild_id int NOT NULL DEFAULT 0,
+    alias VARCHAR(255) NOT NULL,
+
+    started_server_id int REFERENCES started_servers(id),
+    lobby_id int REFERENCES lobbies(id),
+    dom_version integer,
+    announce_channel_id int,
+    pinned_channel_id int,
+    pinned_message_id int,
+    owner_id int REFERENCES players(id),
+
+    CONSTRAINT server_guild_alias_unique UNIQUE (guild_id, alias)
+);
+
+insert into game_servers_new (id, guild_id, alias, started_server_id, lobby_id, dom_version, announce_channel_id, pinned_channel_id, pinned_message_id, owner_id)
+select id, 0, alias, started_server_id, lobby_id, dom_version, announce_channel_id, pinned_channel_id, pinned_message_id, owner_id from game_servers;
+
+drop table if exists game_servers;
+
+alter table game_servers_new rename to game_servers;
+
+PRAGMA foreign_keys = ON;
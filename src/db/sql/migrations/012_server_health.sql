@@ -0,0 +1,2 @@
+alter table game_servers add column consecutive_failures int NOT NULL DEFAULT 0;
+alter table game_servers add column last_success_at int;
@@ -0,0 +1,37 @@
+create table if not exists archived_games (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    -- 0 for games that weren't tied to a discord server
+    guild_id int NOT NULL DEFAULT 0,
+    -- not unique, the alias may have been reused since
+    alias VARCHAR(255) NOT NULL,
+    game_name VARCHAR(255) NOT NULL,
+    address VARCHAR(255) NOT NULL,
+    dom_version integer NOT NULL,
+    final_turn int NOT NULL,
+    finished_at int NOT NULL,
+    owner_discord_user_id int
+);
+
+create table if not exists archived_nations (
+    archived_game_id int NOT NULL REFERENCES archived_games(id),
+    nation_id int NOT NULL,
+    nation_status int NOT NULL,
+    discord_user_id int,
+    won BOOLEAN NOT NULL,
+
+    CONSTRAINT archived_nation_unique UNIQUE (archived_game_id, nation_id)
+);
+
+create table if not exists archived_turn_history (
+    archived_game_id int NOT NULL REFERENCES archived_games(id),
+    turn int NOT NULL,
+    turn_deadline int NOT NULL,
+    nation_id int NOT NULL,
+    nation_status int NOT NULL,
+    submitted int NOT NULL,
+    connected BOOLEAN NOT NULL,
+    submitted_at int,
+    recorded_at int NOT NULL,
+
+    CONSTRAINT archived_turn_history_unique UNIQUE (archived_game_id, turn, nation_id)
+);
@@ -0,0 +1,6 @@
+alter table game_servers add column anonymous BOOLEAN NOT NULL DEFAULT 0;
+alter table archived_games add column anonymous BOOLEAN NOT NULL DEFAULT 0;
+
+-- games used to be anonymous just because of their alias
+update game_servers set anonymous = 1 where alias like '%\_anon' escape '\';
+update archived_games set anonymous = 1 where alias like '%\_anon' escape '\';
@@ -0,0 +1,31 @@
+-- player_id can now be NULL, for a nation nobody is registered as (e.g. one that went AI)
+-- while it waits for somebody to sub in
+create table if not exists server_players_temp (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int REFERENCES players(id),
+    nation_id int,
+    custom_nation_name text,
+    notify_new_turn BOOLEAN,
+    notify_uploading BOOLEAN,
+    notify_reminders BOOLEAN,
+    notify_stales BOOLEAN,
+    notify_defeats BOOLEAN,
+    sub_needed BOOLEAN NOT NULL DEFAULT 0,
+
+    CONSTRAINT server_nation_unique UNIQUE (server_id, nation_id),
+    CHECK(
+        (nation_id IS NULL AND custom_nation_name IS NOT NULL) OR
+            (nation_id IS NOT NULL AND custom_nation_name IS NULL)
+    ),
+    CHECK(player_id IS NOT NULL OR sub_needed)
+);
+
+insert into server_players_temp (server_id, player_id, nation_id, custom_nation_name,
+    notify_new_turn, notify_uploading, notify_reminders, notify_stales, notify_defeats)
+select server_id, player_id, nation_id, custom_nation_name,
+    notify_new_turn, notify_uploading, notify_reminders, notify_stales, notify_defeats
+from server_players;
+
+drop table if exists server_players;
+
+alter table server_players_temp rename to server_players;
@@ -0,0 +1,20 @@
+-- lobbies where the players take turns to pick their nations
+create table if not exists drafts (
+    server_id int NOT NULL PRIMARY KEY REFERENCES game_servers(id),
+    draft_order int NOT NULL,
+    pick_minutes int NOT NULL,
+    -- where the draft was started, to tell everybody about picks. NULL until then.
+    channel_id int,
+    picking_player_id int REFERENCES players(id),
+    pick_deadline int
+);
+
+-- players signed up for a draft who haven't picked a nation yet
+create table if not exists draft_players (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    -- NULL until the draft starts
+    pick_position int,
+
+    CONSTRAINT draft_player_unique UNIQUE (server_id, player_id)
+);
@@ -0,0 +1,9 @@
+-- nations players would like in a lobby, best first, for the bot to share out fairly
+create table if not exists nation_preferences (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    rank int NOT NULL,
+    nation_id int NOT NULL,
+
+    CONSTRAINT nation_preference_unique UNIQUE (server_id, player_id, rank)
+);
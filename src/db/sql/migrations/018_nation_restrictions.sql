@@ -0,0 +1,8 @@
+-- nations a lobby's owner has banned (allowed = 0), or the only ones they allow (allowed = 1)
+create table if not exists nation_restrictions (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    nation_id int NOT NULL,
+    allowed boolean NOT NULL,
+
+    CONSTRAINT nation_restriction_unique UNIQUE (server_id, nation_id)
+);
@@ -0,0 +1,10 @@
+-- players waiting for a place in a full lobby, in the order they signed up, and the
+-- nation they asked for
+create table if not exists waitlist (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    nation_id int,
+    custom_nation_name text,
+
+    CONSTRAINT waitlist_unique UNIQUE (server_id, player_id)
+);
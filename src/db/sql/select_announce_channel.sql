@@ -0,0 +1,3 @@
+SELECT announce_channel_id
+FROM game_servers
+WHERE alias = ?1 AND guild_id = ?2;
@@ -0,0 +1,3 @@
+SELECT id, guild_id, alias, game_name, address, dom_version, final_turn, finished_at, owner_discord_user_id, anonymous
+FROM archived_games
+ORDER BY finished_at DESC, id DESC;
@@ -0,0 +1,4 @@
+SELECT nation_id, nation_status, discord_user_id, won
+FROM archived_nations
+WHERE archived_game_id = ?1
+ORDER BY nation_id;
@@ -0,0 +1,5 @@
+SELECT h.turn, h.turn_deadline, h.nation_id, h.nation_status, h.submitted, h.connected, h.submitted_at, h.recorded_at, a.dom_version
+FROM archived_turn_history h
+JOIN archived_games a ON a.id = h.archived_game_id
+WHERE a.id = ?1
+ORDER BY h.turn, h.nation_id;
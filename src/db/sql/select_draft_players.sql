@@ -0,0 +1,5 @@
+SELECT g.alias, g.guild_id, p.discord_user_id
+FROM draft_players dp
+JOIN game_servers g ON g.id = dp.server_id
+JOIN players p ON p.id = dp.player_id
+ORDER BY dp.pick_position, dp.rowid;
@@ -0,0 +1,5 @@
+SELECT g.alias, g.guild_id, d.draft_order, d.pick_minutes, d.channel_id, p.discord_user_id, d.pick_deadline
+FROM drafts d
+JOIN game_servers g ON g.id = d.server_id
+LEFT JOIN players p ON p.id = d.picking_player_id
+ORDER BY g.alias;
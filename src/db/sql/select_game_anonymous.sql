@@ -0,0 +1,3 @@
+SELECT anonymous
+FROM game_servers
+WHERE alias = ?1 AND guild_id = ?2;
@@ -0,0 +1,6 @@
+SELECT COALESCE(lp.discord_user_id, gp.discord_user_id)
+FROM game_servers g
+LEFT JOIN lobbies l ON l.id = g.lobby_id
+LEFT JOIN players lp ON lp.id = l.owner_id
+LEFT JOIN players gp ON gp.id = g.owner_id
+WHERE g.alias = ?1 AND g.guild_id = ?2;
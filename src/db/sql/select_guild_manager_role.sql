@@ -0,0 +1,3 @@
+SELECT manager_role_id
+FROM guild_settings
+WHERE guild_id = ?1;
@@ -0,0 +1,4 @@
+SELECT guild_id
+FROM game_servers
+WHERE alias = ?1
+ORDER BY guild_id;
@@ -1,6 +1,10 @@
-SELECT g.alias, l.owner_id, l.era, l.player_count, count(sp.player_id), l.description, g.dom_version
+SELECT g.alias, l.owner_id, l.era, l.player_count,
+    count(sp.player_id)
+        + (SELECT count(*) FROM draft_players dp WHERE dp.server_id = g.id)
+        + (SELECT count(DISTINCT np.player_id) FROM nation_preferences np WHERE np.server_id = g.id),
+    l.description, g.dom_version, g.guild_id, g.anonymous
 FROM game_servers g
 JOIN lobbies l ON l.id = g.lobby_id
 LEFT JOIN server_players sp on sp.server_id = g.id
 WHERE g.started_server_id IS NULL
-GROUP BY g.id
\ No newline at end of file
+GROUP BY g.id
@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, np.nation_id
+FROM nation_preferences np
+JOIN game_servers g ON g.id = np.server_id
+JOIN players p ON p.id = np.player_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY np.rowid;
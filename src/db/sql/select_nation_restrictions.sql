@@ -0,0 +1,5 @@
+SELECT nr.nation_id, nr.allowed
+FROM nation_restrictions nr
+JOIN game_servers g ON g.id = nr.server_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY nr.rowid;
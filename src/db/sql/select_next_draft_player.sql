@@ -0,0 +1,7 @@
+SELECT p.discord_user_id
+FROM draft_players dp
+JOIN game_servers g ON g.id = dp.server_id
+JOIN players p ON p.id = dp.player_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY dp.pick_position, dp.rowid
+LIMIT 1;
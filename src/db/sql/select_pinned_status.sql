@@ -0,0 +1,3 @@
+SELECT pinned_channel_id, pinned_message_id
+FROM game_servers
+WHERE alias = ?1 AND guild_id = ?2;
@@ -1,5 +1,10 @@
-SELECT p.discord_user_id, sp.nation_id, sp.custom_nation_name, p.turn_notifications, s.dom_version
+SELECT p.discord_user_id, sp.nation_id, sp.custom_nation_name, p.turn_notifications, s.dom_version,
+    COALESCE(sp.notify_new_turn, p.turn_notifications),
+    COALESCE(sp.notify_uploading, p.turn_notifications),
+    COALESCE(sp.notify_reminders, p.turn_notifications),
+    COALESCE(sp.notify_stales, p.turn_notifications),
+    COALESCE(sp.notify_defeats, p.turn_notifications)
 FROM game_servers s
 JOIN server_players sp on sp.server_id = s.id
 JOIN players p on p.id = sp.player_id
-WHERE s.alias = ?1;
+WHERE s.alias = ?1 AND s.guild_id = ?2;
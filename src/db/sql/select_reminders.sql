@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, r.minutes_before, r.last_sent_turn
+FROM reminders r
+JOIN game_servers g ON g.id = r.server_id
+JOIN players p ON p.id = r.player_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY p.discord_user_id, r.minutes_before DESC;
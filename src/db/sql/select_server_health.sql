@@ -0,0 +1,3 @@
+SELECT consecutive_failures, last_success_at
+FROM game_servers
+WHERE alias = ?1 AND guild_id = ?2;
@@ -8,7 +8,14 @@ SELECT
     l.era,
     l.player_count,
     l.description,
-    g.dom_version
+    g.dom_version,
+    COALESCE(sp.notify_new_turn, p.turn_notifications),
+    COALESCE(sp.notify_uploading, p.turn_notifications),
+    COALESCE(sp.notify_reminders, p.turn_notifications),
+    COALESCE(sp.notify_stales, p.turn_notifications),
+    COALESCE(sp.notify_defeats, p.turn_notifications),
+    g.guild_id,
+    g.anonymous
 FROM players p
 JOIN server_players sp on sp.player_id = p.id
 JOIN game_servers g on g.id = sp.server_id
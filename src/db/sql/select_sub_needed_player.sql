@@ -0,0 +1,5 @@
+SELECT p.discord_user_id
+FROM server_players sp
+JOIN game_servers g ON g.id = sp.server_id
+LEFT JOIN players p ON p.id = sp.player_id
+WHERE sp.sub_needed AND sp.nation_id = ?1 AND g.alias = ?2 AND g.guild_id = ?3;
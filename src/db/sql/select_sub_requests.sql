@@ -0,0 +1,8 @@
+SELECT g.alias, g.guild_id, sp.nation_id, g.dom_version, l.era, s.last_seen_turn, p.discord_user_id, g.anonymous
+FROM server_players sp
+JOIN game_servers g ON g.id = sp.server_id
+LEFT JOIN players p ON p.id = sp.player_id
+LEFT JOIN lobbies l ON l.id = g.lobby_id
+LEFT JOIN started_servers s ON s.id = g.started_server_id
+WHERE sp.sub_needed
+ORDER BY g.alias, sp.nation_id;
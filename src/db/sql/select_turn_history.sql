@@ -0,0 +1,5 @@
+SELECT h.turn, h.turn_deadline, h.nation_id, h.nation_status, h.submitted, h.connected, h.submitted_at, h.recorded_at, g.dom_version
+FROM turn_history h
+JOIN game_servers g ON g.id = h.server_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY h.turn, h.nation_id;
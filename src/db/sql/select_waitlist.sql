@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, w.nation_id, w.custom_nation_name, g.dom_version
+FROM waitlist w
+JOIN game_servers g ON g.id = w.server_id
+JOIN players p ON p.id = w.player_id
+WHERE g.alias = ?1 AND g.guild_id = ?2
+ORDER BY w.rowid;
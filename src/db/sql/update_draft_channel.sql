@@ -0,0 +1,4 @@
+UPDATE drafts
+SET channel_id = ?1
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?2 AND guild_id = ?3);
@@ -0,0 +1,5 @@
+UPDATE drafts
+SET picking_player_id = (SELECT id FROM players WHERE discord_user_id = ?1),
+    pick_deadline = ?2
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?3 AND guild_id = ?4);
@@ -0,0 +1,6 @@
+UPDATE draft_players
+SET pick_position = ?1
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?3 AND guild_id = ?4)
+AND player_id IN
+    (SELECT id from players WHERE discord_user_id = ?2);
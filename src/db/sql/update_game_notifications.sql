@@ -0,0 +1,10 @@
+UPDATE server_players
+SET notify_new_turn = CASE WHEN ?3 IN (0, 1) THEN ?4 ELSE notify_new_turn END,
+    notify_uploading = CASE WHEN ?3 IN (0, 2) THEN ?4 ELSE notify_uploading END,
+    notify_reminders = CASE WHEN ?3 IN (0, 3) THEN ?4 ELSE notify_reminders END,
+    notify_stales = CASE WHEN ?3 IN (0, 4) THEN ?4 ELSE notify_stales END,
+    notify_defeats = CASE WHEN ?3 IN (0, 5) THEN ?4 ELSE notify_defeats END
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?1 AND guild_id = ?5)
+AND player_id IN
+    (SELECT id from players WHERE discord_user_id = ?2);
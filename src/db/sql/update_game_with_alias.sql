@@ -1,3 +1,3 @@
-UPDATE game_servers 
+UPDATE game_servers
 SET    alias = ?2
-WHERE  alias = ?1;
+WHERE  alias = ?1 AND guild_id = ?3;
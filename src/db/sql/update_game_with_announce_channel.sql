@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET announce_channel_id = ?2
+WHERE alias = ?1 AND guild_id = ?3;
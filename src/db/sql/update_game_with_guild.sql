@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET    guild_id = ?3
+WHERE  alias = ?1 AND guild_id = ?2;
@@ -1,3 +1,3 @@
 UPDATE game_servers
 SET started_server_id = NULL
-WHERE alias = ?1 AND lobby_id IS NOT NULL;
+WHERE alias = ?1 AND guild_id = ?2 AND lobby_id IS NOT NULL;
@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET owner_id = (SELECT id FROM players WHERE discord_user_id = ?2)
+WHERE alias = ?1 AND guild_id = ?3;
@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET pinned_channel_id = ?2, pinned_message_id = ?3
+WHERE alias = ?1 AND guild_id = ?4;
@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET consecutive_failures = consecutive_failures + 1
+WHERE alias = ?1 AND guild_id = ?2;
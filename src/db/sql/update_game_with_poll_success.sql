@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET consecutive_failures = 0, last_success_at = ?2
+WHERE alias = ?1 AND guild_id = ?3;
@@ -3,4 +3,4 @@ SET started_server_id =
     (SELECT s.id
     from started_servers s
     where s.address = ?1 and s.last_seen_turn = ?2)
-WHERE alias = ?3;
+WHERE alias = ?3 AND guild_id = ?4;
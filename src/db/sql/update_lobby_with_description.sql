@@ -1,2 +1,2 @@
 UPDATE lobbies SET description = ?2
-WHERE id = (SELECT lobby_id FROM game_servers WHERE alias = ?1);
\ No newline at end of file
+WHERE id = (SELECT lobby_id FROM game_servers WHERE alias = ?1 AND guild_id = ?3);
\ No newline at end of file
@@ -0,0 +1,5 @@
+UPDATE reminders
+SET last_sent_turn = ?4
+WHERE server_id = (SELECT id FROM game_servers WHERE alias = ?1 AND guild_id = ?5)
+AND player_id = (SELECT id FROM players WHERE discord_user_id = ?2)
+AND minutes_before = ?3;
@@ -0,0 +1,11 @@
+UPDATE server_players
+SET player_id = (SELECT id FROM players WHERE discord_user_id = ?1),
+    sub_needed = 0,
+    notify_new_turn = NULL,
+    notify_uploading = NULL,
+    notify_reminders = NULL,
+    notify_stales = NULL,
+    notify_defeats = NULL
+WHERE sub_needed AND nation_id = ?2
+AND server_id IN
+    (SELECT id from game_servers WHERE alias = ?3 AND guild_id = ?4);
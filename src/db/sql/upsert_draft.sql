@@ -0,0 +1,5 @@
+INSERT INTO drafts (server_id, draft_order, pick_minutes)
+SELECT g.id, ?1, ?2
+FROM game_servers g
+WHERE g.alias = ?3 AND g.guild_id = ?4
+ON CONFLICT (server_id) DO UPDATE SET draft_order = ?1, pick_minutes = ?2;
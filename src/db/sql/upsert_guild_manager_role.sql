@@ -0,0 +1,3 @@
+INSERT INTO guild_settings (guild_id, manager_role_id)
+VALUES (?1, ?2)
+ON CONFLICT (guild_id) DO UPDATE SET manager_role_id = excluded.manager_role_id;
@@ -0,0 +1,4 @@
+INSERT OR REPLACE INTO nation_restrictions (server_id, nation_id, allowed)
+SELECT g.id, ?1, ?2
+FROM game_servers g
+WHERE g.alias = ?3 AND g.guild_id = ?4;
@@ -0,0 +1,5 @@
+INSERT INTO server_players (server_id, player_id, nation_id, sub_needed)
+SELECT g.id, NULL, ?1, 1
+FROM game_servers g
+WHERE g.alias = ?2 AND g.guild_id = ?3
+ON CONFLICT (server_id, nation_id) DO UPDATE SET sub_needed = 1;
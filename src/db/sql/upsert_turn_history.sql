@@ -0,0 +1,11 @@
+INSERT INTO turn_history (server_id, turn, turn_deadline, nation_id, nation_status, submitted, connected, submitted_at, recorded_at)
+SELECT g.id, ?2, ?3, ?4, ?5, ?6, ?7, CASE WHEN ?6 = 2 THEN ?8 ELSE NULL END, ?8
+FROM game_servers g
+WHERE g.alias = ?1 AND g.guild_id = ?9
+ON CONFLICT (server_id, turn, nation_id) DO UPDATE SET
+    turn_deadline = excluded.turn_deadline,
+    nation_status = excluded.nation_status,
+    submitted = excluded.submitted,
+    connected = excluded.connected,
+    submitted_at = CASE WHEN excluded.submitted = 2 THEN COALESCE(turn_history.submitted_at, excluded.submitted_at) ELSE NULL END,
+    recorded_at = excluded.recorded_at;
@@ -0,0 +1,495 @@
+//! An optional read-only HTTP server showing what the bot knows about each game, for status
+//! boards and stats scripts that shouldn't have to go through discord.
+//!
+//! - `/` a simple HTML page of every started game
+//! - `/games` every game and lobby
+//! - `/games/{alias}` one game's nations, who has submitted and the deadline
+//! - `/players/{discord_id}/turns` the same for just that player's nations
+//!
+//! `/games` and `/games/{alias}` take `?guild=<discord server id>` since aliases are only
+//! unique within a server. Player ids are never shown for anonymous games.
+
+use crate::{
+    commands::servers::details::cached_details_for_alias,
+    db::DbConnection,
+    model::{
+        enums::SubmissionStatus,
+        game_server::{GameAlias, GameServerState},
+        game_state::{GameDetails, NationDetails, PotentialPlayer, StartedStateDetails},
+    },
+    DetailsCacheHandle,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use log::*;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, UserId};
+use std::net::TcpListener;
+
+#[derive(Clone)]
+struct ApiState {
+    db_conn: DbConnection,
+    details_cache: DetailsCacheHandle,
+}
+
+pub async fn serve(
+    listener: TcpListener,
+    db_conn: DbConnection,
+    details_cache: DetailsCacheHandle,
+) -> anyhow::Result<()> {
+    info!("Serving the http api on {}", listener.local_addr()?);
+    let app = Router::new()
+        .route("/", get(status_page))
+        .route("/games", get(list_games))
+        .route("/games/:alias", get(game))
+        .route("/players/:discord_id/turns", get(player_turns))
+        .with_state(ApiState {
+            db_conn,
+            details_cache,
+        });
+    axum::Server::from_tcp(listener)?
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+struct ApiError(StatusCode, String);
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, error) = self;
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+fn internal_error(err: impl std::fmt::Display) -> ApiError {
+    ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
+
+#[derive(Deserialize)]
+struct GuildQuery {
+    guild: Option<u64>,
+}
+impl GuildQuery {
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild.filter(|&id| id != 0).map(GuildId::new)
+    }
+}
+
+#[derive(Serialize)]
+struct GameSummary {
+    alias: String,
+    guild_id: Option<String>,
+    /// "lobby" or "started"
+    state: &'static str,
+    address: Option<String>,
+}
+
+#[derive(Serialize)]
+struct GameStatus {
+    alias: String,
+    guild_id: Option<String>,
+    owner_id: Option<String>,
+    description: Option<String>,
+    /// "lobby", "uploading" or "playing"
+    state: &'static str,
+    address: Option<String>,
+    game_name: Option<String>,
+    turn: Option<u32>,
+    turn_deadline: Option<DateTime<Utc>>,
+    nations: Vec<NationStatusJson>,
+}
+
+#[derive(Serialize)]
+struct NationStatusJson {
+    nation_id: Option<u32>,
+    name: String,
+    /// Left out for anonymous games and nations nobody registered for
+    player_id: Option<String>,
+    /// e.g. "Human", "AI", "Defeated". Missing for lobbies and for uploading nations.
+    status: Option<&'static str>,
+    /// "not_submitted", "partially_submitted" or "submitted" while playing,
+    /// "not_uploaded" or "uploaded" while uploading
+    submitted: Option<&'static str>,
+}
+
+fn submission_name(submitted: SubmissionStatus) -> &'static str {
+    match submitted {
+        SubmissionStatus::NotSubmitted => "not_submitted",
+        SubmissionStatus::PartiallySubmitted => "partially_submitted",
+        SubmissionStatus::Submitted => "submitted",
+        SubmissionStatus::Unknown(_) => "unknown",
+    }
+}
+
+fn id_string(id: impl Into<u64>) -> String {
+    // discord ids don't fit in a javascript number
+    id.into().to_string()
+}
+
+fn game_status(details: GameDetails) -> GameStatus {
+    let anonymous = details.anonymous;
+    let player_id = |user_id: UserId| (!anonymous).then(|| id_string(user_id));
+    let option_snek_state = details
+        .cache_entry
+        .as_ref()
+        .and_then(|cache_entry| cache_entry.option_snek_state.as_ref());
+
+    let mut status = GameStatus {
+        alias: details.alias.name.clone(),
+        guild_id: details.alias.guild_id.map(id_string),
+        owner_id: details.owner.and_then(player_id),
+        description: details.description.clone(),
+        state: "lobby",
+        address: None,
+        game_name: None,
+        turn: None,
+        turn_deadline: None,
+        nations: vec![],
+    };
+    match &details.nations {
+        NationDetails::Lobby(lobby_details) => {
+            status.nations = lobby_details
+                .players
+                .iter()
+                .map(|lobby_player| NationStatusJson {
+                    nation_id: lobby_player.nation_identifier.id(),
+                    name: lobby_player.cached_name.to_string(),
+                    player_id: player_id(lobby_player.player_id),
+                    status: None,
+                    submitted: None,
+                })
+                .collect();
+        }
+        NationDetails::Started(started_details) => {
+            status.address = Some(started_details.address.clone());
+            status.game_name = Some(started_details.game_name.clone());
+            match &started_details.state {
+                StartedStateDetails::Uploading(uploading_state) => {
+                    status.state = "uploading";
+                    status.nations = uploading_state
+                        .uploading_players
+                        .iter()
+                        .map(|uploading_player| NationStatusJson {
+                            nation_id: uploading_player.potential_player.nation_id(),
+                            name: uploading_player.nation_name(option_snek_state).to_string(),
+                            player_id: uploading_player
+                                .option_player_id()
+                                .and_then(|player| player_id(player.discord_user_id)),
+                            status: None,
+                            submitted: Some(if uploading_player.uploaded {
+                                "uploaded"
+                            } else {
+                                "not_uploaded"
+                            }),
+                        })
+                        .collect();
+                }
+                StartedStateDetails::Playing(playing_state) => {
+                    status.state = "playing";
+                    status.turn = Some(playing_state.turn);
+                    status.turn_deadline = Some(playing_state.turn_deadline);
+                    status.nations = playing_state
+                        .players
+                        .iter()
+                        .map(|potential_player| {
+                            let (option_player, option_player_details) = match potential_player {
+                                PotentialPlayer::RegisteredOnly(player, _) => (Some(player), None),
+                                PotentialPlayer::RegisteredAndGame(player, player_details) => {
+                                    (Some(player), Some(player_details))
+                                }
+                                PotentialPlayer::GameOnly(player_details) => {
+                                    (None, Some(player_details))
+                                }
+                            };
+                            NationStatusJson {
+                                nation_id: potential_player.nation_id(),
+                                name: potential_player.nation_name(option_snek_state).to_string(),
+                                player_id: option_player
+                                    .and_then(|player| player_id(player.discord_user_id)),
+                                status: option_player_details
+                                    .map(|player_details| player_details.player_status.show()),
+                                submitted: option_player_details.map(|player_details| {
+                                    submission_name(player_details.submitted)
+                                }),
+                            }
+                        })
+                        .collect();
+                }
+            }
+        }
+    }
+    status
+}
+
+async fn list_games(
+    State(state): State<ApiState>,
+    Query(query): Query<GuildQuery>,
+) -> Result<Json<Vec<GameSummary>>, ApiError> {
+    let games = state
+        .db_conn
+        .retrieve_all_servers()
+        .map_err(internal_error)?
+        .into_iter()
+        .filter(|server| server.alias.is_visible_from(query.guild_id()))
+        .map(|server| {
+            let (state, address) = match server.state {
+                GameServerState::Lobby(_) => ("lobby", None),
+                GameServerState::StartedState(started_state, _) => {
+                    ("started", Some(started_state.address))
+                }
+            };
+            GameSummary {
+                alias: server.alias.name,
+                guild_id: server.alias.guild_id.map(id_string),
+                state,
+                address,
+            }
+        })
+        .collect();
+    Ok(Json(games))
+}
+
+async fn game_details(state: &ApiState, alias: &GameAlias) -> Result<GameDetails, ApiError> {
+    state
+        .db_conn
+        .game_for_alias(alias)
+        .map_err(|err| ApiError(StatusCode::NOT_FOUND, err.to_string()))?;
+    // the game exists, so this only fails if we haven't heard from its server
+    cached_details_for_alias(alias, state.db_conn.clone(), state.details_cache.clone())
+        .await
+        .map_err(|err| ApiError(StatusCode::SERVICE_UNAVAILABLE, err.to_string()))
+}
+
+async fn game(
+    State(state): State<ApiState>,
+    Path(alias): Path<String>,
+    Query(query): Query<GuildQuery>,
+) -> Result<Json<GameStatus>, ApiError> {
+    let alias = state
+        .db_conn
+        .resolve_alias(&alias.to_lowercase(), query.guild_id())
+        .map_err(|err| ApiError(StatusCode::CONFLICT, err.to_string()))?;
+    let details = game_details(&state, &alias).await?;
+    Ok(Json(game_status(details)))
+}
+
+/// Every started game the player is in, with only their nations
+async fn player_turns(
+    State(state): State<ApiState>,
+    Path(discord_id): Path<u64>,
+) -> Result<Json<Vec<GameStatus>>, ApiError> {
+    if discord_id == 0 {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            "Invalid discord id".to_owned(),
+        ));
+    }
+    let user_id = UserId::new(discord_id);
+    let servers = state
+        .db_conn
+        .servers_for_player(user_id)
+        .map_err(internal_error)?;
+
+    let mut statuses = vec![];
+    for (server, _, _) in servers {
+        // listing these would give away who is playing
+        if server.anonymous {
+            continue;
+        }
+        if let GameServerState::StartedState(..) = server.state {
+            match game_details(&state, &server.alias).await {
+                Ok(details) => {
+                    let mut status = game_status(details);
+                    let player_id = Some(id_string(user_id));
+                    status
+                        .nations
+                        .retain(|nation| nation.player_id == player_id);
+                    statuses.push(status);
+                }
+                Err(ApiError(_, err)) => {
+                    info!("Skipping {} for player turns: {}", server.alias, err)
+                }
+            }
+        }
+    }
+    Ok(Json(statuses))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+async fn status_page(State(state): State<ApiState>) -> Result<Html<String>, ApiError> {
+    let servers = state
+        .db_conn
+        .retrieve_all_servers()
+        .map_err(internal_error)?;
+
+    let mut rows = vec![];
+    for server in servers {
+        if let GameServerState::Lobby(_) = server.state {
+            continue;
+        }
+        let row = match game_details(&state, &server.alias).await {
+            Ok(details) => {
+                let status = game_status(details);
+                let submitted = status
+                    .nations
+                    .iter()
+                    .filter(|nation| matches!(nation.submitted, Some("submitted" | "uploaded")))
+                    .count();
+                let waiting_on = status
+                    .nations
+                    .iter()
+                    .filter(|nation| {
+                        matches!(nation.submitted, Some("not_submitted" | "not_uploaded"))
+                            && nation.status.is_none_or(|status| status == "Human")
+                    })
+                    .map(|nation| escape_html(&nation.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}/{}</td><td>{}</td></tr>",
+                    escape_html(&status.alias),
+                    status
+                        .turn
+                        .map_or_else(|| status.state.to_owned(), |turn| turn.to_string()),
+                    status
+                        .turn_deadline
+                        .map_or_else(String::new, |deadline| deadline
+                            .format("%Y-%m-%d %H:%M UTC")
+                            .to_string()),
+                    submitted,
+                    status.nations.len(),
+                    waiting_on,
+                )
+            }
+            Err(ApiError(_, err)) => format!(
+                "<tr><td>{}</td><td colspan=\"4\">{}</td></tr>",
+                escape_html(&server.alias.name),
+                escape_html(&err)
+            ),
+        };
+        rows.push(row);
+    }
+
+    Ok(Html(format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta http-equiv=\"refresh\" content=\"60\">
+<title>Dominions games</title>
+<style>body {{ font-family: sans-serif; }} td, th {{ padding: 0.2em 1em; text-align: left; }}</style>
+</head>
+<body>
+<table>
+<tr><th>Game</th><th>Turn</th><th>Deadline</th><th>Submitted</th><th>Waiting on</th></tr>
+{}
+</table>
+</body>
+</html>",
+        rows.join("\n")
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        model::{
+            enums::Era,
+            game_server::{GameServer, LobbyState},
+            nation::BotNationIdentifier,
+            player::{NotificationSettings, Player},
+        },
+        test_harness::*,
+    };
+    use reqwest::StatusCode;
+
+    fn add_lobby(db_conn: &DbConnection, alias: &GameAlias, player_id: u64, anonymous: bool) {
+        db_conn
+            .insert_game_server(&GameServer {
+                alias: alias.clone(),
+                state: GameServerState::Lobby(LobbyState {
+                    owner: UserId::new(player_id),
+                    era: Era::Middle,
+                    player_count: 2,
+                    description: None,
+                }),
+                dom_version: 6,
+                anonymous,
+            })
+            .unwrap();
+        let player = Player {
+            discord_user_id: UserId::new(player_id),
+            turn_notifications: true,
+            notifications: NotificationSettings::all(true),
+        };
+        db_conn
+            .insert_player_into_server(&player, alias, BotNationIdentifier::from_id_6(5))
+            .unwrap();
+    }
+
+    async fn get(base: &str, path: &str) -> (StatusCode, serde_json::Value) {
+        let response = reqwest::get(format!("{}{}", base, path)).await.unwrap();
+        (response.status(), response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn games_as_json() {
+        let temp_db = TempDb::new();
+        let guild = GuildId::new(42);
+        add_lobby(
+            &temp_db.db_conn,
+            &GameAlias::new(Some(guild), "samog"),
+            1234,
+            false,
+        );
+        add_lobby(
+            &temp_db.db_conn,
+            &GameAlias::new(Some(guild), "secret"),
+            5678,
+            true,
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve(
+            listener,
+            temp_db.db_conn.clone(),
+            empty_details_cache(),
+        ));
+
+        let (status, games) = get(&base, "/games?guild=42").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(2, games.as_array().unwrap().len());
+        let (_, games) = get(&base, "/games?guild=43").await;
+        assert!(games.as_array().unwrap().is_empty());
+
+        let (status, game) = get(&base, "/games/samog").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("lobby", game["state"]);
+        assert_eq!("42", game["guild_id"]);
+        assert_eq!("1234", game["nations"][0]["player_id"]);
+        assert_eq!(5, game["nations"][0]["nation_id"]);
+
+        let (_, game) = get(&base, "/games/secret").await;
+        assert!(game["owner_id"].is_null());
+        assert!(game["nations"][0]["player_id"].is_null());
+
+        let (status, _) = get(&base, "/games/nope?guild=42").await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        server.abort();
+    }
+}
@@ -1,31 +1,31 @@
-// warning: use of deprecated associated function `serenity::framework::standard::*`:
-// The standard framework is deprecated, and will be removed in 0.13. Please migrate to `poise` for command handling
-#![allow(deprecated)]
 mod commands;
+mod config;
 mod db;
+mod http_api;
 mod model;
+mod prefix_commands;
 mod server;
 mod slash_commands;
 mod snek;
+#[cfg(test)]
+mod test_harness;
 
 use crate::{
-    commands::servers::turn_check::update_details_cache_loop, db::*, model::game_state::CacheEntry,
+    commands::servers::{draft, turn_check::update_details_cache_loop},
+    config::{Config, SlashCommandScope},
+    db::*,
+    model::{game_server::GameAlias, game_state::CacheEntry},
 };
 use anyhow::{anyhow, Context as _};
 use chrono::{DateTime, Utc};
 use log::*;
-use serenity::all::standard::BucketBuilder;
-use serenity::all::{ApplicationId, Interaction};
-use serenity::framework::standard::Configuration;
-use serenity::{async_trait, framework::standard::StandardFramework, prelude::*};
-use simplelog::{Config, LevelFilter, SimpleLogger};
-use std::time::Duration;
+use serenity::all::{ApplicationId, Guild, Interaction, Message};
+use serenity::{async_trait, prelude::*};
+use simplelog::SimpleLogger;
 use std::{env, fs::File, io::Read as _, path::Path, str::FromStr, sync::Arc};
 
-pub const SERVER_POLL_INTERVAL: Duration = Duration::from_secs(60);
-
 // TODO: should this be im-rc? Do I care really?
-pub type DetailsCache = im::HashMap<String, Box<(DateTime<Utc>, Option<CacheEntry>)>>;
+pub type DetailsCache = im::HashMap<GameAlias, Box<(DateTime<Utc>, Option<CacheEntry>)>>;
 struct DetailsCacheKey;
 impl TypeMapKey for DetailsCacheKey {
     type Value = DetailsCache;
@@ -49,7 +49,7 @@ impl DetailsCacheHandle {
     // }
 
     // TODO: still feel like this could be done without cloning
-    async fn get_clone(&self, alias: &str) -> anyhow::Result<CacheEntry> {
+    async fn get_clone(&self, alias: &GameAlias) -> anyhow::Result<CacheEntry> {
         let read_lock = self.0.read().await;
         let details_cache = read_lock
             .get::<DetailsCacheKey>()
@@ -73,26 +73,54 @@ impl DetailsCacheHandle {
     }
 }
 
-struct Handler;
+struct Handler {
+    prefix: String,
+    slash_commands: SlashCommandScope,
+}
 #[async_trait]
 impl EventHandler for Handler {
+    async fn message(&self, ctx: Context, msg: Message) {
+        prefix_commands::message(&ctx, &msg, &self.prefix).await
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         slash_commands::interaction_create(ctx, interaction).await
     }
+
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
+        // guilds we were already in got their commands on startup
+        if is_new == Some(true)
+            && self.slash_commands.has_guild_commands()
+            && ctx.http.application_id().is_some()
+        {
+            if let Err(err) =
+                slash_commands::register_guild_commands(&ctx.http, guild.id, self.slash_commands)
+                    .await
+            {
+                error!(
+                    "Could not create commands in new guild {} ({}): {:?}",
+                    guild.name, guild.id, err
+                );
+            }
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    SimpleLogger::init(LevelFilter::Warn, Config::default())?;
+    let config = Config::load()?;
+    SimpleLogger::init(config.log_level, simplelog::Config::default())?;
     info!("Logger initialised");
+    config.install_timings();
 
-    let mut discord_client = create_discord_client().await?;
+    let mut discord_client = create_discord_client(&config).await?;
     info!("Starting discord client");
     discord_client.start().await?;
     error!("Finished discord client");
     Ok(())
 }
 
+/// For setups from before the config file, which only had the token in its own file
 fn read_token() -> anyhow::Result<String> {
     let mut token_file = File::open("resources/token").context("Opening file 'resources/token'")?;
     let mut temp_token = String::new();
@@ -125,42 +153,28 @@ fn read_application_id() -> anyhow::Result<Option<ApplicationId>> {
     }
 }
 
-async fn create_discord_client() -> anyhow::Result<Client> {
-    let token = read_token()?;
-    let option_application_id = read_application_id()?;
+async fn create_discord_client(config: &Config) -> anyhow::Result<Client> {
+    let token = match &config.token {
+        Some(token) => token.clone(),
+        None => read_token()?,
+    };
+    let option_application_id = match config.application_id {
+        Some(application_id) => Some(application_id),
+        None => read_application_id()?,
+    };
 
     let path = env::current_dir()?;
-    let path = path.join("resources/dom5bot.db");
+    let path = path.join(&config.db_path);
     let db_conn =
         DbConnection::new(&path).context(format!("Opening database '{}'", path.display()))?;
     info!("Opened database connection");
 
-    let framework = StandardFramework::new()
-        .bucket("simple", BucketBuilder::default().delay(1))
-        .await
-        .help(&crate::commands::help::HELP)
-        .before(|_, msg, _| {
-            Box::pin(async move {
-                info!("received message {:?}", msg);
-                !msg.author.bot // ignore bots
-            })
-        })
-        .after(|ctx, msg, _cmd_name, result| {
-            Box::pin(async move {
-                if let Err(err) = result {
-                    let err = anyhow!(err);
-                    let text = format!("ERROR: {:?}", err);
-                    info!("command error: replying with '{}'", text);
-                    let _ = msg.reply((&ctx.cache, ctx.http.as_ref()), &text).await;
-                }
-            })
-        })
-        .group(&crate::commands::servers::SERVER_GROUP);
-    framework.configure(Configuration::new().prefix("!"));
-
     let cache_loop_db_conn = db_conn.clone();
+    let http_api_db_conn = db_conn.clone();
+    let draft_db_conn = db_conn.clone();
 
-    let intents = GatewayIntents::GUILD_MESSAGES
+    let intents = GatewayIntents::GUILDS
+        | GatewayIntents::GUILD_MESSAGES
         | GatewayIntents::DIRECT_MESSAGES
         | GatewayIntents::MESSAGE_CONTENT;
 
@@ -171,10 +185,12 @@ async fn create_discord_client() -> anyhow::Result<Client> {
     }
 
     let discord_client = discord_client_builder
-        .event_handler(Handler)
+        .event_handler(Handler {
+            prefix: config.prefix.clone(),
+            slash_commands: config.slash_commands,
+        })
         .type_map_insert::<DetailsCacheKey>(im::HashMap::new())
         .type_map_insert::<DbConnectionKey>(db_conn)
-        .framework(framework)
         .await
         .context("ClientBuilder::await")?;
     info!("Created discord client");
@@ -183,14 +199,37 @@ async fn create_discord_client() -> anyhow::Result<Client> {
     let cache = Arc::clone(&discord_client.cache);
     let http = Arc::clone(&discord_client.http);
 
+    let turn_check_settings = config.turn_check;
+    tokio::spawn(async move {
+        update_details_cache_loop(
+            cache_loop_db_conn,
+            write_handle_mutex,
+            (cache, http),
+            turn_check_settings,
+        )
+        .await;
+    });
+
+    let draft_http = Arc::clone(&discord_client.http);
     tokio::spawn(async move {
-        update_details_cache_loop(cache_loop_db_conn, write_handle_mutex, (cache, http)).await;
+        draft::draft_deadline_loop(draft_db_conn, draft_http).await;
     });
 
+    if let Some(address) = config.http_api_address {
+        let listener = std::net::TcpListener::bind(address)
+            .context(format!("Binding http api to {}", address))?;
+        let read_handle = DetailsCacheHandle(Arc::clone(&discord_client.data));
+        tokio::spawn(async move {
+            if let Err(err) = http_api::serve(listener, http_api_db_conn, read_handle).await {
+                error!("http api stopped: {:?}", err);
+            }
+        });
+    }
+
     if option_application_id.is_some() {
-        slash_commands::create_guild_commands(discord_client.http.as_ref())
+        slash_commands::create_commands(discord_client.http.as_ref(), config.slash_commands)
             .await
-            .context("create_guild_commands")?;
+            .context("create_commands")?;
     }
 
     // start listening for events by starting a single shard
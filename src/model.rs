@@ -1,7 +1,14 @@
+pub mod archived_game;
+pub mod draft;
 pub mod enums;
 pub mod game_data;
 pub mod game_server;
 pub mod game_state;
 pub mod nation;
+pub mod nation_restrictions;
 pub mod player;
 pub mod raw_game_data;
+pub mod reminder;
+pub mod server_health;
+pub mod sub_request;
+pub mod turn_history;
@@ -0,0 +1,32 @@
+use crate::model::{enums::NationStatus, game_server::GameAlias, nation::GameNationIdentifier};
+use chrono::{DateTime, Utc};
+use serenity::model::id::UserId;
+
+/// A game that has finished, kept around so people can look back at it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedGame {
+    pub id: i64,
+    pub alias: GameAlias,
+    pub game_name: String,
+    pub address: String,
+    pub dom_version: u8,
+    pub final_turn: i32,
+    pub finished_at: DateTime<Utc>,
+    pub owner: Option<UserId>,
+    pub anonymous: bool,
+    pub nations: Vec<ArchivedNation>,
+}
+impl ArchivedGame {
+    pub fn winners(&self) -> impl Iterator<Item = &ArchivedNation> {
+        self.nations.iter().filter(|nation| nation.won)
+    }
+}
+
+/// A nation as it was when the game ended, and who was playing it if they'd registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedNation {
+    pub identifier: GameNationIdentifier,
+    pub status: NationStatus,
+    pub player: Option<UserId>,
+    pub won: bool,
+}
@@ -0,0 +1,38 @@
+use crate::model::game_server::GameAlias;
+use chrono::{DateTime, Utc};
+use enum_primitive_derive::Primitive;
+use serenity::model::id::{ChannelId, UserId};
+
+/// Who picks first in a draft. Everybody only gets one nation, so there's no snaking
+/// back, just the order people signed up in or a random one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Primitive)]
+pub enum DraftOrder {
+    SignUp = 0,
+    Random = 1,
+}
+
+/// Whose turn it is to pick, and until when
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DraftPick {
+    pub player: UserId,
+    pub deadline: DateTime<Utc>,
+}
+
+/// A lobby whose players take turns to pick their nations, instead of racing to `!join`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Draft {
+    pub alias: GameAlias,
+    pub order: DraftOrder,
+    pub pick_minutes: i32,
+    /// Where picks get announced. `None` until the draft starts.
+    pub channel_id: Option<ChannelId>,
+    /// `None` until the draft starts
+    pub current_pick: Option<DraftPick>,
+    /// Everybody who still has to pick, in pick order once the draft has started
+    pub players: Vec<UserId>,
+}
+impl Draft {
+    pub fn has_started(&self) -> bool {
+        self.current_pick.is_some()
+    }
+}
@@ -2,7 +2,11 @@ mod nation_status;
 pub use self::nation_status::*;
 mod nations;
 pub use self::nations::*;
+mod nation_search;
+pub use self::nation_search::*;
 mod submission_status;
 pub use self::submission_status::*;
 mod era;
 pub use self::era::*;
+mod notification_kind;
+pub use self::notification_kind::*;
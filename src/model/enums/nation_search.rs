@@ -0,0 +1,271 @@
+//! Works out which nation somebody meant when they typed its name. Tries the exact name,
+//! community nicknames, acronyms, prefixes of the name or its subtitle, and finally
+//! names that are a typo or two away, and prefers the earlier kinds of match.
+
+use super::nations::{
+    extract_possible_nation_prefix, sanitise_text, Nations, StaticNation, DOM_6_EA_BY_ID,
+    DOM_6_LA_BY_ID, DOM_6_MA_BY_ID, EA_NATIONS_BY_ID, LA_NATIONS_BY_ID, MA_NATIONS_BY_ID,
+};
+use crate::model::enums::Era;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// How many nations to suggest when the name is ambiguous
+pub const MAX_NATION_SUGGESTIONS: usize = 5;
+
+/// Nicknames that aren't a prefix or an acronym of the real name, mapped to the sanitised
+/// nation name (the bit before the comma). Nicknames apply to every era of the nation.
+const NATION_NICKNAMES: &[(&str, &str)] = &[
+    ("tc", "tienchi"),
+    ("bl", "bandarlog"),
+    ("tnn", "tirnanog"),
+    ("bogus", "bogarus"),
+    ("monkeys", "bandarlog"),
+    ("apes", "bandarlog"),
+    ("dogs", "andramania"),
+    ("squids", "rlyeh"),
+    ("nif", "niefelheim"),
+];
+
+/// How well a nation matched. Earlier variants are better matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchQuality {
+    Exact,
+    Nickname,
+    Acronym,
+    Prefix,
+    /// A prefix of a later word in the name, e.g. "emerald" for "Pythium, Emerald Empire"
+    WordPrefix,
+    /// How many edits (including swapped letters) away from the name it was
+    Typo(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NationMatch {
+    pub nation: StaticNation,
+    pub quality: MatchQuality,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NationSearch {
+    Found(StaticNation),
+    /// The best matches, best first. Never more than `MAX_NATION_SUGGESTIONS`.
+    Ambiguous(Vec<StaticNation>),
+    NotFound,
+}
+impl NationSearch {
+    /// Picks the nation if exactly one of the best matches is better than all the others
+    pub fn from_matches(matches: Vec<NationMatch>) -> Self {
+        let best_quality = match matches.first() {
+            Some(best) => best.quality,
+            None => return NationSearch::NotFound,
+        };
+        let best = matches
+            .iter()
+            .take_while(|nation_match| nation_match.quality == best_quality)
+            .map(|nation_match| nation_match.nation)
+            .take(MAX_NATION_SUGGESTIONS)
+            .collect::<Vec<_>>();
+        match best[..] {
+            [nation] => NationSearch::Found(nation),
+            _ => NationSearch::Ambiguous(best),
+        }
+    }
+}
+
+impl Nations {
+    /// Every dom5 nation that looks like what was typed, best match first
+    pub fn search(query: &str, option_era_filter: Option<Era>) -> Vec<NationMatch> {
+        search_maps(
+            query,
+            option_era_filter,
+            [
+                (&*EA_NATIONS_BY_ID, Era::Early),
+                (&*MA_NATIONS_BY_ID, Era::Middle),
+                (&*LA_NATIONS_BY_ID, Era::Late),
+            ],
+        )
+    }
+
+    /// Every dom6 nation that looks like what was typed, best match first
+    pub fn search_6(query: &str, option_era_filter: Option<Era>) -> Vec<NationMatch> {
+        search_maps(
+            query,
+            option_era_filter,
+            [
+                (&*DOM_6_EA_BY_ID, Era::Early),
+                (&*DOM_6_MA_BY_ID, Era::Middle),
+                (&*DOM_6_LA_BY_ID, Era::Late),
+            ],
+        )
+    }
+}
+
+fn search_maps(
+    query: &str,
+    option_era_filter: Option<Era>,
+    maps: [(&HashMap<u32, &'static str>, Era); 3],
+) -> Vec<NationMatch> {
+    let query = Cow::Owned(query.trim().to_lowercase());
+    let (query, option_specific_era) = extract_possible_nation_prefix(query);
+    // an empty query is a prefix of everything, which is handy for autocomplete
+    let query = sanitise_text(query);
+    let option_era = option_specific_era.or(option_era_filter);
+
+    let mut matches = maps
+        .iter()
+        .filter(|(_, era)| option_era.is_none_or(|filter| filter == *era))
+        .flat_map(|&(nations_by_id, era)| {
+            nations_by_id
+                .iter()
+                .map(move |(&id, &name)| StaticNation { id, name, era })
+        })
+        .filter_map(|nation| {
+            match_quality(&query, nation.name).map(|quality| NationMatch { nation, quality })
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|nation_match| {
+        (
+            nation_match.quality,
+            nation_match.nation.era as u8,
+            nation_match.nation.name,
+        )
+    });
+    matches
+}
+
+fn sanitise(text: &str) -> String {
+    sanitise_text(Cow::Owned(text.to_lowercase())).into_owned()
+}
+
+fn initials<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    words
+        .filter_map(|word| sanitise(word).chars().next())
+        .collect()
+}
+
+/// How many typos we forgive, so that short names don't match everything
+fn max_typos(query_len: usize) -> usize {
+    match query_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+fn match_quality(query: &str, name: &str) -> Option<MatchQuality> {
+    let base_name = name.split(',').next().unwrap_or(name);
+    let sanitised_base = sanitise(base_name);
+    let sanitised_name = sanitise(name);
+
+    if query == sanitised_base || query == sanitised_name {
+        return Some(MatchQuality::Exact);
+    }
+
+    // a nickname can be followed by the start of the subtitle, e.g. "tcbarb"
+    let subtitle = sanitised_name
+        .strip_prefix(&sanitised_base)
+        .unwrap_or_default();
+    let is_nickname = NATION_NICKNAMES.iter().any(|&(nickname, nation)| {
+        nation == sanitised_base
+            && query
+                .strip_prefix(nickname)
+                .is_some_and(|rest| subtitle.starts_with(rest))
+    });
+    if is_nickname {
+        return Some(MatchQuality::Nickname);
+    }
+
+    let words = name
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    let base_words = base_name.split_whitespace().collect::<Vec<_>>();
+    let acronyms = [
+        initials(base_words.iter().copied()),
+        initials(words.iter().copied()),
+    ];
+    if acronyms
+        .iter()
+        .any(|acronym| acronym.len() > 1 && acronym == query)
+    {
+        return Some(MatchQuality::Acronym);
+    }
+
+    if sanitised_name.starts_with(query) {
+        return Some(MatchQuality::Prefix);
+    }
+
+    if query.len() >= 3
+        && (1..words.len()).any(|i| sanitise(&words[i..].join(" ")).starts_with(query))
+    {
+        return Some(MatchQuality::WordPrefix);
+    }
+
+    let query_len = query.chars().count();
+    let allowed_typos = max_typos(query_len);
+    if allowed_typos == 0 {
+        return None;
+    }
+    // compare against the whole name, and against the start of it in case they stopped typing
+    let name_chars = sanitised_name.chars().collect::<Vec<_>>();
+    let prefix_distances = (query_len.saturating_sub(1)..=query_len + 1)
+        .filter(|&len| len <= name_chars.len())
+        .map(|len| strsim::osa_distance(query, &name_chars[..len].iter().collect::<String>()));
+    [
+        strsim::osa_distance(query, &sanitised_base),
+        strsim::osa_distance(query, &sanitised_name),
+    ]
+    .into_iter()
+    .chain(prefix_distances)
+    .min()
+    .filter(|&typos| typos <= allowed_typos)
+    .map(MatchQuality::Typo)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn found_6(query: &str, era: Option<Era>) -> Option<(u32, Era)> {
+        match NationSearch::from_matches(Nations::search_6(query, era)) {
+            NationSearch::Found(nation) => Some((nation.id, nation.era)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn finds_nations_by_acronym_nickname_and_typo() {
+        assert_eq!(Some((69, Era::Middle)), found_6("TC", Some(Era::Middle)));
+        assert_eq!(Some((127, Era::Late)), found_6("LA R'lyeh", None));
+        assert_eq!(Some((116, Era::Late)), found_6("bogus", None));
+        assert_eq!(Some((68, Era::Middle)), found_6("BL", None));
+        assert_eq!(Some((109, Era::Late)), found_6("tc barb", None));
+        assert_eq!(Some((56, Era::Middle)), found_6("emerald", None));
+        assert_eq!(Some((116, Era::Late)), found_6("bograus", None));
+        assert_eq!(Some((115, Era::Late)), found_6("midgård", None));
+        assert_eq!(
+            Some((62, Era::Middle)),
+            found_6("pyréne", Some(Era::Middle))
+        );
+        assert_eq!(None, found_6("qwertyuiop", None));
+    }
+
+    #[test]
+    fn ambiguous_names_suggest_the_closest_nations() {
+        match NationSearch::from_matches(Nations::search_6("pythium", None)) {
+            NationSearch::Ambiguous(nations) => {
+                let ids = nations.iter().map(|nation| nation.id).collect::<Vec<_>>();
+                assert_eq!(vec![56, 98], ids);
+            }
+            other => panic!("expected ambiguous, got {:?}", other),
+        }
+        // an exact match beats a longer name starting the same way
+        assert_eq!(Some((7, Era::Early)), {
+            match NationSearch::from_matches(Nations::search("ulm", Some(Era::Early))) {
+                NationSearch::Found(nation) => Some((nation.id, nation.era)),
+                _ => None,
+            }
+        });
+    }
+}
@@ -18,63 +18,13 @@ impl Nations {
             .or_else(|| from_id_from_map(id, Era::Late, &DOM_6_LA_BY_ID))
     }
 
-    pub fn from_name_prefix(
-        name_prefix: &str,
-        option_era_filter: Option<Era>,
-    ) -> Vec<StaticNation> {
-        // todo: cow utils
-        let name_prefix = name_prefix.to_owned().to_lowercase().into();
-
-        // okay we want to try with this era, and if it doesn't work we forget about it and try that
-        let (name_prefix, option_specific_era) = extract_possible_nation_prefix(name_prefix);
-        let sanitised_prefix = sanitise_text(name_prefix);
-
-        let option_era = option_specific_era.or(option_era_filter);
-
-        let option_nations_by_id: Option<(&'static HashMap<u32, &'static str>, Era)> =
-            match option_era {
-                Some(Era::Early) => Some((&EA_NATIONS_BY_ID, Era::Early)),
-                Some(Era::Middle) => Some((&MA_NATIONS_BY_ID, Era::Middle)),
-                Some(Era::Late) => Some((&LA_NATIONS_BY_ID, Era::Late)),
-                None => None,
-            };
-
-        match option_nations_by_id {
-            Some((nations_by_id, era)) => {
-                find_nation_options_from_map(nations_by_id, sanitised_prefix.as_ref(), era)
-            }
-            // guess we just have to look in all 3
-            None => {
-                find_nation_options_from_map(
-                    &EA_NATIONS_BY_ID,
-                    sanitised_prefix.as_ref(),
-                    Era::Early,
-                )
-            }
-            .or_else(|| {
-                find_nation_options_from_map(
-                    &MA_NATIONS_BY_ID,
-                    sanitised_prefix.as_ref(),
-                    Era::Middle,
-                )
-            })
-            .or_else(|| {
-                find_nation_options_from_map(
-                    &LA_NATIONS_BY_ID,
-                    sanitised_prefix.as_ref(),
-                    Era::Late,
-                )
-            }),
-        }
-        .unwrap_or_default()
-    }
-
     pub fn from_name_prefix_6(
         name_prefix: &str,
         option_era_filter: Option<Era>,
     ) -> Vec<StaticNation> {
-        let name_prefix = sanitise_text(name_prefix.to_owned().to_lowercase().into());
+        let name_prefix = name_prefix.to_owned().to_lowercase().into();
         let (name_prefix, option_specific_era) = extract_possible_nation_prefix(name_prefix);
+        let name_prefix = sanitise_text(name_prefix);
         let option_era = option_specific_era.or(option_era_filter);
 
         match option_era {
@@ -92,7 +42,9 @@ impl Nations {
     }
 }
 
-fn extract_possible_nation_prefix(lowercase_name_prefix: Cow<str>) -> (Cow<str>, Option<Era>) {
+pub(super) fn extract_possible_nation_prefix(
+    lowercase_name_prefix: Cow<str>,
+) -> (Cow<str>, Option<Era>) {
     if lowercase_name_prefix.starts_with("ea ") {
         (cow_drop(lowercase_name_prefix, 3), Some(Era::Early))
     } else if lowercase_name_prefix.starts_with("ma ") {
@@ -107,8 +59,10 @@ fn extract_possible_nation_prefix(lowercase_name_prefix: Cow<str>) -> (Cow<str>,
 pub fn sanitise_text(mut lowercase_text: Cow<str>) -> Cow<str> {
     lowercase_text = cow_r_c(lowercase_text, '\'', "");
     lowercase_text = cow_r_c(lowercase_text, ',', "");
-    lowercase_text = cow_r_c(lowercase_text, 'è', "e");
-    lowercase_text = cow_r_c(lowercase_text, 'å', "a");
+    if !lowercase_text.is_ascii() {
+        // Pyrène, Midgård etc, and whatever accents people type on their phones
+        lowercase_text = Cow::Owned(deunicode::deunicode(&lowercase_text));
+    }
     lowercase_text = cow_r_c(lowercase_text, '-', "");
     lowercase_text = cow_r_c(lowercase_text, ' ', "");
     lowercase_text
@@ -419,27 +373,6 @@ fn from_id_from_map(id: u32, era: Era, map: &HashMap<u32, &'static str>) -> Opti
     })
 }
 
-fn find_nation_options_from_map(
-    nations_by_id: &HashMap<u32, &'static str>,
-    sanitised_prefix: &str,
-    era: Era,
-) -> Option<Vec<StaticNation>> {
-    // It's not like there's massive amounts of nations I guess, so linear is fine
-    let vec = nations_by_id
-        .iter()
-        .filter(|(_, name)| {
-            let sanitised_name = sanitise_text(name.to_owned().to_lowercase().into()); // todo: cow utils
-            sanitised_name.starts_with(sanitised_prefix)
-        })
-        .map(|(&id, name)| StaticNation { id, name, era })
-        .collect::<Vec<_>>();
-    if !vec.is_empty() {
-        Some(vec)
-    } else {
-        None
-    }
-}
-
 fn find_nation_options_from_map_6(
     nations_by_name: &HashMap<&'static str, (&'static str, u32)>,
     sanitised_prefix: &str,
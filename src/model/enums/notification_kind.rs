@@ -0,0 +1,38 @@
+use enum_primitive_derive::Primitive;
+use std::fmt;
+
+/// The different kinds of DM the bot can send a player about a game
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Primitive)]
+pub enum NotificationKind {
+    NewTurn = 1,
+    Uploading = 2,
+    Reminders = 3,
+    Stales = 4,
+    Defeats = 5,
+}
+
+impl NotificationKind {
+    pub fn from_string(string: &str) -> Option<NotificationKind> {
+        match string.to_lowercase().as_ref() {
+            "turn" | "turns" | "new-turn" => Some(NotificationKind::NewTurn),
+            "upload" | "uploading" => Some(NotificationKind::Uploading),
+            "reminder" | "reminders" => Some(NotificationKind::Reminders),
+            "stale" | "stales" => Some(NotificationKind::Stales),
+            "defeat" | "defeats" => Some(NotificationKind::Defeats),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match *self {
+            NotificationKind::NewTurn => "new turn",
+            NotificationKind::Uploading => "uploading",
+            NotificationKind::Reminders => "reminders",
+            NotificationKind::Stales => "stales",
+            NotificationKind::Defeats => "defeats",
+        };
+        f.write_str(text)
+    }
+}
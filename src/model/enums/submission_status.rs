@@ -25,4 +25,13 @@ impl SubmissionStatus {
             _ => SubmissionStatus::Unknown(x),
         }
     }
+
+    pub fn to_int(self) -> u8 {
+        match self {
+            SubmissionStatus::NotSubmitted => 0,
+            SubmissionStatus::PartiallySubmitted => 1,
+            SubmissionStatus::Submitted => 2,
+            SubmissionStatus::Unknown(x) => x,
+        }
+    }
 }
@@ -1,5 +1,5 @@
-use crate::model::nation::Nation;
-use chrono::{DateTime, Utc};
+use crate::model::{enums::NationStatus, nation::Nation};
+use chrono::{DateTime, Duration, Utc};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GameData {
@@ -7,4 +7,36 @@ pub struct GameData {
     pub nations: Vec<Option<Nation>>,
     pub turn: i32,
     pub turn_deadline: DateTime<Utc>,
+    /// The game is over, and whoever is still in it won
+    pub finished: bool,
+}
+impl GameData {
+    /// Nations that haven't been defeated
+    pub fn survivors(&self) -> impl Iterator<Item = &Nation> {
+        self.nations
+            .iter()
+            .flatten()
+            .filter(|nation| matches!(nation.status, NationStatus::Human | NationStatus::AI))
+    }
+
+    /// The direct connect protocol never says when a game is over, but once there's only one
+    /// nation left standing there's nobody else to fight. It's only a guess (a bad status is
+    /// enough to fool it) so the game isn't archived until its owner says so with `!finish`.
+    pub fn looks_finished(&self) -> bool {
+        !self.finished
+            && self.turn > 0
+            && self.nations.iter().flatten().count() > 1
+            && self.survivors().count() < 2
+    }
+
+    /// Whether anything players would notice has changed. The deadline is worked out from the
+    /// time remaining when we asked, so it drifts by a second or so between polls even when
+    /// nobody has touched the timer.
+    pub fn same_status_as(&self, other: &GameData) -> bool {
+        self.game_name == other.game_name
+            && self.nations == other.nations
+            && self.turn == other.turn
+            && self.finished == other.finished
+            && (self.turn_deadline - other.turn_deadline).abs() < Duration::minutes(1)
+    }
 }
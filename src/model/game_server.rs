@@ -1,11 +1,14 @@
 use crate::model::enums::Era;
-use serenity::model::id::UserId;
+use serenity::model::id::{GuildId, UserId};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GameServer {
-    pub alias: String,
+    pub alias: GameAlias,
     pub state: GameServerState,
     pub dom_version: u8,
+    /// Nobody but the players themselves gets told who is playing which nation
+    pub anonymous: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,3 +30,44 @@ pub struct LobbyState {
     pub player_count: i32,
     pub description: Option<String>,
 }
+
+/// Aliases are only unique within a discord server, so games are identified by both
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GameAlias {
+    /// `None` for games added in a DM, or before the bot could be in more than one server.
+    /// Those games can be seen from every server.
+    pub guild_id: Option<GuildId>,
+    pub name: String,
+}
+
+impl GameAlias {
+    pub fn new(guild_id: Option<GuildId>, name: &str) -> Self {
+        GameAlias {
+            guild_id,
+            name: name.to_owned(),
+        }
+    }
+
+    /// How the guild is stored in the db, where 0 means no guild
+    pub fn guild_key(&self) -> i64 {
+        self.guild_id.map_or(0, |guild_id| guild_id.get() as i64)
+    }
+
+    pub fn from_guild_key(guild_key: i64, name: String) -> Self {
+        GameAlias {
+            guild_id: (guild_key != 0).then(|| GuildId::new(guild_key as u64)),
+            name,
+        }
+    }
+
+    /// Whether a command used in this guild (or in a DM, for `None`) can see the game
+    pub fn is_visible_from(&self, option_guild_id: Option<GuildId>) -> bool {
+        self.guild_id.is_none() || option_guild_id.is_none() || self.guild_id == option_guild_id
+    }
+}
+
+impl fmt::Display for GameAlias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::model::enums::{Era, NationStatus, SubmissionStatus};
 use crate::model::game_data::GameData;
+use crate::model::game_server::GameAlias;
 use crate::model::nation::{BotNationIdentifier, GameNationIdentifier};
 use crate::model::player::Player;
 use crate::snek::SnekGameStatus;
@@ -17,9 +18,10 @@ pub struct CacheEntry {
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct GameDetails {
-    pub alias: String,
+    pub alias: GameAlias,
     pub owner: Option<UserId>,
     pub description: Option<String>,
+    pub anonymous: bool,
     pub nations: NationDetails,
     pub cache_entry: Option<CacheEntry>,
 }
@@ -156,6 +158,11 @@ pub struct LobbyDetails {
     pub players: Vec<LobbyPlayer>,
     pub era: Option<Era>,
     pub remaining_slots: u32,
+    /// Whoever gets the next free slots, in order
+    pub waitlist: Vec<LobbyPlayer>,
+    pub banned_nations: Vec<Cow<'static, str>>,
+    /// Empty if every nation that isn't banned is allowed
+    pub allowed_nations: Vec<Cow<'static, str>>,
 }
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct LobbyPlayer {
@@ -0,0 +1,13 @@
+/// Which nations a lobby's owner lets players have, e.g. for tournament rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NationRestrictions {
+    pub banned: Vec<u32>,
+    /// If this is empty, every nation that isn't banned is allowed
+    pub allowed: Vec<u32>,
+}
+impl NationRestrictions {
+    pub fn permits(&self, nation_id: u32) -> bool {
+        !self.banned.contains(&nation_id)
+            && (self.allowed.is_empty() || self.allowed.contains(&nation_id))
+    }
+}
@@ -1,7 +1,60 @@
+use crate::model::enums::NotificationKind;
 use serenity::model::id::UserId;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Player {
     pub discord_user_id: UserId,
     pub turn_notifications: bool,
+    /// What this player wants to hear about for the game they were looked up for,
+    /// i.e. any per-game overrides with `turn_notifications` as the fallback
+    pub notifications: NotificationSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationSettings {
+    pub new_turn: bool,
+    pub uploading: bool,
+    pub reminders: bool,
+    pub stales: bool,
+    pub defeats: bool,
+}
+impl NotificationSettings {
+    pub fn all(enabled: bool) -> Self {
+        NotificationSettings {
+            new_turn: enabled,
+            uploading: enabled,
+            reminders: enabled,
+            stales: enabled,
+            defeats: enabled,
+        }
+    }
+
+    pub fn get(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::NewTurn => self.new_turn,
+            NotificationKind::Uploading => self.uploading,
+            NotificationKind::Reminders => self.reminders,
+            NotificationKind::Stales => self.stales,
+            NotificationKind::Defeats => self.defeats,
+        }
+    }
+
+    pub fn show(&self) -> String {
+        let enabled = [
+            NotificationKind::NewTurn,
+            NotificationKind::Uploading,
+            NotificationKind::Reminders,
+            NotificationKind::Stales,
+            NotificationKind::Defeats,
+        ]
+        .into_iter()
+        .filter(|&kind| self.get(kind))
+        .map(|kind| kind.to_string())
+        .collect::<Vec<_>>();
+        match enabled.len() {
+            0 => "none".to_owned(),
+            5 => "all".to_owned(),
+            _ => enabled.join(", "),
+        }
+    }
 }
@@ -1,14 +1,20 @@
-#[repr(C)]
-#[derive(Debug, Clone, PartialEq)]
+/// The game info packet as sent by the server, see `server::packet` for the wire format.
+/// Fields we don't understand yet are kept so that they show up when debugging.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RawGameData {
-    pub a: [u8; 6],        // 6
-    pub game_name: String, // null terminated
-    pub c: [u8; 6],        // 4
-    pub d: i32,            // 4
-    // pub e: u8,  // 1
-    pub f: Vec<u8>, // ; 750],
-    pub g: u8,      // 1
-    pub h: u32,     // 4
-    pub i: u32,     // 4
-    pub j: u8,      // 1
+    pub unknown_prefix: [u8; 6],
+    pub game_name: String,
+    pub unknown_settings: [u8; 6],
+    /// Milliseconds until the next turn is hosted
+    pub turn_timer_ms: i32,
+    /// One entry per nation slot, see `NationStatus::from_int`
+    pub nation_statuses: Vec<u8>,
+    /// One entry per nation slot, see `SubmissionStatus::from_int`
+    pub submissions: Vec<u8>,
+    /// One entry per nation slot, 1 if that player is connected to the server
+    pub connections: Vec<u8>,
+    pub unknown_flag: u8,
+    /// -1 while the game is still being set up
+    pub turn: i32,
+    pub unknown_trailer: [u8; 5],
 }
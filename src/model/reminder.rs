@@ -0,0 +1,9 @@
+use serenity::model::id::UserId;
+
+/// A player wants to be pinged this long before the deadline if they haven't submitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reminder {
+    pub discord_user_id: UserId,
+    pub minutes_before: i32,
+    pub last_sent_turn: Option<i32>,
+}
@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+
+/// How turn checking has been getting on talking to a started game's server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ServerHealth {
+    /// How many checks in a row have failed, 0 if the last one worked
+    pub consecutive_failures: u32,
+    /// `None` if it has never answered since we started keeping track
+    pub last_success: Option<DateTime<Utc>>,
+}
+impl ServerHealth {
+    pub fn is_unreachable(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
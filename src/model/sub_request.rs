@@ -0,0 +1,15 @@
+use crate::model::{enums::Era, game_server::GameAlias, nation::BotNationIdentifier};
+use serenity::model::id::UserId;
+
+/// A nation in a started game that needs somebody new to play it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubRequest {
+    pub alias: GameAlias,
+    pub nation: BotNationIdentifier,
+    /// The lobby's era, or the nation's if the game was never a lobby
+    pub era: Option<Era>,
+    pub last_seen_turn: Option<i32>,
+    /// Whoever's dropping out. `None` for nations that nobody was registered as.
+    pub player: Option<UserId>,
+    pub anonymous: bool,
+}
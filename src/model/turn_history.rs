@@ -0,0 +1,14 @@
+use crate::model::nation::Nation;
+use chrono::{DateTime, Utc};
+
+/// The last state we saw a nation in during a single turn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NationTurnRecord {
+    pub turn: i32,
+    pub turn_deadline: DateTime<Utc>,
+    pub nation: Nation,
+    /// The first time we saw the nation as having submitted, if they ever did
+    pub submitted_at: Option<DateTime<Utc>>,
+    /// The last time we polled the server during this turn
+    pub recorded_at: DateTime<Utc>,
+}
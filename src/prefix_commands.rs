@@ -0,0 +1,77 @@
+//! Commands sent as messages starting with the prefix, e.g. `!details mygame`. Which
+//! commands there are and what arguments they take comes from `COMMANDS`, the same as
+//! for slash commands.
+
+use crate::commands::{
+    registry::{split_words, BotCommand, CommandError, PrivateCommandError},
+    servers::{find_command, CommandResponse},
+};
+use anyhow::anyhow;
+use log::*;
+use serenity::{all::CreateMessage, client::Context, model::channel::Message};
+
+pub async fn message(ctx: &Context, msg: &Message, prefix: &str) {
+    if msg.author.bot {
+        return;
+    }
+    let Some(text) = msg.content.strip_prefix(prefix) else {
+        return;
+    };
+    let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let Some(command) = find_command(name) else {
+        return;
+    };
+    info!("received message {:?}", msg);
+
+    if let Err(err) = run_command(ctx, msg, command, rest).await {
+        if let Some(private_err) = err.downcast_ref::<PrivateCommandError>() {
+            reply_privately(ctx, msg, private_err).await;
+            return;
+        }
+        let err = anyhow!(err);
+        let text = format!("ERROR: {:?}", err);
+        info!("command error: replying with '{}'", text);
+        let _ = msg.reply((&ctx.cache, ctx.http.as_ref()), &text).await;
+    }
+}
+
+/// Take the message down, and tell only its author what went wrong
+async fn reply_privately(ctx: &Context, msg: &Message, err: &PrivateCommandError) {
+    info!(
+        "private command error: deleting message and DMing '{}'",
+        err
+    );
+    if let Err(delete_err) = msg.delete((&ctx.cache, ctx.http.as_ref())).await {
+        error!("Could not delete message {}: {:?}", msg.id, delete_err);
+    }
+    let text = format!("ERROR: {}", err);
+    if let Err(dm_err) = msg
+        .author
+        .direct_message(&ctx.http, CreateMessage::default().content(text))
+        .await
+    {
+        error!("Could not DM {}: {:?}", msg.author.id, dm_err);
+    }
+}
+
+async fn run_command(
+    ctx: &Context,
+    msg: &Message,
+    command: &BotCommand,
+    rest: &str,
+) -> Result<(), CommandError> {
+    let args = command.args_from_words(split_words(rest))?;
+    let command_response = (command.run)(ctx, msg.channel_id, msg.author.id, args).await?;
+
+    match command_response {
+        CommandResponse::Reply(reply) => {
+            msg.reply((&ctx.cache, ctx.http.as_ref()), reply).await?;
+        }
+        CommandResponse::Embed(embed) => {
+            msg.channel_id
+                .send_message(&ctx.http, CreateMessage::default().embed(*embed))
+                .await?;
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::config;
 use crate::model::{
     enums::{NationStatus, SubmissionStatus},
     game_data::GameData,
@@ -5,18 +6,15 @@ use crate::model::{
     raw_game_data::RawGameData,
 };
 use anyhow::{anyhow, Context};
-use byteorder::{LittleEndian, ReadBytesExt};
 use chrono::Utc;
-use flate2::read::ZlibDecoder;
 use log::*;
+use packet::*;
 use scraper::{ElementRef, Html, Selector};
-use std::{
-    io::{BufRead, Cursor, Read},
-    str::FromStr,
-    time::Duration,
-};
+use std::{str::FromStr, time::Duration};
 use tokio::{io::AsyncWriteExt, time};
 
+pub mod packet;
+
 pub async fn get_game_data_async(
     server_address: &str,
     dom_version: u8,
@@ -24,12 +22,18 @@ pub async fn get_game_data_async(
     let option_url = url::Url::parse(server_address).ok();
     let is_html_page = option_url
         .as_ref()
-        .and_then(|url| url.path().split('.').last().map(|s| s.to_owned()))
+        .and_then(|url| url.path().split('.').next_back().map(|s| s.to_owned()))
         .is_some_and(|x| x == "html");
     if is_html_page {
-        // assume html pages are dom 6 only
+        // only dom6 has html status pages
+        if dom_version != 6 {
+            return Err(anyhow!(
+                "Status pages are only for Dominions 6 games, use the server's address and port for Dominions {}",
+                dom_version
+            ));
+        }
         let response = time::timeout(
-            Duration::from_secs(5),
+            config::timings().server_timeout,
             reqwest::get(option_url.expect("we already checked this")),
         )
         .await
@@ -42,14 +46,15 @@ pub async fn get_game_data_async(
         parse_status_html(Html::parse_document(&text))
     } else {
         // assume it's direct connect
+        let version = ProtocolVersion::from_dom_version(dom_version)?;
         let raw_data = time::timeout(
-            Duration::from_secs(5),
-            get_raw_game_data_async(server_address),
+            config::timings().server_timeout,
+            get_raw_game_data_async(server_address, version),
         )
         .await
         .context("retrieving info from the server timed out")?
         .context("cannot retrieve info from the server")?;
-        let game_data = interpret_raw_data(raw_data, dom_version)?;
+        let game_data = interpret_raw_data(raw_data, version)?;
         Ok(game_data)
     }
 }
@@ -78,6 +83,7 @@ fn parse_status_html(page: Html) -> anyhow::Result<GameData> {
             turn: -1, // TODO: this is a horrible hack to contort the dom6 data back into the dom5 form
             turn_deadline: Utc::now(),
             nations: vec![],
+            finished: false,
         });
     }
 
@@ -95,6 +101,7 @@ fn parse_status_html(page: Html) -> anyhow::Result<GameData> {
         nations,
         turn,
         turn_deadline,
+        finished,
     })
 }
 
@@ -106,7 +113,12 @@ fn parse_nation_row(td_selector: &Selector, finished: bool, row: ElementRef) ->
 
     let status = cells.next()?.inner_html();
     let (submitted, status) = if finished {
-        (SubmissionStatus::Submitted, NationStatus::DefeatedThisTurn)
+        // everybody still standing at the end won
+        match status.as_ref() {
+            "Eliminated" => (SubmissionStatus::Submitted, NationStatus::Defeated),
+            "Computer" => (SubmissionStatus::Submitted, NationStatus::AI),
+            _ => (SubmissionStatus::Submitted, NationStatus::Human),
+        }
     } else {
         match status.as_ref() {
             "Turn played" => (SubmissionStatus::Submitted, NationStatus::Human),
@@ -205,44 +217,48 @@ fn text_to_seconds(count: &str, unit: &str) -> anyhow::Result<i32> {
     Ok(mult * count)
 }
 
-fn interpret_raw_data(raw_data: RawGameData, dom_version: u8) -> anyhow::Result<GameData> {
+fn interpret_raw_data(raw_data: RawGameData, version: ProtocolVersion) -> anyhow::Result<GameData> {
     let turn_deadline = Utc::now()
-        .checked_add_signed(chrono::Duration::milliseconds(raw_data.d.into()))
+        .checked_add_signed(chrono::Duration::milliseconds(
+            raw_data.turn_timer_ms.into(),
+        ))
         .ok_or_else(|| anyhow!("invalid duration remaining in turn"))?;
 
     let mut game_data = GameData {
         game_name: raw_data.game_name,
         nations: vec![],
-        turn: raw_data.h as i32,
+        turn: raw_data.turn,
         turn_deadline,
+        finished: false,
     };
-    for i in 0..250 {
-        let status_num = raw_data.f[i];
+    for slot in 0..version.nation_slots() {
+        let status_num = raw_data.nation_statuses[slot];
         if status_num != 0 && status_num != 3 {
-            let submitted = raw_data.f[i + 250];
-            let connected = raw_data.f[i + 500];
-            let nation_id = (i - 1) as u32; // why -1? No fucking idea
-            let identifier = match dom_version {
-                5 => GameNationIdentifier::from_id(nation_id),
-                6 => GameNationIdentifier::from_id_6(nation_id),
-                _ => return Err(anyhow!("Dom {} lol", dom_version)),
+            let nation_id = version
+                .nation_id(slot)
+                .ok_or_else(|| anyhow!("Nation in unused slot {}", slot))?;
+            let identifier = match version {
+                ProtocolVersion::Dom5 => GameNationIdentifier::from_id(nation_id),
+                ProtocolVersion::Dom6 => GameNationIdentifier::from_id_6(nation_id),
             };
             let nation = Nation {
                 identifier,
                 status: NationStatus::from_int(status_num)
                     .ok_or_else(|| anyhow!("Unknown nation status {}", status_num))?,
-                submitted: SubmissionStatus::from_int(submitted),
-                connected: connected == 1,
+                submitted: SubmissionStatus::from_int(raw_data.submissions[slot]),
+                connected: raw_data.connections[slot] == 1,
             };
             game_data.nations.push(Some(nation));
         }
     }
     Ok(game_data)
 }
-async fn get_raw_game_data_async(server_address: &str) -> anyhow::Result<RawGameData> {
-    let buffer = call_server_for_info_async(server_address).await?;
-    let decompressed = decompress_server_info(&buffer)?;
-    let game_data = parse_data(&decompressed)?;
+async fn get_raw_game_data_async(
+    server_address: &str,
+    version: ProtocolVersion,
+) -> anyhow::Result<RawGameData> {
+    let payload = call_server_for_info_async(server_address).await?;
+    let game_data = decode_game_info(&payload, version)?;
     Ok(game_data)
 }
 async fn call_server_for_info_async(server_address: &str) -> anyhow::Result<Vec<u8>> {
@@ -250,150 +266,25 @@ async fn call_server_for_info_async(server_address: &str) -> anyhow::Result<Vec<
     debug!("call_server_for_info_async for {}", server_address);
     let mut stream = tokio::net::TcpStream::connect(server_address).await?;
 
-    // No idea where this means lol
-    // It's a modification of the original dom3/4 script, it got changed in patch 5.44
-    // https://steamcommunity.com/app/722060/discussions/0/1749024748627269322/?ctp=2#c1749024925634051868
-    let request = [
-        b'f', b'H', // wtr.write_u32::<LittleEndian>(1)?;
-        // wtr.write_u8(3)?;
-        0x07, 0x00, 0x00, 0x00, b'=', 0x1e, 0x02, 0x11, b'E', 0x05, 0x00,
-    ];
-
-    stream.write_all(&request).await?;
+    stream.write_all(&encode_frame(&GAME_INFO_REQUEST)).await?;
 
-    let mut header_buffer = [0; 6];
+    let mut header_buffer = [0; FRAME_HEADER_LEN];
     debug!("trying to receive");
-    let header_bytes_read = stream.read_exact(&mut header_buffer).await?;
+    stream.read_exact(&mut header_buffer).await?;
+    let (compression, body_len) = parse_frame_header(&header_buffer)?;
     debug!(
-        "received {} header bytes, byte 2 is {}",
-        header_bytes_read, header_buffer[2],
+        "received header {:?}, expecting {} body bytes",
+        header_buffer, body_len
     );
 
-    let mut body_buffer = vec![0; header_buffer[2] as usize];
-    let body_bytes_read = stream.read_exact(&mut body_buffer).await?;
-    debug!("received {} body bytes", body_bytes_read);
+    let mut body_buffer = vec![0; body_len];
+    stream.read_exact(&mut body_buffer).await?;
+    debug!("received {} body bytes", body_len);
 
     debug!("sending close");
-    let close_request = [
-        b'f', b'H', 0x01, 0x00, 0x00, 0x00, // ::<LittleEndian>(1)?;
-        11,
-    ];
-    stream.write_all(&close_request).await?;
-
-    let mut buffer = header_buffer.to_vec();
-    buffer.append(&mut body_buffer);
-
-    Ok(buffer)
-}
-fn decompress_server_info(raw: &[u8]) -> anyhow::Result<Vec<u8>> {
-    debug!("HEADER {:?}", &raw[0..10]);
-    if raw[1] == b'J' {
-        debug!("decompressing");
-        let mut decoder = ZlibDecoder::new(&raw[10..]);
-        let mut decompressed = vec![];
-        let _ = decoder.read_to_end(&mut decompressed)?;
-        Ok(decompressed)
-    } else {
-        debug!("No need to decompress");
-        Ok(raw[10..].to_vec())
-    }
-}
-fn parse_data(data: &[u8]) -> anyhow::Result<RawGameData> {
-    let len = data.len();
-    debug!("done: data.len(): {}", len);
-
-    let mut cursor = Cursor::new(data);
-    let mut a = [0u8; 6];
-    Read::read_exact(&mut cursor, &mut a)?;
-    debug!(
-        "cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    // debug!("A: {:#?}", a);
-    // debug!("Au32b: {}", u32::from_be_bytes([a[0], a[1]]));
-    // debug!("Au32l: {}", u32::from_le_bytes([a[0], a[1]]));
-    debug!("parsing name");
-    let mut game_name_bytes = vec![];
-    let read_bytes = cursor.read_until(0, &mut game_name_bytes)?;
-    debug!(
-        "read_bytes: {}, game_name_len: {}",
-        read_bytes,
-        game_name_bytes.len()
-    );
-
-    // remove null terminator
-    let game_name = String::from_utf8_lossy(&game_name_bytes[0..read_bytes - 1]).to_string();
-
-    debug!("game name: {}", game_name);
-    debug!(
-        "cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-
-    debug!(
-        "cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let mut c = [0u8; 6];
-    Read::read_exact(&mut cursor, &mut c)?;
-    debug!("reading timer");
-    let d = cursor.read_i32::<LittleEndian>()?;
-    debug!("timer value: {}", d);
-
-    // let e = cursor.read_u8()?;
+    stream.write_all(&encode_frame(&CLOSE_REQUEST)).await?;
 
-    debug!(
-        "f cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let mut f = vec![0u8; 750];
-    cursor.read_exact(&mut f)?;
-    debug!(
-        "g cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let g = cursor.read_u8()?;
-    debug!(
-        "h cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let h = cursor.read_u32::<LittleEndian>()?;
-    debug!(
-        "i cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let i = cursor.read_u32::<LittleEndian>()?;
-    debug!(
-        "j cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-    let j = cursor.read_u8()?;
-    debug!(
-        "finish cursor position: {}, cursor len: {}",
-        cursor.position(),
-        cursor.get_ref().len()
-    );
-
-    Ok(RawGameData {
-        a,
-        game_name,
-        c,
-        d,
-        // e: e,
-        f,
-        g,
-        h,
-        i,
-        j,
-    })
+    Ok(decode_frame_body(compression, &body_buffer)?)
 }
 
 #[cfg(test)]
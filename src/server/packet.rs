@@ -0,0 +1,513 @@
+//! The direct connect protocol that Dominions servers speak.
+//!
+//! Every message in either direction is a frame:
+//!   - `b'f'`
+//!   - `b'H'` for a plain body or `b'J'` for a zlib compressed one
+//!   - the length of the rest of the frame as a little endian u32
+//!   - the body, which for the frames we send is just the request
+//!
+//! The body of a frame the server sends back starts with another little endian u32. For
+//! compressed frames it's the decompressed length, followed by the zlib stream. Plain frames
+//! have it too, followed by the payload. We've never checked what it is on those, so it
+//! gets skipped.
+//!
+//! The payload of the game info response is:
+//!   - 6 bytes we don't understand
+//!   - the game name, null terminated
+//!   - 6 more bytes we don't understand
+//!   - the turn timer in milliseconds as a little endian i32
+//!   - the nation status, submission status and connection status of each nation slot
+//!   - 1 byte we don't understand
+//!   - the turn number as a little endian i32
+//!   - 5 bytes we don't understand
+//!
+//! Thanks to http://www.cs.helsinki.fi/u/aitakang/dom3_serverformat_notes
+
+use crate::model::raw_game_data::RawGameData;
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::ZlibDecoder;
+use log::warn;
+use std::{fmt, io::Read};
+
+pub const FRAME_HEADER_LEN: usize = 6;
+const FRAME_MAGIC: u8 = b'f';
+const PLAIN: u8 = b'H';
+const COMPRESSED: u8 = b'J';
+/// The game info is a couple of KB at most, anything this big is not a dominions server
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// It's a modification of the original dom3/4 request, it got changed in patch 5.44
+/// https://steamcommunity.com/app/722060/discussions/0/1749024748627269322/?ctp=2#c1749024925634051868
+pub const GAME_INFO_REQUEST: [u8; 7] = [b'=', 0x1e, 0x02, 0x11, b'E', 0x05, 0x00];
+pub const CLOSE_REQUEST: [u8; 1] = [11];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    Dom5,
+    Dom6,
+}
+
+impl ProtocolVersion {
+    pub fn from_dom_version(dom_version: u8) -> Result<Self, PacketError> {
+        match dom_version {
+            5 => Ok(ProtocolVersion::Dom5),
+            6 => Ok(ProtocolVersion::Dom6),
+            _ => Err(PacketError::UnsupportedVersion(dom_version)),
+        }
+    }
+
+    /// How many entries there are in each of the per-nation sections
+    pub fn nation_slots(self) -> usize {
+        match self {
+            ProtocolVersion::Dom5 | ProtocolVersion::Dom6 => 250,
+        }
+    }
+
+    /// Slot 0 is never a real nation, everything else is off by one
+    pub fn nation_id(self, slot: usize) -> Option<u32> {
+        match self {
+            ProtocolVersion::Dom5 | ProtocolVersion::Dom6 => {
+                slot.checked_sub(1).map(|id| id as u32)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PacketError {
+    UnsupportedVersion(u8),
+    BadMagic(u8),
+    UnknownCompression(u8),
+    FrameTooLarge(usize),
+    LengthMismatch {
+        what: &'static str,
+        declared: usize,
+        actual: usize,
+    },
+    Truncated {
+        field: &'static str,
+        needed: usize,
+        remaining: usize,
+    },
+    UnterminatedGameName,
+    Decompression(std::io::Error),
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PacketError::UnsupportedVersion(version) => {
+                write!(f, "Dominions {} is not supported", version)
+            }
+            PacketError::BadMagic(byte) => {
+                write!(f, "Packet started with {:#04x} instead of 'f'", byte)
+            }
+            PacketError::UnknownCompression(byte) => {
+                write!(f, "Unknown packet compression flag {:#04x}", byte)
+            }
+            PacketError::FrameTooLarge(len) => write!(f, "Packet claims to be {} bytes long", len),
+            PacketError::LengthMismatch {
+                what,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "{} should be {} bytes but was {} bytes",
+                what, declared, actual
+            ),
+            PacketError::Truncated {
+                field,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "Packet ended while reading {}: needed {} bytes but only {} were left",
+                field, needed, remaining
+            ),
+            PacketError::UnterminatedGameName => write!(f, "Game name was not null terminated"),
+            PacketError::Decompression(e) => write!(f, "Could not decompress packet: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PacketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PacketError::Decompression(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Plain,
+    Zlib,
+}
+
+/// Checks the first `FRAME_HEADER_LEN` bytes of a frame and returns how long the rest of it is
+pub fn parse_frame_header(header: &[u8]) -> Result<(Compression, usize), PacketError> {
+    if header.len() < FRAME_HEADER_LEN {
+        return Err(PacketError::Truncated {
+            field: "frame header",
+            needed: FRAME_HEADER_LEN,
+            remaining: header.len(),
+        });
+    }
+    if header[0] != FRAME_MAGIC {
+        return Err(PacketError::BadMagic(header[0]));
+    }
+    let compression = match header[1] {
+        PLAIN => Compression::Plain,
+        COMPRESSED => Compression::Zlib,
+        other => return Err(PacketError::UnknownCompression(other)),
+    };
+    let len = LittleEndian::read_u32(&header[2..FRAME_HEADER_LEN]) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(PacketError::FrameTooLarge(len));
+    }
+    Ok((compression, len))
+}
+
+/// Turns the body of a frame the server sent (everything after the header) into the payload
+pub fn decode_frame_body(compression: Compression, body: &[u8]) -> Result<Vec<u8>, PacketError> {
+    let mut reader = PacketReader::new(body);
+    match compression {
+        Compression::Plain => {
+            reader.u32("payload length")?;
+            Ok(reader.rest().to_vec())
+        }
+        Compression::Zlib => {
+            let declared = reader.u32("decompressed length")? as usize;
+            if declared > MAX_FRAME_LEN {
+                return Err(PacketError::FrameTooLarge(declared));
+            }
+            let mut decompressed = Vec::with_capacity(declared);
+            // read one byte more than we allow so that we notice if there's too much
+            ZlibDecoder::new(reader.rest())
+                .take(MAX_FRAME_LEN as u64 + 1)
+                .read_to_end(&mut decompressed)
+                .map_err(PacketError::Decompression)?;
+            if decompressed.len() > MAX_FRAME_LEN {
+                return Err(PacketError::FrameTooLarge(decompressed.len()));
+            }
+            // our test frames are built by hand, so until we've checked this against real
+            // servers, don't refuse to read a payload just because its length is unexpected
+            if decompressed.len() != declared {
+                warn!(
+                    "{}",
+                    PacketError::LengthMismatch {
+                        what: "decompressed payload",
+                        declared,
+                        actual: decompressed.len(),
+                    }
+                );
+            }
+            Ok(decompressed)
+        }
+    }
+}
+
+/// Wraps a request in a frame
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.push(FRAME_MAGIC);
+    frame.push(PLAIN);
+    let mut len = [0u8; 4];
+    LittleEndian::write_u32(&mut len, payload.len() as u32);
+    frame.extend_from_slice(&len);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Wraps a payload in an uncompressed frame, like the server does for the game info
+#[cfg(test)]
+pub fn encode_plain_frame(payload: &[u8]) -> Vec<u8> {
+    let mut body = vec![0u8; 4];
+    LittleEndian::write_u32(&mut body, payload.len() as u32);
+    body.extend_from_slice(payload);
+    encode_frame(&body)
+}
+
+/// Wraps a payload in a zlib compressed frame, like the server does for the game info
+#[cfg(test)]
+pub fn encode_compressed_frame(payload: &[u8]) -> Vec<u8> {
+    use flate2::{write::ZlibEncoder, Compression as Level};
+    use std::io::Write;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Level::default());
+    encoder
+        .write_all(payload)
+        .expect("writing to a vec can't fail");
+    let compressed = encoder.finish().expect("writing to a vec can't fail");
+
+    let mut body = vec![0u8; 4];
+    LittleEndian::write_u32(&mut body, payload.len() as u32);
+    body.extend_from_slice(&compressed);
+
+    let mut frame = encode_frame(&body);
+    frame[1] = COMPRESSED;
+    frame
+}
+
+/// The inverse of `decode_game_info`
+#[cfg(test)]
+pub fn encode_game_info(data: &RawGameData) -> Vec<u8> {
+    let mut payload = data.unknown_prefix.to_vec();
+    payload.extend_from_slice(data.game_name.as_bytes());
+    payload.push(0);
+    payload.extend_from_slice(&data.unknown_settings);
+    payload.extend_from_slice(&data.turn_timer_ms.to_le_bytes());
+    payload.extend_from_slice(&data.nation_statuses);
+    payload.extend_from_slice(&data.submissions);
+    payload.extend_from_slice(&data.connections);
+    payload.push(data.unknown_flag);
+    payload.extend_from_slice(&data.turn.to_le_bytes());
+    payload.extend_from_slice(&data.unknown_trailer);
+    payload
+}
+
+pub fn decode_game_info(
+    payload: &[u8],
+    version: ProtocolVersion,
+) -> Result<RawGameData, PacketError> {
+    let nation_slots = version.nation_slots();
+    let mut reader = PacketReader::new(payload);
+
+    let unknown_prefix = reader.array("prefix")?;
+    let game_name = reader.null_terminated_string()?;
+    let unknown_settings = reader.array("settings")?;
+    let turn_timer_ms = reader.i32("turn timer")?;
+    let nation_statuses = reader.bytes("nation statuses", nation_slots)?.to_vec();
+    let submissions = reader.bytes("submission statuses", nation_slots)?.to_vec();
+    let connections = reader.bytes("connection statuses", nation_slots)?.to_vec();
+    let unknown_flag = reader.array::<1>("flag")?[0];
+    let turn = reader.i32("turn")?;
+    let unknown_trailer = reader.array("trailer")?;
+
+    if !reader.rest().is_empty() {
+        // newer patches might add fields on the end, which is fine
+        log::debug!(
+            "{} unexpected bytes at the end of the game info for {}",
+            reader.rest().len(),
+            game_name
+        );
+    }
+
+    Ok(RawGameData {
+        unknown_prefix,
+        game_name,
+        unknown_settings,
+        turn_timer_ms,
+        nation_statuses,
+        submissions,
+        connections,
+        unknown_flag,
+        turn,
+        unknown_trailer,
+    })
+}
+
+/// Like a `Cursor` but the errors say which field was cut off
+struct PacketReader<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> PacketReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        PacketReader { remaining: data }
+    }
+
+    fn bytes(&mut self, field: &'static str, len: usize) -> Result<&'a [u8], PacketError> {
+        if self.remaining.len() < len {
+            return Err(PacketError::Truncated {
+                field,
+                needed: len,
+                remaining: self.remaining.len(),
+            });
+        }
+        let (bytes, rest) = self.remaining.split_at(len);
+        self.remaining = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self, field: &'static str) -> Result<[u8; N], PacketError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(field, N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self, field: &'static str) -> Result<u32, PacketError> {
+        Ok(LittleEndian::read_u32(self.bytes(field, 4)?))
+    }
+
+    fn i32(&mut self, field: &'static str) -> Result<i32, PacketError> {
+        Ok(LittleEndian::read_i32(self.bytes(field, 4)?))
+    }
+
+    fn null_terminated_string(&mut self) -> Result<String, PacketError> {
+        let len = self
+            .remaining
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(PacketError::UnterminatedGameName)?;
+        let name = String::from_utf8_lossy(&self.remaining[..len]).into_owned();
+        self.remaining = &self.remaining[len + 1..];
+        Ok(name)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.remaining
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DOM6_PLAYING: &[u8] = include_bytes!("fixtures/dom6_playing.bin");
+    const DOM6_PLAYING_PLAIN: &[u8] = include_bytes!("fixtures/dom6_playing_plain.bin");
+    const DOM5_SETUP: &[u8] = include_bytes!("fixtures/dom5_setup.bin");
+
+    fn decode_frame(frame: &[u8]) -> Result<Vec<u8>, PacketError> {
+        let (compression, len) = parse_frame_header(frame)?;
+        let body = &frame[FRAME_HEADER_LEN..];
+        if body.len() != len {
+            return Err(PacketError::LengthMismatch {
+                what: "frame body",
+                declared: len,
+                actual: body.len(),
+            });
+        }
+        decode_frame_body(compression, body)
+    }
+
+    fn decode(frame: &[u8], version: ProtocolVersion) -> Result<RawGameData, PacketError> {
+        decode_game_info(&decode_frame(frame)?, version)
+    }
+
+    #[test]
+    fn decodes_compressed_game_info() {
+        let data = decode(DOM6_PLAYING, ProtocolVersion::Dom6).unwrap();
+        assert_eq!("samog", data.game_name);
+        assert_eq!(12, data.turn);
+        assert_eq!(90_061_000, data.turn_timer_ms);
+        assert_eq!(250, data.nation_statuses.len());
+        // slots 6, 7 and 8 are people, 9 is an AI
+        assert_eq!(&[1, 1, 1, 2], &data.nation_statuses[6..10]);
+        assert_eq!(&[2, 1, 0, 2], &data.submissions[6..10]);
+        assert_eq!(&[1, 0, 0, 0], &data.connections[6..10]);
+        assert!(data.nation_statuses[10..].iter().all(|&status| status == 0));
+    }
+
+    #[test]
+    fn plain_and_compressed_frames_agree() {
+        assert_eq!(
+            decode(DOM6_PLAYING, ProtocolVersion::Dom6).unwrap(),
+            decode(DOM6_PLAYING_PLAIN, ProtocolVersion::Dom6).unwrap(),
+        );
+    }
+
+    #[test]
+    fn decodes_game_being_set_up() {
+        let data = decode(DOM5_SETUP, ProtocolVersion::Dom5).unwrap();
+        assert_eq!("curtains", data.game_name);
+        assert_eq!(-1, data.turn);
+        assert_eq!(0, data.turn_timer_ms);
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let truncated = &DOM6_PLAYING[..DOM6_PLAYING.len() - 10];
+        assert!(matches!(
+            decode(truncated, ProtocolVersion::Dom6),
+            Err(PacketError::LengthMismatch {
+                what: "frame body",
+                ..
+            })
+        ));
+        assert!(matches!(
+            decode(&DOM6_PLAYING[..3], ProtocolVersion::Dom6),
+            Err(PacketError::Truncated {
+                field: "frame header",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_payload_is_an_error() {
+        let payload = decode_frame(DOM6_PLAYING_PLAIN).unwrap();
+        let frame = encode_plain_frame(&payload[..payload.len() - 3]);
+        assert!(matches!(
+            decode(&frame, ProtocolVersion::Dom6),
+            Err(PacketError::Truncated {
+                field: "trailer",
+                ..
+            })
+        ));
+        let frame = encode_plain_frame(&payload[..30]);
+        assert!(matches!(
+            decode(&frame, ProtocolVersion::Dom6),
+            Err(PacketError::Truncated {
+                field: "nation statuses",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn wrong_decompressed_length_is_only_a_warning() {
+        let payload = decode_frame(DOM6_PLAYING).unwrap();
+        let mut frame = encode_compressed_frame(&payload);
+        let declared = FRAME_HEADER_LEN..FRAME_HEADER_LEN + 4;
+        LittleEndian::write_u32(&mut frame[declared], payload.len() as u32 + 5);
+        assert_eq!(payload, decode_frame(&frame).unwrap());
+    }
+
+    #[test]
+    fn unexpected_headers_are_errors() {
+        let mut bad_magic = DOM6_PLAYING.to_vec();
+        bad_magic[0] = b'g';
+        assert!(matches!(
+            decode(&bad_magic, ProtocolVersion::Dom6),
+            Err(PacketError::BadMagic(b'g'))
+        ));
+
+        let mut bad_compression = DOM6_PLAYING.to_vec();
+        bad_compression[1] = b'Z';
+        assert!(matches!(
+            decode(&bad_compression, ProtocolVersion::Dom6),
+            Err(PacketError::UnknownCompression(b'Z'))
+        ));
+
+        assert!(matches!(
+            ProtocolVersion::from_dom_version(4),
+            Err(PacketError::UnsupportedVersion(4))
+        ));
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let payload = decode_frame(DOM6_PLAYING).unwrap();
+        let data = decode_game_info(&payload, ProtocolVersion::Dom6).unwrap();
+        assert_eq!(payload, encode_game_info(&data));
+        assert_eq!(
+            payload,
+            decode_frame(&encode_compressed_frame(&payload)).unwrap()
+        );
+        assert_eq!(DOM6_PLAYING_PLAIN, &encode_plain_frame(&payload)[..]);
+    }
+
+    #[test]
+    fn request_frames() {
+        assert_eq!(
+            vec![b'f', b'H', 0x07, 0x00, 0x00, 0x00, b'=', 0x1e, 0x02, 0x11, b'E', 0x05, 0x00],
+            encode_frame(&GAME_INFO_REQUEST)
+        );
+        assert_eq!(
+            vec![b'f', b'H', 0x01, 0x00, 0x00, 0x00, 11],
+            encode_frame(&CLOSE_REQUEST)
+        );
+    }
+}
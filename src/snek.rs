@@ -1,7 +1,8 @@
+use crate::config;
 use anyhow::Context;
 use reqwest::StatusCode;
 use serde::{de, Deserialize, Deserializer};
-use std::{collections::HashMap, str::FromStr, time::Duration};
+use std::{collections::HashMap, str::FromStr};
 use tokio::time;
 use url::Url;
 
@@ -50,7 +51,7 @@ pub async fn snek_details_async(address: &str) -> anyhow::Result<Option<SnekGame
     let game_id = port - 30_000;
 
     let response = time::timeout(
-        Duration::from_secs(5),
+        config::timings().server_timeout,
         reqwest::get(&format!(
             "https://dom5.snek.earth/api/games/{}/status",
             game_id
@@ -0,0 +1,425 @@
+//! A fake Dominions server for testing the turn checking end to end.
+//!
+//! `FakeDominionsServer` listens on a local port and answers the game info request like
+//! a real server does over direct connect, and also serves the same game as a dom6 style
+//! `status.html` page. Tests script the game with `FakeGame` and then poll it as normal.
+
+use crate::{
+    db::DbConnection,
+    model::{
+        enums::{Era, NationStatus, Nations, SubmissionStatus},
+        game_data::GameData,
+        game_server::{GameAlias, GameServer, GameServerState, LobbyState},
+        game_state::CacheEntry,
+        raw_game_data::RawGameData,
+    },
+    server::packet::*,
+    DetailsCacheHandle, DetailsCacheKey,
+};
+use chrono::Utc;
+use serenity::{
+    model::id::UserId,
+    prelude::{RwLock, TypeMap},
+};
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+const DAY_MS: i32 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FakeNation {
+    pub status: NationStatus,
+    pub submitted: SubmissionStatus,
+    pub connected: bool,
+}
+
+/// The state of a game as the server sees it. Nations are keyed by nation id.
+#[derive(Debug, Clone)]
+pub struct FakeGame {
+    pub game_name: String,
+    /// -1 while the game is being set up
+    pub turn: i32,
+    pub turn_timer_ms: i32,
+    pub nations: BTreeMap<u32, FakeNation>,
+    /// The server hangs up on everybody without answering
+    pub crashed: bool,
+    /// Somebody has won, which only the status page says
+    pub finished: bool,
+    pending_defeats: Vec<u32>,
+}
+
+impl FakeGame {
+    pub fn new(game_name: &str) -> Self {
+        FakeGame {
+            game_name: game_name.to_owned(),
+            turn: -1,
+            turn_timer_ms: 0,
+            nations: BTreeMap::new(),
+            crashed: false,
+            finished: false,
+            pending_defeats: vec![],
+        }
+    }
+
+    /// Somebody has uploaded a pretender for this nation
+    pub fn with_human(mut self, nation_id: u32) -> Self {
+        self.nations.insert(
+            nation_id,
+            FakeNation {
+                status: NationStatus::Human,
+                submitted: SubmissionStatus::NotSubmitted,
+                connected: false,
+            },
+        );
+        self
+    }
+
+    pub fn with_ai(mut self, nation_id: u32) -> Self {
+        self.nations.insert(
+            nation_id,
+            FakeNation {
+                status: NationStatus::AI,
+                submitted: SubmissionStatus::Submitted,
+                connected: false,
+            },
+        );
+        self
+    }
+
+    fn nation_mut(&mut self, nation_id: u32) -> &mut FakeNation {
+        self.nations
+            .get_mut(&nation_id)
+            .unwrap_or_else(|| panic!("nation {} is not in the fake game", nation_id))
+    }
+
+    pub fn submit(&mut self, nation_id: u32) {
+        self.nation_mut(nation_id).submitted = SubmissionStatus::Submitted;
+    }
+
+    pub fn partially_submit(&mut self, nation_id: u32) {
+        self.nation_mut(nation_id).submitted = SubmissionStatus::PartiallySubmitted;
+    }
+
+    pub fn connect(&mut self, nation_id: u32, connected: bool) {
+        self.nation_mut(nation_id).connected = connected;
+    }
+
+    pub fn go_ai(&mut self, nation_id: u32) {
+        let nation = self.nation_mut(nation_id);
+        nation.status = NationStatus::AI;
+        nation.submitted = SubmissionStatus::Submitted;
+    }
+
+    /// The nation will show as defeated this turn once the next turn is hosted
+    pub fn defeat(&mut self, nation_id: u32) {
+        self.pending_defeats.push(nation_id);
+    }
+
+    /// Run the timer down, so that the next turn is hosted because time ran out
+    /// rather than because everybody submitted
+    pub fn time_out(&mut self) {
+        self.turn_timer_ms = 0;
+    }
+
+    /// Move on to the next turn (or the first one, if the game is being set up)
+    pub fn host_turn(&mut self) {
+        self.turn = if self.turn < 0 { 1 } else { self.turn + 1 };
+        self.turn_timer_ms = DAY_MS;
+        for nation in self.nations.values_mut() {
+            if nation.status == NationStatus::DefeatedThisTurn {
+                nation.status = NationStatus::Defeated;
+            }
+            nation.submitted = if nation.status == NationStatus::Human {
+                SubmissionStatus::NotSubmitted
+            } else {
+                SubmissionStatus::Submitted
+            };
+        }
+        for nation_id in std::mem::take(&mut self.pending_defeats) {
+            self.nation_mut(nation_id).status = NationStatus::DefeatedThisTurn;
+        }
+    }
+
+    pub fn to_raw_game_data(&self, version: ProtocolVersion) -> RawGameData {
+        let slots = version.nation_slots();
+        let mut nation_statuses = vec![0; slots];
+        let mut submissions = vec![0; slots];
+        let mut connections = vec![0; slots];
+        for (&nation_id, nation) in &self.nations {
+            let slot = (0..slots)
+                .find(|&slot| version.nation_id(slot) == Some(nation_id))
+                .unwrap_or_else(|| panic!("no slot for nation {}", nation_id));
+            nation_statuses[slot] = nation.status as u8;
+            submissions[slot] = nation.submitted.to_int();
+            connections[slot] = nation.connected as u8;
+        }
+        RawGameData {
+            unknown_prefix: [0x04, 0, 0, 0, 0, 0],
+            game_name: self.game_name.clone(),
+            unknown_settings: [0x02, 0, 0, 0, 0x01, 0],
+            turn_timer_ms: self.turn_timer_ms,
+            nation_statuses,
+            submissions,
+            connections,
+            unknown_flag: 0,
+            turn: self.turn,
+            unknown_trailer: [0; 5],
+        }
+    }
+
+    /// The game as shown on the dom6 status page
+    pub fn to_status_html(&self) -> String {
+        let mut rows = vec![];
+        if self.turn < 0 {
+            rows.push("<tr><td>Game is being setup</td></tr>".to_owned());
+        } else {
+            let total_minutes = self.turn_timer_ms / 60_000;
+            let time_left = if self.finished {
+                " (finished)".to_owned()
+            } else if total_minutes == 0 {
+                String::new()
+            } else {
+                format!(
+                    " (time left: {} hours and {} minutes)",
+                    total_minutes / 60,
+                    total_minutes % 60
+                )
+            };
+            rows.push(format!(
+                "<tr><td colspan=\"2\">{}, turn {}{}</td></tr>",
+                self.game_name, self.turn, time_left
+            ));
+            for (&nation_id, nation) in &self.nations {
+                let name = Nations::from_id_6(nation_id)
+                    .unwrap_or_else(|| panic!("{} is not a dom6 nation id", nation_id))
+                    .name;
+                let status = match (nation.status, nation.submitted) {
+                    (NationStatus::Defeated | NationStatus::DefeatedThisTurn, _) => "Eliminated",
+                    (NationStatus::Human, SubmissionStatus::Submitted) => "Turn played",
+                    (NationStatus::Human, SubmissionStatus::PartiallySubmitted) => {
+                        "Turn unfinished"
+                    }
+                    (NationStatus::Human, _) => "-",
+                    _ => "Computer",
+                };
+                rows.push(format!("<tr><td>{}</td><td>{}</td></tr>", name, status));
+            }
+        }
+        format!(
+            "<html><body><table>\n{}\n</table></body></html>",
+            rows.join("\n")
+        )
+    }
+}
+
+/// Listens on local ports until dropped
+pub struct FakeDominionsServer {
+    /// For direct connect, e.g. "127.0.0.1:1234"
+    pub address: String,
+    /// e.g. "http://127.0.0.1:1235/status.html"
+    pub status_url: String,
+    game: Arc<Mutex<FakeGame>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl FakeDominionsServer {
+    pub async fn start(
+        game: FakeGame,
+        version: ProtocolVersion,
+        compression: Compression,
+    ) -> std::io::Result<Self> {
+        let game = Arc::new(Mutex::new(game));
+
+        let direct_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = direct_listener.local_addr()?.to_string();
+        let direct_game = Arc::clone(&game);
+        let direct_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = direct_listener.accept().await {
+                let game = Arc::clone(&direct_game);
+                tokio::spawn(async move {
+                    // the client hanging up early is its own problem
+                    let _ = serve_direct_connection(stream, game, version, compression).await;
+                });
+            }
+        });
+
+        let http_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let status_url = format!("http://{}/status.html", http_listener.local_addr()?);
+        let http_game = Arc::clone(&game);
+        let http_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = http_listener.accept().await {
+                let game = Arc::clone(&http_game);
+                tokio::spawn(async move {
+                    let _ = serve_status_page(stream, game).await;
+                });
+            }
+        });
+
+        Ok(FakeDominionsServer {
+            address,
+            status_url,
+            game,
+            tasks: vec![direct_task, http_task],
+        })
+    }
+
+    /// Change the game. Don't hold on to this over an `await`.
+    pub fn game(&self) -> MutexGuard<'_, FakeGame> {
+        self.game.lock().expect("fake game lock poisoned")
+    }
+}
+
+impl Drop for FakeDominionsServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Requests are never compressed, and the body is just the request
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<Vec<u8>> {
+    let mut header = [0u8; FRAME_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    let (_, len) = parse_frame_header(&header)?;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+async fn serve_direct_connection(
+    mut stream: TcpStream,
+    game: Arc<Mutex<FakeGame>>,
+    version: ProtocolVersion,
+    compression: Compression,
+) -> anyhow::Result<()> {
+    loop {
+        let request = read_request(&mut stream).await?;
+        if game.lock().expect("fake game lock poisoned").crashed {
+            return Ok(());
+        }
+        if request[..] == GAME_INFO_REQUEST[..] {
+            let payload = encode_game_info(
+                &game
+                    .lock()
+                    .expect("fake game lock poisoned")
+                    .to_raw_game_data(version),
+            );
+            let response = match compression {
+                Compression::Plain => encode_plain_frame(&payload),
+                Compression::Zlib => encode_compressed_frame(&payload),
+            };
+            stream.write_all(&response).await?;
+        } else if request[..] == CLOSE_REQUEST[..] {
+            return Ok(());
+        } else {
+            return Err(anyhow::anyhow!("Unexpected request {:?}", request));
+        }
+    }
+}
+
+async fn serve_status_page(
+    mut stream: TcpStream,
+    game: Arc<Mutex<FakeGame>>,
+) -> anyhow::Result<()> {
+    // we don't care what they asked for, just wait until they've finished asking
+    let mut request = vec![];
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let body = game
+        .lock()
+        .expect("fake game lock poisoned")
+        .to_status_html();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// A fully migrated database in the temp dir, deleted again on drop
+pub struct TempDb {
+    pub db_conn: DbConnection,
+    path: PathBuf,
+}
+
+impl TempDb {
+    pub fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "dom5bot-test-{}-{}.db",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let db_conn = DbConnection::new(&path).expect("could not create test db");
+        TempDb { db_conn, path }
+    }
+
+    /// A dom6 lobby owned by user 1
+    pub fn add_lobby(&self, alias: &GameAlias, era: Era, player_count: i32) {
+        self.db_conn
+            .insert_game_server(&GameServer {
+                alias: alias.clone(),
+                state: GameServerState::Lobby(LobbyState {
+                    owner: UserId::new(1),
+                    era,
+                    player_count,
+                    description: None,
+                }),
+                dom_version: 6,
+                anonymous: false,
+            })
+            .unwrap();
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub fn empty_details_cache() -> DetailsCacheHandle {
+    let mut type_map = TypeMap::new();
+    type_map.insert::<DetailsCacheKey>(im::HashMap::new());
+    DetailsCacheHandle(Arc::new(RwLock::new(type_map)))
+}
+
+/// As if the game had just been polled and this is what its server said
+pub async fn cache_game_data(cache: &DetailsCacheHandle, alias: &GameAlias, game_data: GameData) {
+    let mut guard = cache.0.write().await;
+    guard
+        .get_mut::<DetailsCacheKey>()
+        .expect("the cache is always made with an entry")
+        .insert(
+            alias.clone(),
+            Box::new((
+                Utc::now(),
+                Some(CacheEntry {
+                    game_data,
+                    option_snek_state: None,
+                }),
+            )),
+        );
+}